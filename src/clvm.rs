@@ -0,0 +1,597 @@
+use std::fmt;
+
+use anyhow::{anyhow, Result};
+use chia::{
+    clvm_traits::FromClvm,
+    clvm_utils::tree_hash,
+    protocol::{Bytes, Bytes32},
+    puzzles::{
+        did::DidArgs,
+        nft::{
+            NftOwnershipLayerArgs, NftOwnershipLayerSolution, NftStateLayerArgs,
+            NftStateLayerSolution,
+        },
+        singleton::{SingletonArgs, SingletonSolution},
+    },
+};
+use chia_puzzles::{
+    DID_INNERPUZ_HASH, NFT_OWNERSHIP_LAYER_HASH, NFT_STATE_LAYER_HASH,
+    SINGLETON_TOP_LAYER_V1_1_HASH,
+};
+use chia_wallet_sdk::driver::Puzzle;
+use clvmr::{
+    reduction::Reduction,
+    run_program,
+    serde::{node_from_bytes_backrefs, node_to_bytes},
+    Allocator, ChiaDialect, NodePtr, SExp,
+};
+use serde::Serialize;
+
+pub const MAX_SIMULATION_COST: u64 = 11_000_000_000;
+
+/// The cost limit for running a recorded spend again to answer a GET. Anyone
+/// can ask for one, so it's kept well below what a whole block may cost.
+pub const MAX_REPLAY_COST: u64 = 1_000_000_000;
+
+const CREATE_COIN: u32 = 51;
+
+/// Running a program used up its cost limit before it finished.
+#[derive(Debug, Clone, Copy)]
+pub struct CostExceeded(pub u64);
+
+impl fmt::Display for CostExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "clvm error: cost exceeded {}", self.0)
+    }
+}
+
+impl std::error::Error for CostExceeded {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AggSigKind {
+    Parent,
+    Puzzle,
+    Amount,
+    PuzzleAmount,
+    ParentAmount,
+    ParentPuzzle,
+    Unsafe,
+    Me,
+}
+
+impl AggSigKind {
+    fn from_opcode(opcode: u32) -> Option<Self> {
+        Some(match opcode {
+            43 => Self::Parent,
+            44 => Self::Puzzle,
+            45 => Self::Amount,
+            46 => Self::PuzzleAmount,
+            47 => Self::ParentAmount,
+            48 => Self::ParentPuzzle,
+            49 => Self::Unsafe,
+            50 => Self::Me,
+            _ => return None,
+        })
+    }
+}
+
+/// A public key and the message it has to sign, before the coin details and
+/// network data that every kind but `unsafe` appends to it.
+#[derive(Debug, Clone, Serialize)]
+pub struct AggSigCondition {
+    pub kind: AggSigKind,
+    pub public_key: Bytes,
+    pub message: Bytes,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Simulation {
+    pub cost: u64,
+    pub conditions: Vec<RawCondition>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RawCondition {
+    pub opcode: u32,
+    pub args: Vec<Bytes>,
+}
+
+/// Runs a puzzle reveal against a solution without touching the database.
+pub fn simulate(puzzle_reveal: &[u8], solution: &[u8], max_cost: u64) -> Result<Simulation> {
+    let mut allocator = Allocator::new();
+    let (cost, conditions) = run(&mut allocator, puzzle_reveal, solution, max_cost)?;
+
+    Ok(Simulation {
+        cost,
+        conditions: conditions
+            .into_iter()
+            .map(|(opcode, args)| -> Result<RawCondition> {
+                Ok(RawCondition {
+                    opcode,
+                    args: args
+                        .into_iter()
+                        .map(|arg| -> Result<Bytes> {
+                            Ok(Bytes::new(node_to_bytes(&allocator, arg)?))
+                        })
+                        .collect::<Result<_>>()?,
+                })
+            })
+            .collect::<Result<_>>()?,
+    })
+}
+
+/// Runs a spend and keeps only the signatures it requires, in the order its
+/// conditions were output.
+pub fn agg_sig_conditions(
+    puzzle_reveal: &[u8],
+    solution: &[u8],
+    max_cost: u64,
+) -> Result<Vec<AggSigCondition>> {
+    let mut allocator = Allocator::new();
+    let (_, conditions) = run(&mut allocator, puzzle_reveal, solution, max_cost)?;
+
+    conditions
+        .into_iter()
+        .filter_map(|(opcode, args)| Some((AggSigKind::from_opcode(opcode)?, args)))
+        .map(|(kind, args)| -> Result<AggSigCondition> {
+            let [public_key, message, ..] = args[..] else {
+                return Err(anyhow!("invalid AGG_SIG condition"));
+            };
+
+            Ok(AggSigCondition {
+                kind,
+                public_key: Bytes::from_clvm(&allocator, public_key)
+                    .map_err(|error| anyhow!("invalid AGG_SIG public key: {error}"))?,
+                message: Bytes::from_clvm(&allocator, message)
+                    .map_err(|error| anyhow!("invalid AGG_SIG message: {error}"))?,
+            })
+        })
+        .collect()
+}
+
+fn run(
+    allocator: &mut Allocator,
+    puzzle_reveal: &[u8],
+    solution: &[u8],
+    max_cost: u64,
+) -> Result<(u64, Vec<(u32, Vec<NodePtr>)>)> {
+    let puzzle = node_from_bytes_backrefs(allocator, puzzle_reveal)
+        .map_err(|error| anyhow!("invalid puzzle reveal: {error}"))?;
+    let solution = node_from_bytes_backrefs(allocator, solution)
+        .map_err(|error| anyhow!("invalid solution: {error}"))?;
+
+    let Reduction(cost, output) =
+        run_program(allocator, &ChiaDialect::new(0), puzzle, solution, max_cost).map_err(
+            |error| match error.1.as_str() {
+                "cost exceeded" => anyhow::Error::new(CostExceeded(max_cost)),
+                message => anyhow!("clvm error: {message}"),
+            },
+        )?;
+
+    let conditions = Vec::<(u32, Vec<NodePtr>)>::from_clvm(&*allocator, output)
+        .map_err(|error| anyhow!("invalid conditions: {error}"))?;
+
+    Ok((cost, conditions))
+}
+
+impl Simulation {
+    /// The spend's cost split evenly between the coins it creates, or `None`
+    /// if it doesn't create any.
+    pub fn cost_per_created_coin(&self) -> Option<u64> {
+        let created = self
+            .conditions
+            .iter()
+            .filter(|condition| condition.opcode == CREATE_COIN)
+            .count() as u64;
+
+        self.cost.checked_div(created)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Uncurried {
+    /// The tree hash of the puzzle itself when it isn't curried.
+    pub mod_hash: Bytes32,
+    /// The serialized curried arguments, which are empty when the puzzle
+    /// isn't curried.
+    pub args: Vec<Bytes>,
+}
+
+pub fn puzzle_hash(puzzle_reveal: &[u8]) -> Result<Bytes32> {
+    let mut allocator = Allocator::new();
+
+    let puzzle = node_from_bytes_backrefs(&mut allocator, puzzle_reveal)
+        .map_err(|error| anyhow!("invalid puzzle reveal: {error}"))?;
+
+    Ok(tree_hash(&allocator, puzzle).into())
+}
+
+pub fn uncurry(puzzle_reveal: &[u8]) -> Result<Uncurried> {
+    let mut allocator = Allocator::new();
+
+    let puzzle = node_from_bytes_backrefs(&mut allocator, puzzle_reveal)
+        .map_err(|error| anyhow!("invalid puzzle reveal: {error}"))?;
+    let puzzle = Puzzle::parse(&allocator, puzzle);
+
+    let args = match puzzle.as_curried() {
+        Some(curried) => Vec::<NodePtr>::from_clvm(&allocator, curried.args)
+            .map_err(|error| anyhow!("invalid curried args: {error}"))?
+            .into_iter()
+            .map(|arg| -> Result<Bytes> { Ok(Bytes::new(node_to_bytes(&allocator, arg)?)) })
+            .collect::<Result<_>>()?,
+        None => Vec::new(),
+    };
+
+    Ok(Uncurried {
+        mod_hash: puzzle.mod_hash().into(),
+        args,
+    })
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct DidRecovery {
+    /// The tree hash of the DIDs that can approve a recovery, or `None` if
+    /// the DID can't be recovered.
+    pub recovery_list_hash: Option<Bytes32>,
+    pub num_verifications_required: u64,
+}
+
+/// The recovery arguments curried into the DID inner puzzle of a singleton's
+/// puzzle reveal, or `None` if the puzzle isn't a DID.
+pub fn did_recovery(puzzle_reveal: &[u8]) -> Result<Option<DidRecovery>> {
+    let mut allocator = Allocator::new();
+
+    let puzzle = node_from_bytes_backrefs(&mut allocator, puzzle_reveal)
+        .map_err(|error| anyhow!("invalid puzzle reveal: {error}"))?;
+    let puzzle = Puzzle::parse(&allocator, puzzle);
+
+    let Some(singleton) = puzzle
+        .as_curried()
+        .filter(|_| puzzle.mod_hash().to_bytes() == SINGLETON_TOP_LAYER_V1_1_HASH)
+    else {
+        return Ok(None);
+    };
+
+    let inner_puzzle = SingletonArgs::<Puzzle>::from_clvm(&allocator, singleton.args)
+        .map_err(|error| anyhow!("invalid singleton args: {error}"))?
+        .inner_puzzle;
+
+    let Some(did) = inner_puzzle
+        .as_curried()
+        .filter(|_| inner_puzzle.mod_hash().to_bytes() == DID_INNERPUZ_HASH)
+    else {
+        return Ok(None);
+    };
+
+    let args = DidArgs::<NodePtr, NodePtr>::from_clvm(&allocator, did.args)
+        .map_err(|error| anyhow!("invalid DID args: {error}"))?;
+
+    Ok(Some(DidRecovery {
+        recovery_list_hash: args.recovery_list_hash,
+        num_verifications_required: args.num_verifications_required,
+    }))
+}
+
+/// The `-10` condition an NFT's inner puzzle outputs to move it to a new
+/// owner DID, which the ownership layer consumes.
+const NFT_TRANSFER: u8 = 0xf6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NftOwner {
+    /// The launcher id of the owner DID, or `None` both for NFTs without an
+    /// ownership layer and for ones without an owner.
+    pub did_id: Option<Bytes32>,
+}
+
+/// The NFT's owner DID after its spend, from the ownership layer of the
+/// puzzle reveal. The spend's inner puzzle is run to find a transfer, which
+/// takes the place of the owner curried into the reveal. Returns `Ok(None)`
+/// if the puzzle isn't an NFT.
+pub fn nft_owner(puzzle_reveal: &[u8], solution: &[u8], max_cost: u64) -> Result<Option<NftOwner>> {
+    let mut allocator = Allocator::new();
+
+    let puzzle = node_from_bytes_backrefs(&mut allocator, puzzle_reveal)
+        .map_err(|error| anyhow!("invalid puzzle reveal: {error}"))?;
+    let solution = node_from_bytes_backrefs(&mut allocator, solution)
+        .map_err(|error| anyhow!("invalid solution: {error}"))?;
+    let puzzle = Puzzle::parse(&allocator, puzzle);
+
+    let Some(singleton) = puzzle
+        .as_curried()
+        .filter(|_| puzzle.mod_hash().to_bytes() == SINGLETON_TOP_LAYER_V1_1_HASH)
+    else {
+        return Ok(None);
+    };
+
+    let state_layer = SingletonArgs::<Puzzle>::from_clvm(&allocator, singleton.args)
+        .map_err(|error| anyhow!("invalid singleton args: {error}"))?
+        .inner_puzzle;
+    let solution = SingletonSolution::<NodePtr>::from_clvm(&allocator, solution)
+        .map_err(|error| anyhow!("invalid singleton solution: {error}"))?
+        .inner_solution;
+
+    let Some(state_args) = state_layer
+        .as_curried()
+        .filter(|_| state_layer.mod_hash().to_bytes() == NFT_STATE_LAYER_HASH)
+    else {
+        return Ok(None);
+    };
+
+    let ownership_layer = Puzzle::parse(
+        &allocator,
+        NftStateLayerArgs::<NodePtr, NodePtr>::from_clvm(&allocator, state_args.args)
+            .map_err(|error| anyhow!("invalid NFT state layer args: {error}"))?
+            .inner_puzzle,
+    );
+    let solution = NftStateLayerSolution::<NodePtr>::from_clvm(&allocator, solution)
+        .map_err(|error| anyhow!("invalid NFT state layer solution: {error}"))?
+        .inner_solution;
+
+    let Some(ownership_args) = ownership_layer
+        .as_curried()
+        .filter(|_| ownership_layer.mod_hash().to_bytes() == NFT_OWNERSHIP_LAYER_HASH)
+    else {
+        return Ok(Some(NftOwner { did_id: None }));
+    };
+
+    let ownership_args =
+        NftOwnershipLayerArgs::<NodePtr, NodePtr>::from_clvm(&allocator, ownership_args.args)
+            .map_err(|error| anyhow!("invalid NFT ownership layer args: {error}"))?;
+    let solution = NftOwnershipLayerSolution::<NodePtr>::from_clvm(&allocator, solution)
+        .map_err(|error| anyhow!("invalid NFT ownership layer solution: {error}"))?
+        .inner_solution;
+
+    let Reduction(_, output) = run_program(
+        &mut allocator,
+        &ChiaDialect::new(0),
+        ownership_args.inner_puzzle,
+        solution,
+        max_cost,
+    )
+    .map_err(|error| anyhow!("clvm error: {}", error.1))?;
+
+    let conditions = Vec::<Vec<NodePtr>>::from_clvm(&allocator, output)
+        .map_err(|error| anyhow!("invalid conditions: {error}"))?;
+
+    for condition in conditions {
+        let [opcode, new_owner, ..] = condition[..] else {
+            continue;
+        };
+
+        if matches!(allocator.sexp(opcode), SExp::Atom)
+            && allocator.atom(opcode).as_ref() == [NFT_TRANSFER]
+        {
+            let did_id = Option::<Bytes32>::from_clvm(&allocator, new_owner)
+                .map_err(|error| anyhow!("invalid NFT transfer owner: {error}"))?;
+
+            return Ok(Some(NftOwner { did_id }));
+        }
+    }
+
+    Ok(Some(NftOwner {
+        did_id: ownership_args.current_owner,
+    }))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Disassembly {
+    pub text: String,
+    /// Whether the text was cut off at the length limit.
+    pub truncated: bool,
+}
+
+enum Step {
+    Node(NodePtr),
+    Rest(NodePtr),
+}
+
+/// Renders serialized CLVM as an s-expression, stopping once the text exceeds
+/// `max_len` bytes. Atoms are written as quoted strings when they're printable,
+/// as integers when they're short, and as hex otherwise. Operators aren't
+/// named, since an atom's meaning depends on where it's evaluated.
+pub fn disassemble(program: &[u8], max_len: usize) -> Result<Disassembly> {
+    let mut allocator = Allocator::new();
+
+    let node = node_from_bytes_backrefs(&mut allocator, program)
+        .map_err(|error| anyhow!("invalid program: {error}"))?;
+
+    let mut text = String::new();
+
+    // An explicit stack, since deeply nested programs would overflow the real one.
+    let mut stack = vec![Step::Node(node)];
+
+    while let Some(step) = stack.pop() {
+        // The text is at most `max_len` bytes here, so an atom is only written
+        // up to where it would be cut off.
+        let room = max_len + 1 - text.len();
+
+        match step {
+            Step::Node(node) => match allocator.sexp(node) {
+                SExp::Atom => write_atom(&mut text, allocator.atom(node).as_ref(), room),
+                SExp::Pair(first, rest) => {
+                    text.push('(');
+                    stack.push(Step::Rest(rest));
+                    stack.push(Step::Node(first));
+                }
+            },
+            Step::Rest(node) => match allocator.sexp(node) {
+                SExp::Atom => {
+                    let atom = allocator.atom(node);
+
+                    if !atom.as_ref().is_empty() {
+                        text.push_str(" . ");
+                        write_atom(&mut text, atom.as_ref(), room);
+                    }
+
+                    text.push(')');
+                }
+                SExp::Pair(first, rest) => {
+                    text.push(' ');
+                    stack.push(Step::Rest(rest));
+                    stack.push(Step::Node(first));
+                }
+            },
+        }
+
+        if text.len() > max_len {
+            let mut end = max_len;
+
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+
+            text.truncate(end);
+            text.push_str("...");
+
+            return Ok(Disassembly {
+                text,
+                truncated: true,
+            });
+        }
+    }
+
+    Ok(Disassembly {
+        text,
+        truncated: false,
+    })
+}
+
+/// Writes the atom, or at least its first `room` bytes of text when it's a
+/// string or hex that won't fit.
+fn write_atom(text: &mut String, atom: &[u8], room: usize) {
+    if atom.is_empty() {
+        text.push_str("()");
+        return;
+    }
+
+    let printable = atom.len() > 1
+        && atom
+            .iter()
+            .all(|&byte| (0x20..=0x7e).contains(&byte) && byte != b'"');
+
+    if printable {
+        let atom = &atom[..atom.len().min(room)];
+
+        text.push('"');
+        text.push_str(std::str::from_utf8(atom).unwrap());
+        text.push('"');
+        return;
+    }
+
+    let canonical = atom.len() == 1
+        || !((atom[0] == 0x00 && atom[1] & 0x80 == 0) || (atom[0] == 0xff && atom[1] & 0x80 != 0));
+
+    if atom.len() <= 8 && canonical {
+        let mut value = if atom[0] & 0x80 != 0 { -1i64 } else { 0 };
+
+        for &byte in atom {
+            value = (value << 8) | i64::from(byte);
+        }
+
+        text.push_str(&value.to_string());
+        return;
+    }
+
+    text.push_str("0x");
+    text.push_str(&hex::encode(&atom[..atom.len().min(room.div_ceil(2))]));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn atom_text(atom: &[u8]) -> String {
+        let mut text = String::new();
+        write_atom(&mut text, atom, usize::MAX);
+        text
+    }
+
+    #[test]
+    fn atoms_are_written_as_strings_integers_or_hex() {
+        assert_eq!(atom_text(&[]), "()");
+        assert_eq!(atom_text(b"hello"), "\"hello\"");
+        assert_eq!(atom_text(b"a"), "97");
+        assert_eq!(atom_text(&[0x80]), "-128");
+        assert_eq!(atom_text(&[0x00, 0x80]), "128");
+        assert_eq!(atom_text(&[0xff, 0x7f]), "-129");
+        assert_eq!(atom_text(&[0x00, 0x01]), "0x0001");
+        assert_eq!(
+            atom_text(b"say \"hi\" twice"),
+            "0x7361792022686922207477696365"
+        );
+        assert_eq!(atom_text(&[0x01; 9]), "0x010101010101010101");
+    }
+
+    #[test]
+    fn atoms_are_cut_to_the_room_left() {
+        let mut text = String::new();
+        write_atom(&mut text, &[0xab; 1000], 5);
+        assert_eq!(text, "0xababab");
+
+        let mut text = String::new();
+        write_atom(&mut text, &[b'a'; 1000], 3);
+        assert_eq!(text, "\"aaa\"");
+    }
+
+    #[test]
+    fn disassembles_lists_and_pairs() {
+        let list = disassemble(&[0xff, 0x01, 0xff, 0x02, 0x80], 100).unwrap();
+        assert_eq!(list.text, "(1 2)");
+        assert!(!list.truncated);
+
+        let pair = disassemble(&[0xff, 0x01, 0x02], 100).unwrap();
+        assert_eq!(pair.text, "(1 . 2)");
+    }
+
+    #[test]
+    fn long_output_is_truncated_at_the_limit() {
+        let mut allocator = Allocator::new();
+        let atom = allocator.new_atom(&[0xab; 100_000]).unwrap();
+        let program = node_to_bytes(&allocator, atom).unwrap();
+
+        let disassembly = disassemble(&program, 100).unwrap();
+
+        assert!(disassembly.truncated);
+        assert_eq!(disassembly.text.len(), 103);
+        assert!(disassembly.text.starts_with("0xabab"));
+        assert!(disassembly.text.ends_with("..."));
+
+        // Output that ends exactly at the limit isn't cut.
+        let disassembly = disassemble(&[0xff, 0x01, 0xff, 0x02, 0x80], 5).unwrap();
+        assert_eq!(disassembly.text, "(1 2)");
+        assert!(!disassembly.truncated);
+
+        let disassembly = disassemble(&[0xff, 0x01, 0xff, 0x02, 0x80], 4).unwrap();
+        assert_eq!(disassembly.text, "(1 2...");
+        assert!(disassembly.truncated);
+    }
+
+    #[test]
+    fn puzzles_that_arent_dids_have_no_recovery() {
+        assert!(did_recovery(&[0x01]).unwrap().is_none());
+        assert!(did_recovery(&[0xff]).is_err());
+    }
+
+    #[test]
+    fn puzzles_that_arent_nfts_have_no_owner() {
+        assert!(nft_owner(&[0x01], &[0x80], MAX_SIMULATION_COST)
+            .unwrap()
+            .is_none());
+        assert!(nft_owner(&[0xff], &[0x80], MAX_SIMULATION_COST).is_err());
+    }
+
+    #[test]
+    fn running_past_the_cost_limit_is_reported_as_such() {
+        // (+ (q . 1) (q . 2))
+        let program = [
+            0xff, 0x10, 0xff, 0xff, 0x01, 0x01, 0xff, 0xff, 0x01, 0x02, 0x80,
+        ];
+
+        let error = simulate(&program, &[0x80], 1).unwrap_err();
+        assert!(error.downcast_ref::<CostExceeded>().is_some());
+
+        let error = simulate(&[0xff], &[0x80], MAX_REPLAY_COST).unwrap_err();
+        assert!(error.downcast_ref::<CostExceeded>().is_none());
+    }
+}
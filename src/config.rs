@@ -1,8 +1,10 @@
 use std::{env, fs, path::PathBuf};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
 
+use crate::db::{Durability, IndexKind, IntegrityCheck};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     #[serde(default = "default_db_path")]
@@ -17,6 +19,202 @@ pub struct Config {
     pub port: u16,
     #[serde(default = "default_batch_size")]
     pub batch_size: u32,
+    #[serde(default = "default_block_pagination")]
+    pub block_pagination: PaginationConfig,
+    #[serde(default = "default_coin_pagination")]
+    pub coin_pagination: PaginationConfig,
+    /// Pages of spends are kept small, since each includes its puzzle reveal and solution.
+    #[serde(default = "default_spend_pagination")]
+    pub spend_pagination: PaginationConfig,
+    /// Requests taking at least this many milliseconds are logged, or never if `0`.
+    #[serde(default = "default_slow_request_threshold_ms")]
+    pub slow_request_threshold_ms: u64,
+    /// Whether coin responses include the puzzle reveal and solution unless overridden.
+    #[serde(default = "default_include_spend")]
+    pub include_spend: bool,
+    /// Whether hashes and bytes are written as `0x` prefixed hex unless a
+    /// request asks otherwise with `?prefix=0x` or `?prefix=none`.
+    #[serde(default = "default_hex_prefix")]
+    pub hex_prefix: bool,
+    /// Responses larger than this are rejected with a 413, and paginated
+    /// lists are cut short to fit within it.
+    #[serde(default = "default_max_response_bytes")]
+    pub max_response_bytes: usize,
+    /// How many range scans can read from the database at once before the
+    /// rest wait their turn. It must be at least `1`.
+    #[serde(default = "default_max_concurrent_reads")]
+    pub max_concurrent_reads: usize,
+    /// The size of the prefix bloom filters in the index column families'
+    /// SST files, or `0` to leave them out. Only files written after a change
+    /// pick it up.
+    #[serde(default = "default_index_bloom_bits_per_key")]
+    pub index_bloom_bits_per_key: u32,
+    /// The size of the zstd dictionary trained for each bottommost SST file
+    /// of coin spends, or `0` to compress without one. Existing files are
+    /// only rewritten with a dictionary by `compact-spends`.
+    #[serde(default)]
+    pub spend_dictionary_bytes: u32,
+    /// How many blocks below the peak a block has to be before it's treated
+    /// as finalized, since reorgs deeper than this are not expected.
+    #[serde(default = "default_reorg_buffer")]
+    pub reorg_buffer: u32,
+    /// The index isn't reported as synced while the latest transaction block
+    /// is older than this, since the node is probably stalled. `0` disables
+    /// the check.
+    #[serde(default = "default_max_peak_lag_seconds")]
+    pub max_peak_lag_seconds: u64,
+    /// POST bodies nested more deeply than this are rejected before parsing.
+    #[serde(default = "default_max_json_depth")]
+    pub max_json_depth: usize,
+    /// The bech32m prefix of encoded addresses, such as `txch` for testnet.
+    #[serde(default = "default_address_prefix")]
+    pub address_prefix: String,
+    /// Block events are sent to subscribers at most this often, always with
+    /// the latest block, so that catching up doesn't flood them. `0` sends
+    /// every block.
+    #[serde(default = "default_event_coalesce_ms")]
+    pub event_coalesce_ms: u64,
+    /// Whether a panicking handler gets a 500 response instead of dropping
+    /// the connection.
+    #[serde(default = "default_catch_panics")]
+    pub catch_panics: bool,
+    /// The longest the indexer waits between retries while polling the node
+    /// keeps failing. Retries start at the poll interval and double.
+    #[serde(default = "default_node_retry_max_seconds")]
+    pub node_retry_max_seconds: u64,
+    /// Whether JSON responses are pretty printed unless a request asks
+    /// otherwise with `?pretty=true` or `?pretty=false`.
+    #[serde(default)]
+    pub pretty: bool,
+    /// How often single-flight entries that no request is waiting on anymore
+    /// are removed. `0` never removes them.
+    #[serde(default = "default_single_flight_prune_seconds")]
+    pub single_flight_prune_seconds: u64,
+    /// Internal errors and caught panics are reported to this Sentry DSN when
+    /// built with the `sentry` feature. Nothing is reported when it's unset.
+    #[serde(default)]
+    pub sentry_dsn: Option<String>,
+    /// How many of the newest blocks are kept in memory, so that the first
+    /// page of `/blocks?reverse=true` is served without reading the database.
+    /// `0` turns this off.
+    #[serde(default = "default_recent_blocks")]
+    pub recent_blocks: usize,
+    /// How many requests a single client IP can have in flight at once before
+    /// the rest are refused with a 429, or `0` for no limit. This is on top of
+    /// `max_concurrent_reads`, which is shared by every client. Behind a
+    /// reverse proxy every request comes from the proxy's IP.
+    #[serde(default)]
+    pub max_requests_per_ip: usize,
+    /// How many blocks are written in a single commit while catching up, with
+    /// the peak moved once at the end. They're still fetched `batch_size` at a
+    /// time. Anything up to `batch_size`, including the default of `0`, commits
+    /// each fetched batch on its own.
+    #[serde(default)]
+    pub commit_blocks: u32,
+    /// Commits the blocks fetched so far once this long has passed since the
+    /// commit's first fetch, even if `commit_blocks` hasn't been reached. `0`
+    /// doesn't limit it.
+    #[serde(default)]
+    pub commit_interval_ms: u64,
+    /// Serves reads from the database another instance is syncing into,
+    /// instead of syncing. `db_path` is opened as a RocksDB secondary, and
+    /// nothing is ever written to it, so the node's database, certificates
+    /// and admin reindexing aren't used. Responses trail the syncing instance
+    /// by up to `catch_up_interval_ms` (plus however long replaying its writes
+    /// takes), and two instances can briefly disagree on the peak. It counts
+    /// as synced once it has caught up, so `max_peak_lag_seconds` is what
+    /// tells a stalled syncing instance apart.
+    #[serde(default)]
+    pub read_only: bool,
+    /// Where a read-only instance keeps its own logs, which has to be a
+    /// different directory for each instance. Defaults to `db_path` with a
+    /// `.secondary` extension.
+    #[serde(default)]
+    pub secondary_path: Option<PathBuf>,
+    /// How often a read-only instance replays the syncing instance's writes.
+    #[serde(default = "default_catch_up_interval_ms")]
+    pub catch_up_interval_ms: u64,
+    /// Route paths that aren't mounted, written exactly as they are in
+    /// `router`, such as `/ws/events` or `/coins/id/{coin_id}/proof`.
+    /// Requests to them fall through to the 404 fallback.
+    #[serde(default)]
+    pub disabled_routes: Vec<String>,
+    /// How many of the most recent blocks (and the coins created in them) to
+    /// read into the cache before serving. Disabled by default, since it
+    /// lengthens startup.
+    #[serde(default)]
+    pub warm_up_blocks: u32,
+    /// The most coins a graph walk (lineage or a timeline's children) visits
+    /// in one request, which requests can lower but not raise.
+    #[serde(default = "default_max_nodes")]
+    pub max_nodes: u32,
+    /// The bearer token for `/admin` endpoints, which are refused if unset.
+    #[serde(default)]
+    pub admin_token: Option<String>,
+    #[serde(default)]
+    pub websocket: WebSocketConfig,
+    /// The URL the API is reachable at from outside, such as
+    /// `https://example.com/api` behind a proxy that rewrites paths. Links to
+    /// other pages are built on it, or are relative to the request if unset.
+    #[serde(default)]
+    pub public_base_url: Option<String>,
+    /// How long to wait for the database to flush on shutdown before exiting
+    /// anyway. Batches committed with `Durability::Async` since the WAL was
+    /// last synced may then be lost, and are synced again from the node.
+    #[serde(default = "default_shutdown_flush_timeout_ms")]
+    pub shutdown_flush_timeout_ms: u64,
+    #[serde(default)]
+    pub durability: Durability,
+    #[serde(default)]
+    pub integrity_check: IntegrityCheck,
+    /// Secondary indexes to maintain while syncing. The height indexes are
+    /// always maintained. An index that's enabled after blocks were synced
+    /// without it is missing those entries until it's rebuilt with
+    /// `rebuild-index`, which refuses to rebuild a disabled index.
+    #[serde(default = "default_enabled_indexes")]
+    pub enabled_indexes: Vec<IndexKind>,
+}
+
+/// Each WebSocket client has its own bounded queue of events waiting to be
+/// sent, so that a slow client only ever holds `queue_capacity` events.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct WebSocketConfig {
+    #[serde(default = "default_ws_queue_capacity")]
+    pub queue_capacity: usize,
+    #[serde(default)]
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for WebSocketConfig {
+    fn default() -> Self {
+        Self {
+            queue_capacity: default_ws_queue_capacity(),
+            overflow: OverflowPolicy::default(),
+        }
+    }
+}
+
+/// What happens to a client whose queue is full when another event arrives.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// The oldest queued event is dropped to make room.
+    #[default]
+    DropOldest,
+    /// The connection is closed, so the client knows to reconnect and catch up.
+    Close,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PaginationConfig {
+    pub default: u32,
+    pub max: u32,
+}
+
+impl PaginationConfig {
+    pub fn limit(&self, requested: Option<u32>) -> u32 {
+        requested.unwrap_or(self.default).min(self.max)
+    }
 }
 
 impl Default for Config {
@@ -28,6 +226,43 @@ impl Default for Config {
             key_path: default_key_path(),
             port: default_port(),
             batch_size: default_batch_size(),
+            block_pagination: default_block_pagination(),
+            coin_pagination: default_coin_pagination(),
+            spend_pagination: default_spend_pagination(),
+            slow_request_threshold_ms: default_slow_request_threshold_ms(),
+            include_spend: default_include_spend(),
+            hex_prefix: default_hex_prefix(),
+            max_response_bytes: default_max_response_bytes(),
+            max_concurrent_reads: default_max_concurrent_reads(),
+            index_bloom_bits_per_key: default_index_bloom_bits_per_key(),
+            spend_dictionary_bytes: 0,
+            reorg_buffer: default_reorg_buffer(),
+            max_peak_lag_seconds: default_max_peak_lag_seconds(),
+            max_json_depth: default_max_json_depth(),
+            address_prefix: default_address_prefix(),
+            event_coalesce_ms: default_event_coalesce_ms(),
+            catch_panics: default_catch_panics(),
+            node_retry_max_seconds: default_node_retry_max_seconds(),
+            pretty: false,
+            single_flight_prune_seconds: default_single_flight_prune_seconds(),
+            sentry_dsn: None,
+            recent_blocks: default_recent_blocks(),
+            max_requests_per_ip: 0,
+            commit_blocks: 0,
+            commit_interval_ms: 0,
+            read_only: false,
+            secondary_path: None,
+            catch_up_interval_ms: default_catch_up_interval_ms(),
+            disabled_routes: Vec::new(),
+            warm_up_blocks: 0,
+            max_nodes: default_max_nodes(),
+            admin_token: None,
+            websocket: WebSocketConfig::default(),
+            public_base_url: None,
+            shutdown_flush_timeout_ms: default_shutdown_flush_timeout_ms(),
+            durability: Durability::default(),
+            integrity_check: IntegrityCheck::default(),
+            enabled_indexes: default_enabled_indexes(),
         }
     }
 }
@@ -37,11 +272,28 @@ impl Config {
         let home_dir = homedir::my_home().unwrap().unwrap();
         let config_path = home_dir.join(".xchdev").join("config.toml");
 
-        if let Ok(config) = fs::read_to_string(config_path) {
-            Ok(toml::from_str(&config)?)
+        let config = if let Ok(config) = fs::read_to_string(config_path) {
+            toml::from_str(&config)?
         } else {
-            Ok(Config::default())
+            Config::default()
+        };
+
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Rejects values that parse but can't work.
+    fn validate(&self) -> Result<()> {
+        if self.max_concurrent_reads == 0 {
+            bail!("`max_concurrent_reads` must be at least 1, or every read would wait forever");
+        }
+
+        if i32::try_from(self.spend_dictionary_bytes).is_err() {
+            bail!("`spend_dictionary_bytes` can be at most {}", i32::MAX);
         }
+
+        Ok(())
     }
 }
 
@@ -82,3 +334,104 @@ fn default_port() -> u16 {
 fn default_batch_size() -> u32 {
     1000
 }
+
+fn default_block_pagination() -> PaginationConfig {
+    PaginationConfig {
+        default: 50,
+        max: 500,
+    }
+}
+
+fn default_coin_pagination() -> PaginationConfig {
+    PaginationConfig {
+        default: 100,
+        max: 1000,
+    }
+}
+
+fn default_spend_pagination() -> PaginationConfig {
+    PaginationConfig {
+        default: 10,
+        max: 50,
+    }
+}
+
+fn default_slow_request_threshold_ms() -> u64 {
+    1000
+}
+
+fn default_include_spend() -> bool {
+    true
+}
+
+fn default_hex_prefix() -> bool {
+    true
+}
+
+fn default_ws_queue_capacity() -> usize {
+    256
+}
+
+fn default_max_nodes() -> u32 {
+    10_000
+}
+
+fn default_max_response_bytes() -> usize {
+    32 * 1024 * 1024
+}
+
+fn default_max_concurrent_reads() -> usize {
+    num_cpus::get()
+}
+
+fn default_shutdown_flush_timeout_ms() -> u64 {
+    10_000
+}
+
+fn default_max_peak_lag_seconds() -> u64 {
+    600
+}
+
+fn default_event_coalesce_ms() -> u64 {
+    250
+}
+
+fn default_address_prefix() -> String {
+    "xch".to_string()
+}
+
+fn default_max_json_depth() -> usize {
+    32
+}
+
+fn default_node_retry_max_seconds() -> u64 {
+    60
+}
+
+fn default_single_flight_prune_seconds() -> u64 {
+    60
+}
+
+fn default_recent_blocks() -> usize {
+    500
+}
+
+fn default_catch_up_interval_ms() -> u64 {
+    1000
+}
+
+fn default_catch_panics() -> bool {
+    true
+}
+
+fn default_index_bloom_bits_per_key() -> u32 {
+    10
+}
+
+fn default_reorg_buffer() -> u32 {
+    32
+}
+
+fn default_enabled_indexes() -> Vec<IndexKind> {
+    IndexKind::ALL.to_vec()
+}
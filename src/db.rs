@@ -1,7 +1,27 @@
+mod backfill;
+mod compact;
 mod database;
+#[cfg(test)]
+mod fixtures;
+mod index;
+mod read;
+mod recent_blocks;
+mod rollback;
 mod rows;
+mod stats;
 mod transaction;
+mod verify;
+mod warm_up;
 
+pub use backfill::*;
+pub use compact::*;
 pub use database::*;
+#[cfg(test)]
+pub use fixtures::*;
+pub use index::*;
+pub use read::*;
 pub use rows::*;
+pub use stats::*;
 pub use transaction::*;
+pub use verify::*;
+pub use warm_up::*;
@@ -0,0 +1,116 @@
+use std::sync::atomic::Ordering;
+
+use anyhow::Result;
+use chia::protocol::Bytes32;
+use rocksdb::IteratorMode;
+use tracing::info;
+
+use super::{BlockRow, CoinRow, CoinSpendRow, CoinType, Database, Supply};
+
+const BACKFILL_BATCH_SIZE: usize = 10_000;
+
+impl Database {
+    /// Copies the spent height of every coin spend onto its coin row, for
+    /// databases synced before rows recorded it. Rows that are already up to
+    /// date are skipped, so an interrupted backfill can simply be run again.
+    pub fn backfill_spent_heights(&self) -> Result<()> {
+        info!("Backfilling spent heights onto coin rows");
+
+        let mut tx = self.transaction();
+
+        let mut processed = 0;
+        let mut pending = 0;
+
+        for item in self
+            .inner
+            .iterator_cf(self.coin_spend_cf(), IteratorMode::Start)
+        {
+            let (key, value) = item?;
+
+            let coin_id = Bytes32::try_from(&key[..])?;
+            let spend = pot::from_slice::<CoinSpendRow>(&value)?;
+
+            let Some(coin) = self.coin(coin_id)? else {
+                continue;
+            };
+
+            if coin.spent_height == Some(spend.spent_height) {
+                continue;
+            }
+
+            tx.set_spent_height(coin_id, &coin, Some(spend.spent_height))?;
+
+            processed += 1;
+            pending += 1;
+
+            if pending >= BACKFILL_BATCH_SIZE {
+                tx.commit()?;
+
+                tx = self.transaction();
+                pending = 0;
+
+                info!("Backfilled {} coin rows", processed);
+            }
+        }
+
+        tx.set_spent_heights_backfilled()?;
+        tx.commit()?;
+
+        self.spent_heights_backfilled.store(true, Ordering::Relaxed);
+
+        info!("Finished backfilling {} coin rows", processed);
+
+        Ok(())
+    }
+
+    /// Recounts the supply totals from every block row and reward coin, for
+    /// databases synced before they were recorded. This reads the whole coin
+    /// column family, so it should be run while the indexer is stopped.
+    pub fn backfill_supply(&self) -> Result<()> {
+        info!("Backfilling supply totals");
+
+        let mut supply = Supply {
+            issued: 0,
+            burned: 0,
+        };
+
+        for item in self.inner.iterator_cf(self.block_cf(), IteratorMode::Start) {
+            let (_, value) = item?;
+            let block = pot::from_slice::<BlockRow>(&value)?;
+
+            if let Some(transaction_info) = block.transaction_info {
+                supply.burned += u128::from(transaction_info.fees);
+            }
+        }
+
+        let mut processed = 0;
+
+        for item in self.inner.iterator_cf(self.coin_cf(), IteratorMode::Start) {
+            let (_, value) = item?;
+            let coin = pot::from_slice::<CoinRow>(&value)?;
+
+            if coin.kind == CoinType::Reward {
+                supply.issued += u128::from(coin.amount);
+            }
+
+            processed += 1;
+
+            if processed % (BACKFILL_BATCH_SIZE * 100) == 0 {
+                info!("Counted {} coin rows", processed);
+            }
+        }
+
+        let mut tx = self.transaction();
+        tx.set_supply(supply)?;
+        tx.commit()?;
+
+        self.supply_backfilled.store(true, Ordering::Relaxed);
+
+        info!(
+            "Finished backfilling supply, with {} issued and {} burned",
+            supply.issued, supply.burned
+        );
+
+        Ok(())
+    }
+}
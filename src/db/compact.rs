@@ -0,0 +1,52 @@
+use std::time::Instant;
+
+use anyhow::Result;
+use rocksdb::{BottommostLevelCompaction, CompactOptions};
+use tracing::info;
+
+use super::Database;
+
+impl Database {
+    /// Rewrites every coin spend SST file, which is how files written before
+    /// `spend_dictionary_bytes` was set (or changed) pick up a newly trained
+    /// dictionary. Logs the size of the column family before and after.
+    pub fn compact_spends(&self) -> Result<()> {
+        let before = self.spend_sst_bytes()?;
+        let start = Instant::now();
+
+        info!("Compacting coin spends ({} bytes)", before);
+
+        let mut options = CompactOptions::default();
+        options.set_bottommost_level_compaction(BottommostLevelCompaction::Force);
+
+        self.inner.compact_range_cf_opt(
+            self.coin_spend_cf(),
+            None::<&[u8]>,
+            None::<&[u8]>,
+            &options,
+        );
+
+        let after = self.spend_sst_bytes()?;
+
+        info!(
+            "Compacted coin spends from {} to {} bytes ({:.1}% of the original) in {:?}",
+            before,
+            after,
+            if before == 0 {
+                100.0
+            } else {
+                after as f64 / before as f64 * 100.0
+            },
+            start.elapsed()
+        );
+
+        Ok(())
+    }
+
+    fn spend_sst_bytes(&self) -> Result<u64> {
+        Ok(self
+            .inner
+            .property_int_value_cf(self.coin_spend_cf(), "rocksdb.total-sst-files-size")?
+            .unwrap_or(0))
+    }
+}
@@ -1,124 +1,448 @@
-use std::{path::Path, sync::Arc};
+use std::{
+    cmp::Ordering,
+    path::Path,
+    sync::{
+        atomic::{self, AtomicBool},
+        Arc, RwLock,
+    },
+};
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use chia::protocol::Bytes32;
+use itertools::Itertools;
 use rocksdb::{
-    ColumnFamily, ColumnFamilyDescriptor, Direction, IteratorMode, MergeOperands, Options, DB,
+    BlockBasedOptions, ColumnFamily, ColumnFamilyDescriptor, DBRawIterator, Direction,
+    IteratorMode, MergeOperands, Options, ReadOptions, WriteOptions, DB,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{
+    recent_blocks::RecentBlocks, BlockRow, CoinRow, CoinSpendRow, IndexKind, ReadLimiter,
+    Transaction, TransactionInfo,
 };
 
-use super::{BlockRow, CoinRow, CoinSpendRow, Transaction};
+pub(super) const SUPPLY_ISSUED_KEY: &[u8] = b"issued";
+pub(super) const SUPPLY_BURNED_KEY: &[u8] = b"burned";
+
+/// Each puzzle hash summary total is keyed by `puzzle_hash || tag`, while the
+/// number of the puzzle hash's coins created or spent at each height is keyed
+/// by `puzzle_hash || SUMMARY_ACTIVITY_TAG || height`.
+pub(super) const SUMMARY_ACTIVITY_TAG: u8 = 0;
+pub(super) const SUMMARY_RECEIVED_TAG: u8 = 1;
+pub(super) const SUMMARY_SENT_TAG: u8 = 2;
+pub(super) const SUMMARY_COINS_TAG: u8 = 3;
+pub(super) const SUMMARY_SPENT_COINS_TAG: u8 = 4;
+
+/// Running totals in mojos, where `issued` is the value of every reward coin
+/// and `burned` is every block's fees. Fees are paid out in the farmer's
+/// reward coin, so `issued - burned` is the newly minted supply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Supply {
+    pub issued: u128,
+    pub burned: u128,
+}
+
+/// A puzzle hash's totals from the summary index. The balance is what was
+/// received and not yet sent, and the heights are the first and last at which
+/// one of its coins was created or spent.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PuzzleHashSummary {
+    pub received: u128,
+    pub sent: u128,
+    pub coins: u64,
+    pub spent_coins: u64,
+    pub first_height: Option<u32>,
+    pub last_height: Option<u32>,
+}
+
+/// The order a puzzle hash's coin ids are read in.
+///
+/// Each order has its own index rather than sharing one key layout. Coin id
+/// order is what the puzzle hash cursors and index intersections rely on, and
+/// putting the height in that key would break both, while the height index
+/// can be enabled (and rebuilt) on its own without touching the other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PuzzleHashOrder {
+    /// Ascending coin id, from the `puzzle_hash` index.
+    CoinId,
+    /// Ascending created height and then coin id, from the
+    /// `puzzle_hash_height` index.
+    CreatedHeight,
+}
 
-struct Column {
-    name: &'static str,
+pub(super) struct Column {
+    pub(super) name: &'static str,
     prefix: Option<usize>,
+    merge: Merge,
+}
+
+/// How a column's merge operands are combined.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Merge {
+    /// Nothing is merged into the column.
+    None,
+    /// Index entries, whose merged values are concatenated.
+    Concat,
+    /// Values are little endian `i64` counts that merges add together.
+    Count,
+    /// Values are little endian `i128` totals that merges add together, for
+    /// mojo amounts that overflow an `i64` well before the prefarm is counted.
+    WideCount,
+}
+
+pub(super) const COLUMNS: &[Column] = &[
+    Column {
+        name: "blocks",
+        prefix: None,
+        merge: Merge::None,
+    },
+    Column {
+        name: "block_hash",
+        prefix: None,
+        merge: Merge::None,
+    },
+    Column {
+        name: "coins",
+        prefix: None,
+        merge: Merge::None,
+    },
+    Column {
+        name: "tails",
+        prefix: None,
+        merge: Merge::None,
+    },
+    Column {
+        name: "coin_spends",
+        prefix: None,
+        merge: Merge::None,
+    },
+    Column {
+        name: "puzzle_hash_index",
+        prefix: Some(32),
+        merge: Merge::Concat,
+    },
+    Column {
+        name: "puzzle_hash_height_index",
+        prefix: Some(32),
+        merge: Merge::Concat,
+    },
+    Column {
+        name: "puzzle_hash_summary_index",
+        prefix: Some(32),
+        merge: Merge::WideCount,
+    },
+    Column {
+        name: "hint_index",
+        prefix: Some(32),
+        merge: Merge::Concat,
+    },
+    Column {
+        name: "parent_coin_id_index",
+        prefix: Some(32),
+        merge: Merge::Concat,
+    },
+    Column {
+        name: "created_height_index",
+        prefix: Some(4),
+        merge: Merge::Concat,
+    },
+    Column {
+        name: "spent_height_index",
+        prefix: Some(4),
+        merge: Merge::Concat,
+    },
+    Column {
+        name: "offer_nonce_index",
+        prefix: Some(32),
+        merge: Merge::Concat,
+    },
+    Column {
+        name: "amount_index",
+        prefix: Some(8),
+        merge: Merge::Concat,
+    },
+    Column {
+        name: "name_index",
+        prefix: None,
+        merge: Merge::Count,
+    },
+    Column {
+        name: "cat_coin_counts",
+        prefix: None,
+        merge: Merge::Count,
+    },
+    Column {
+        name: "supply",
+        prefix: None,
+        merge: Merge::WideCount,
+    },
+];
+
+/// Whether each committed write batch is fsynced before returning.
+///
+/// With `Async`, a crash can lose the most recently committed batches (but
+/// never leaves a partial one), which is fine as long as the indexer can
+/// re-sync them from the node. `Sync` trades write throughput for never
+/// losing a committed batch.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Durability {
+    Sync,
+    #[default]
+    Async,
 }
 
 #[derive(Clone)]
-pub struct Database(pub(super) Arc<DB>);
+pub struct Database {
+    pub(super) inner: Arc<DB>,
+    durability: Durability,
+    pub(super) enabled_indexes: Arc<[IndexKind]>,
+    /// The enabled indexes that are complete, from their built markers.
+    pub(super) built_indexes: Arc<RwLock<Vec<IndexKind>>>,
+    pub(super) read_limiter: Arc<ReadLimiter>,
+    /// Whether every coin row has its spent height, either because the
+    /// database was created with them or because they were backfilled.
+    pub(super) spent_heights_backfilled: Arc<AtomicBool>,
+    /// Whether the supply totals cover every block, for the same reasons.
+    pub(super) supply_backfilled: Arc<AtomicBool>,
+    pub(super) recent_blocks: Arc<RwLock<RecentBlocks>>,
+    /// Whether this is a secondary instance that only follows another
+    /// instance's writes, opened by `new_secondary`.
+    read_only: bool,
+}
 
 impl Database {
-    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
-        let cf_names = [
-            Column {
-                name: "blocks",
-                prefix: None,
-            },
-            Column {
-                name: "block_hash",
-                prefix: None,
-            },
-            Column {
-                name: "coins",
-                prefix: None,
-            },
-            Column {
-                name: "tails",
-                prefix: None,
-            },
-            Column {
-                name: "coin_spends",
-                prefix: None,
-            },
-            Column {
-                name: "puzzle_hash_index",
-                prefix: Some(32),
-            },
-            Column {
-                name: "hint_index",
-                prefix: Some(32),
-            },
-            Column {
-                name: "parent_coin_id_index",
-                prefix: Some(32),
-            },
-            Column {
-                name: "created_height_index",
-                prefix: Some(4),
-            },
-            Column {
-                name: "spent_height_index",
-                prefix: Some(4),
-            },
-        ];
-
-        let mut options = Options::default();
+    pub fn new(
+        path: impl AsRef<Path>,
+        durability: Durability,
+        enabled_indexes: &[IndexKind],
+        max_concurrent_reads: usize,
+        index_bloom_bits_per_key: u32,
+        spend_dictionary_bytes: u32,
+        recent_blocks: usize,
+    ) -> Result<Self> {
+        let (mut options, cf_descriptors) =
+            open_options(index_bloom_bits_per_key, spend_dictionary_bytes)?;
         options.create_if_missing(true);
         options.create_missing_column_families(true);
-        options.set_max_background_jobs(8);
-        options.increase_parallelism(num_cpus::get() as i32);
-        options.set_write_buffer_size(512 * 1024 * 1024);
-        options.set_max_write_buffer_number(6);
-        options.set_max_background_jobs(4);
-        options.set_target_file_size_base(64 * 1024 * 1024);
-        options.set_disable_auto_compactions(false);
-        options.prepare_for_bulk_load();
-
-        // Create column family descriptors with custom options
-        let cf_descriptors: Vec<ColumnFamilyDescriptor> = cf_names
-            .iter()
-            .map(|column| {
-                let mut cf_opts = Options::default();
-
-                // Optimize index column families
-                if let Some(prefix) = column.prefix {
-                    cf_opts
-                        .set_prefix_extractor(rocksdb::SliceTransform::create_fixed_prefix(prefix));
-                    cf_opts.set_memtable_prefix_bloom_ratio(0.1);
-                }
 
-                cf_opts.set_compression_type(rocksdb::DBCompressionType::Lz4);
+        // Open database with column families
+        let db = DB::open_cf_descriptors(&options, path, cf_descriptors)?;
+
+        Self::from_db(
+            db,
+            durability,
+            enabled_indexes,
+            max_concurrent_reads,
+            recent_blocks,
+            false,
+        )
+    }
 
-                // Use different settings for coin data vs indexes
-                if column.prefix.is_some() {
-                    cf_opts.set_merge_operator_associative("test operator", concat_merge);
-                } else {
-                    cf_opts.set_bottommost_compression_type(rocksdb::DBCompressionType::Zstd);
-                }
+    /// Opens the database at `primary_path` as a RocksDB secondary, which
+    /// reads the files another process is writing and keeps its own logs in
+    /// `secondary_path`. It only sees the primary's writes as of the last
+    /// `catch_up_with_primary`, and can't write anything itself.
+    pub fn new_secondary(
+        primary_path: impl AsRef<Path>,
+        secondary_path: impl AsRef<Path>,
+        enabled_indexes: &[IndexKind],
+        max_concurrent_reads: usize,
+        index_bloom_bits_per_key: u32,
+        spend_dictionary_bytes: u32,
+        recent_blocks: usize,
+    ) -> Result<Self> {
+        let (mut options, cf_descriptors) =
+            open_options(index_bloom_bits_per_key, spend_dictionary_bytes)?;
+
+        // A secondary has to keep every file open to follow the primary, since
+        // a file it reopens later may have been deleted by a compaction.
+        options.set_max_open_files(-1);
+
+        let db = DB::open_cf_descriptors_as_secondary(
+            &options,
+            primary_path.as_ref(),
+            secondary_path.as_ref(),
+            cf_descriptors,
+        )?;
+
+        Self::from_db(
+            db,
+            Durability::default(),
+            enabled_indexes,
+            max_concurrent_reads,
+            recent_blocks,
+            true,
+        )
+    }
 
-                ColumnFamilyDescriptor::new(column.name.to_string(), cf_opts)
-            })
-            .collect();
+    fn from_db(
+        db: DB,
+        durability: Durability,
+        enabled_indexes: &[IndexKind],
+        max_concurrent_reads: usize,
+        recent_blocks: usize,
+        read_only: bool,
+    ) -> Result<Self> {
+        // A new database writes spent heights onto coin rows from the start.
+        let spent_heights_backfilled = if db.get(b"spent_heights_backfilled")?.is_some() {
+            true
+        } else if db.get(b"peak_height")?.is_none() {
+            if !read_only {
+                db.put(b"spent_heights_backfilled", [])?;
+            }
+            true
+        } else {
+            false
+        };
 
-        // Open database with column families
-        let db = DB::open_cf_descriptors(&options, path, cf_descriptors)?;
+        let supply_backfilled = if db.get(b"supply_backfilled")?.is_some() {
+            true
+        } else if db.get(b"peak_height")?.is_none() {
+            if !read_only {
+                db.put(b"supply_backfilled", [])?;
+            }
+            true
+        } else {
+            false
+        };
+
+        let built_indexes = Self::read_built_indexes(&db, enabled_indexes, read_only)?;
+
+        let db = Self {
+            inner: Arc::new(db),
+            durability,
+            enabled_indexes: enabled_indexes.into(),
+            built_indexes: Arc::new(RwLock::new(built_indexes)),
+            read_limiter: Arc::new(ReadLimiter::new(max_concurrent_reads)),
+            spent_heights_backfilled: Arc::new(AtomicBool::new(spent_heights_backfilled)),
+            supply_backfilled: Arc::new(AtomicBool::new(supply_backfilled)),
+            recent_blocks: Arc::new(RwLock::new(RecentBlocks::new(recent_blocks))),
+            read_only,
+        };
+
+        db.load_recent_blocks()?;
 
-        Ok(Self(Arc::new(db)))
+        Ok(db)
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Replays what the primary has written since the last call, for a
+    /// database opened by `new_secondary`. The recent blocks, built indexes
+    /// and backfill flags are read again, since they aren't kept up to date by
+    /// the primary's commits.
+    pub fn catch_up_with_primary(&self) -> Result<()> {
+        self.inner.try_catch_up_with_primary()?;
+
+        for (key, backfilled) in [
+            (
+                &b"spent_heights_backfilled"[..],
+                &self.spent_heights_backfilled,
+            ),
+            (&b"supply_backfilled"[..], &self.supply_backfilled),
+        ] {
+            if self.inner.get(key)?.is_some() {
+                backfilled.store(true, atomic::Ordering::Relaxed);
+            }
+        }
+
+        *self.built_indexes.write().unwrap() =
+            Self::read_built_indexes(&self.inner, &self.enabled_indexes, true)?;
+
+        self.load_recent_blocks()
+    }
+
+    /// The ring starts out with the blocks already on disk, so the first
+    /// page is served from memory without waiting for new blocks.
+    fn load_recent_blocks(&self) -> Result<()> {
+        let capacity = self.recent_blocks.read().unwrap().capacity();
+        let mut written = Vec::new();
+
+        if let Some(peak_height) = self.peak_height()? {
+            let start_height = peak_height.saturating_sub(capacity.saturating_sub(1) as u32);
+
+            for height in start_height..=peak_height {
+                if let Some(block) = self.block(height)? {
+                    written.push((height, block));
+                }
+            }
+        }
+
+        self.recent_blocks.write().unwrap().reset(&written);
+
+        Ok(())
     }
 
     pub fn peak_height(&self) -> Result<Option<u32>> {
-        let height = self.0.get(b"peak_height")?;
+        let height = self.inner.get(b"peak_height")?;
         Ok(height.map(|bytes| u32::from_be_bytes(bytes.try_into().unwrap())))
     }
 
     pub fn block(&self, height: u32) -> Result<Option<BlockRow>> {
-        let block = self.0.get_cf(self.block_cf(), height.to_be_bytes())?;
+        let block = self.inner.get_cf(self.block_cf(), height.to_be_bytes())?;
         Ok(block
             .map(|bytes| pot::from_slice::<BlockRow>(&bytes))
             .transpose()?)
     }
 
+    /// The lowest and highest heights with a block row, read from the ends of
+    /// the block column family, or `None` before any block is indexed.
+    pub fn block_height_range(&self) -> Result<Option<(u32, u32)>> {
+        let height = |mode: IteratorMode<'_>| -> Result<Option<u32>> {
+            for item in self.inner.iterator_cf(self.block_cf(), mode) {
+                let (key, _) = item?;
+
+                if let Ok(key) = key[..].try_into() {
+                    return Ok(Some(u32::from_be_bytes(key)));
+                }
+            }
+
+            Ok(None)
+        };
+
+        Ok(height(IteratorMode::Start)?.zip(height(IteratorMode::End)?))
+    }
+
+    pub fn block_timestamp(&self, height: u32) -> Result<Option<u64>> {
+        Ok(self
+            .block(height)?
+            .and_then(|block| block.transaction_info)
+            .map(|transaction_info| transaction_info.timestamp))
+    }
+
+    /// The timestamp of the highest transaction block at or below `height`,
+    /// since only transaction blocks have timestamps.
+    pub fn latest_timestamp(&self, height: u32) -> Result<Option<u64>> {
+        let start_key = height.to_be_bytes();
+
+        let iter = self.inner.iterator_cf(
+            self.block_cf(),
+            IteratorMode::From(&start_key, Direction::Reverse),
+        );
+
+        for item in iter {
+            let (_, value) = item?;
+
+            if let Some(transaction_info) = pot::from_slice::<BlockRow>(&value)?.transaction_info {
+                return Ok(Some(transaction_info.timestamp));
+            }
+        }
+
+        Ok(None)
+    }
+
+    pub fn blocks(&self, heights: &[u32]) -> Result<Vec<Option<BlockRow>>> {
+        self.multi_get(
+            self.block_cf(),
+            heights.iter().map(|height| height.to_be_bytes()),
+        )
+    }
+
     pub fn block_height(&self, hash: Bytes32) -> Result<Option<u32>> {
-        let height = self.0.get_cf(self.block_hash_cf(), hash.as_ref())?;
+        let height = self.inner.get_cf(self.block_hash_cf(), hash.as_ref())?;
         Ok(height.map(|bytes| u32::from_be_bytes(bytes.try_into().unwrap())))
     }
 
@@ -134,6 +458,17 @@ impl Database {
 
         let end_height = end_height.min(self.peak_height()?.unwrap_or(0));
 
+        if matches!(direction, Direction::Reverse) {
+            if let Some(blocks) = self
+                .recent_blocks
+                .read()
+                .unwrap()
+                .reverse_range(start_height, end_height)
+            {
+                return Ok(blocks);
+            }
+        }
+
         let mut result = Vec::new();
 
         let start_key = match direction {
@@ -142,7 +477,7 @@ impl Database {
         };
 
         let iter = self
-            .0
+            .inner
             .iterator_cf(self.block_cf(), IteratorMode::From(&start_key, direction));
 
         for item in iter {
@@ -173,166 +508,1353 @@ impl Database {
         Ok(result)
     }
 
+    pub fn block_hashes_by_prefix(
+        &self,
+        prefix: &[u8],
+        limit: usize,
+    ) -> Result<Vec<(Bytes32, u32)>> {
+        self.scan_prefix(self.block_hash_cf(), prefix, limit)?
+            .into_iter()
+            .map(|(key, value)| -> Result<(Bytes32, u32)> {
+                Ok((
+                    Bytes32::try_from(&key[..])?,
+                    u32::from_be_bytes(value[..].try_into()?),
+                ))
+            })
+            .collect()
+    }
+
+    /// Transaction blocks with a timestamp of at least `since`, newest first.
+    pub fn transaction_blocks_since(&self, since: u64) -> Result<Vec<(u32, TransactionInfo)>> {
+        let iter = self.inner.iterator_cf(self.block_cf(), IteratorMode::End);
+
+        let mut result = Vec::new();
+
+        for item in iter {
+            let (key, value) = item?;
+
+            if key.len() != 4 {
+                bail!("invalid block height key");
+            }
+
+            let height = u32::from_be_bytes(key[..].try_into().unwrap());
+
+            let Some(transaction_info) = pot::from_slice::<BlockRow>(&value)?.transaction_info
+            else {
+                continue;
+            };
+
+            if transaction_info.timestamp < since {
+                break;
+            }
+
+            result.push((height, transaction_info));
+        }
+
+        Ok(result)
+    }
+
     pub fn coin(&self, coin_id: Bytes32) -> Result<Option<CoinRow>> {
         Ok(self
-            .0
+            .inner
             .get_cf(self.coin_cf(), coin_id)?
             .map(|bytes| pot::from_slice::<CoinRow>(&bytes))
             .transpose()?)
     }
 
+    pub fn coins(&self, coin_ids: &[Bytes32]) -> Result<Vec<Option<CoinRow>>> {
+        self.multi_get(self.coin_cf(), coin_ids)
+    }
+
+    /// The coin's spent height, read from its row unless rows haven't been
+    /// backfilled yet, in which case the coin spend is looked up instead.
+    pub fn coin_spent_height(&self, coin_id: Bytes32, coin: &CoinRow) -> Result<Option<u32>> {
+        if self
+            .spent_heights_backfilled
+            .load(atomic::Ordering::Relaxed)
+        {
+            return Ok(coin.spent_height);
+        }
+
+        Ok(self.coin_spend(coin_id)?.map(|spend| spend.spent_height))
+    }
+
+    /// The spent heights of coins read with `coins`, like `coin_spent_height`
+    /// but with the coin spends multi-read when they're needed. A coin that
+    /// wasn't found has no spent height.
+    pub fn coin_spent_heights(
+        &self,
+        coin_ids: &[Bytes32],
+        coins: &[Option<CoinRow>],
+    ) -> Result<Vec<Option<u32>>> {
+        if self
+            .spent_heights_backfilled
+            .load(atomic::Ordering::Relaxed)
+        {
+            return Ok(coins
+                .iter()
+                .map(|coin| coin.as_ref().and_then(|coin| coin.spent_height))
+                .collect());
+        }
+
+        Ok(self
+            .coin_spends(coin_ids)?
+            .into_iter()
+            .zip(coins)
+            .map(|(spend, coin)| {
+                spend
+                    .filter(|_| coin.is_some())
+                    .map(|spend| spend.spent_height)
+            })
+            .collect())
+    }
+
+    pub fn coin_spends(&self, coin_ids: &[Bytes32]) -> Result<Vec<Option<CoinSpendRow>>> {
+        self.multi_get(self.coin_spend_cf(), coin_ids)
+    }
+
     pub fn coin_spend(&self, coin_id: Bytes32) -> Result<Option<CoinSpendRow>> {
         Ok(self
-            .0
+            .inner
             .get_cf(self.coin_spend_cf(), coin_id)?
             .map(|bytes| pot::from_slice::<CoinSpendRow>(&bytes))
             .transpose()?)
     }
 
-    pub fn coins_by_puzzle_hash(&self, puzzle_hash: Bytes32) -> Result<Vec<Bytes32>> {
-        let mut result = Vec::new();
+    pub fn coin_ids_by_prefix(&self, prefix: &[u8], limit: usize) -> Result<Vec<Bytes32>> {
+        self.scan_prefix(self.coin_cf(), prefix, limit)?
+            .into_iter()
+            .map(|(key, _)| -> Result<Bytes32> { Ok(Bytes32::try_from(&key[..])?) })
+            .collect()
+    }
 
-        let iter = self
-            .0
-            .prefix_iterator_cf(self.puzzle_hash_index_cf(), puzzle_hash.as_ref());
+    pub fn coins_by_puzzle_hash(
+        &self,
+        puzzle_hash: Bytes32,
+        order: PuzzleHashOrder,
+    ) -> Result<Vec<Bytes32>> {
+        match order {
+            PuzzleHashOrder::CoinId => self.index_page(
+                self.puzzle_hash_index_cf(),
+                puzzle_hash.as_ref(),
+                None,
+                usize::MAX,
+            ),
+            PuzzleHashOrder::CreatedHeight => {
+                let mut result = Vec::new();
+
+                for item in self
+                    .inner
+                    .prefix_iterator_cf(self.puzzle_hash_height_index_cf(), puzzle_hash)
+                {
+                    let (key, _) = item?;
+
+                    if !key.starts_with(puzzle_hash.as_ref()) {
+                        break;
+                    }
 
-        for item in iter {
-            let (key, _) = item?;
+                    if key.len() == 68 {
+                        result.push(Bytes32::try_from(&key[36..68]).unwrap());
+                    }
+                }
 
-            if key.len() == 64 {
-                let coin_id = Bytes32::try_from(&key[32..64]).unwrap();
-                result.push(coin_id);
+                Ok(result)
             }
         }
+    }
 
-        Ok(result)
+    /// Whether any coin has ever been created with the puzzle hash, spent or
+    /// not, found by seeking to its first index key.
+    pub fn puzzle_hash_used(&self, puzzle_hash: Bytes32) -> Result<bool> {
+        self.has_index_prefix(self.puzzle_hash_index_cf(), puzzle_hash.as_ref())
+    }
+
+    pub fn coins_by_puzzle_hash_page(
+        &self,
+        puzzle_hash: Bytes32,
+        cursor: Option<Bytes32>,
+        limit: usize,
+    ) -> Result<Vec<Bytes32>> {
+        self.index_page(
+            self.puzzle_hash_index_cf(),
+            puzzle_hash.as_ref(),
+            cursor,
+            limit,
+        )
+    }
+
+    /// Coins from several puzzle hashes merged into a single page ordered by
+    /// coin id, with the cursor applying across the whole set.
+    pub fn coins_by_puzzle_hashes(
+        &self,
+        puzzle_hashes: &[Bytes32],
+        cursor: Option<Bytes32>,
+        limit: usize,
+    ) -> Result<Vec<Bytes32>> {
+        let mut coin_ids = Vec::new();
+
+        for puzzle_hash in puzzle_hashes.iter().unique() {
+            coin_ids.extend(self.index_page(
+                self.puzzle_hash_index_cf(),
+                puzzle_hash.as_ref(),
+                cursor,
+                limit,
+            )?);
+        }
+
+        coin_ids.sort_unstable();
+        coin_ids.truncate(limit);
+
+        Ok(coin_ids)
     }
 
     pub fn coins_by_parent_coin_id(&self, parent_coin_id: Bytes32) -> Result<Vec<Bytes32>> {
-        let mut result = Vec::new();
+        self.coins_by_parent_coin_id_page(parent_coin_id, None, usize::MAX)
+    }
 
-        let iter = self
-            .0
-            .prefix_iterator_cf(self.parent_coin_id_index_cf(), parent_coin_id.as_ref());
+    pub fn coins_by_parent_coin_id_page(
+        &self,
+        parent_coin_id: Bytes32,
+        cursor: Option<Bytes32>,
+        limit: usize,
+    ) -> Result<Vec<Bytes32>> {
+        self.index_page(
+            self.parent_coin_id_index_cf(),
+            parent_coin_id.as_ref(),
+            cursor,
+            limit,
+        )
+    }
 
-        for item in iter {
-            let (key, _) = item?;
+    /// Children of the coin with the puzzle hash, read by intersecting the
+    /// parent and puzzle hash indexes when both are enabled.
+    pub fn coins_by_parent_coin_id_and_puzzle_hash(
+        &self,
+        parent_coin_id: Bytes32,
+        puzzle_hash: Bytes32,
+        cursor: Option<Bytes32>,
+        limit: usize,
+    ) -> Result<Vec<Bytes32>> {
+        if self.is_index_built(IndexKind::PuzzleHash) {
+            return self.intersect_index_prefixes(
+                (self.parent_coin_id_index_cf(), parent_coin_id.as_ref()),
+                (self.puzzle_hash_index_cf(), puzzle_hash.as_ref()),
+                cursor,
+                limit,
+            );
+        }
 
-            if key.len() == 64 {
-                let coin_id = Bytes32::try_from(&key[32..64]).unwrap();
-                result.push(coin_id);
+        let mut result = Vec::new();
+        let mut cursor = cursor;
+
+        while result.len() < limit {
+            let coin_ids = self.coins_by_parent_coin_id_page(parent_coin_id, cursor, limit)?;
+            let exhausted = coin_ids.len() < limit;
+            let coins = self.coins(&coin_ids)?;
+
+            cursor = coin_ids.last().copied();
+
+            result.extend(
+                coin_ids
+                    .into_iter()
+                    .zip(coins)
+                    .filter(|(_, coin)| {
+                        coin.as_ref()
+                            .is_some_and(|coin| coin.puzzle_hash == puzzle_hash)
+                    })
+                    .map(|(coin_id, _)| coin_id),
+            );
+
+            if exhausted {
+                break;
             }
         }
 
+        result.truncate(limit);
+
         Ok(result)
     }
 
-    pub fn coins_by_hint(&self, hint: Bytes32) -> Result<Vec<Bytes32>> {
-        let mut result = Vec::new();
+    pub fn count_coins_by_parent_coin_id(&self, parent_coin_id: Bytes32) -> Result<usize> {
+        self.count_index_prefix(self.parent_coin_id_index_cf(), parent_coin_id.as_ref())
+    }
 
-        let iter = self
-            .0
-            .prefix_iterator_cf(self.hint_index_cf(), hint.as_ref());
+    /// The hint's coins in ascending created height and then coin id order,
+    /// or both descending with `reverse`. The cursor is the created height and
+    /// coin id of the last coin already seen.
+    pub fn coins_by_hint(
+        &self,
+        hint: Bytes32,
+        cursor: Option<(u32, Bytes32)>,
+        reverse: bool,
+        limit: usize,
+    ) -> Result<Vec<(u32, Bytes32)>> {
+        let mut options = ReadOptions::default();
+        options.set_total_order_seek(true);
+
+        let start = match cursor {
+            Some((created_height, coin_id)) => [
+                hint.as_ref(),
+                &created_height.to_be_bytes(),
+                coin_id.as_ref(),
+            ]
+            .concat(),
+            None if reverse => [hint.as_ref(), &[0xff; 36]].concat(),
+            None => hint.to_vec(),
+        };
+
+        let direction = if reverse {
+            Direction::Reverse
+        } else {
+            Direction::Forward
+        };
+
+        let iter = self.inner.iterator_cf_opt(
+            self.hint_index_cf(),
+            options,
+            IteratorMode::From(&start, direction),
+        );
+
+        let mut result = Vec::new();
 
         for item in iter {
+            if result.len() >= limit {
+                break;
+            }
+
             let (key, _) = item?;
 
-            if key.len() == 64 {
-                let coin_id = Bytes32::try_from(&key[32..64]).unwrap();
-                result.push(coin_id);
+            if !key.starts_with(hint.as_ref()) {
+                break;
+            }
+
+            if key.len() != 68 || key[..] == start[..] {
+                continue;
             }
+
+            let created_height = u32::from_be_bytes(key[32..36].try_into().unwrap());
+            let coin_id = Bytes32::try_from(&key[36..68]).unwrap();
+
+            result.push((created_height, coin_id));
         }
 
         Ok(result)
     }
 
-    pub fn coins_by_created_height(&self, created_height: u32) -> Result<Vec<Bytes32>> {
-        let mut result = Vec::new();
+    pub fn coins_by_amount(
+        &self,
+        amount: u64,
+        cursor: Option<Bytes32>,
+        limit: usize,
+    ) -> Result<Vec<Bytes32>> {
+        self.index_page(self.amount_index_cf(), &amount.to_be_bytes(), cursor, limit)
+    }
 
-        let iter = self
-            .0
-            .prefix_iterator_cf(self.created_height_index_cf(), created_height.to_be_bytes());
+    /// Coins with amounts in `min..=max`, ordered by amount and then coin id.
+    /// The cursor is the `amount || coin_id` key of the last coin already seen.
+    pub fn coins_by_amount_range(
+        &self,
+        min: u64,
+        max: u64,
+        cursor: Option<&[u8]>,
+        limit: usize,
+    ) -> Result<Vec<(u64, Bytes32)>> {
+        let mut options = ReadOptions::default();
+        options.set_total_order_seek(true);
+
+        let min = min.to_be_bytes();
+        let start = cursor.filter(|cursor| cursor[..] > min[..]).unwrap_or(&min);
+
+        let iter = self.inner.iterator_cf_opt(
+            self.amount_index_cf(),
+            options,
+            IteratorMode::From(start, Direction::Forward),
+        );
+
+        let mut result = Vec::new();
 
         for item in iter {
+            if result.len() >= limit {
+                break;
+            }
+
             let (key, _) = item?;
 
-            if key.len() == 36 {
-                let coin_id = Bytes32::try_from(&key[4..36]).unwrap();
-                result.push(coin_id);
+            if key.len() != 40 || Some(&key[..]) == cursor {
+                continue;
+            }
+
+            let amount = u64::from_be_bytes(key[..8].try_into().unwrap());
+
+            if amount > max {
+                break;
             }
+
+            result.push((amount, Bytes32::try_from(&key[8..40]).unwrap()));
         }
 
         Ok(result)
     }
 
-    pub fn coins_by_spent_height(&self, spent_height: u32) -> Result<Vec<Bytes32>> {
-        let mut result = Vec::new();
+    pub fn coins_by_offer_nonce(
+        &self,
+        nonce: Bytes32,
+        cursor: Option<Bytes32>,
+        limit: usize,
+    ) -> Result<Vec<Bytes32>> {
+        self.index_page(self.offer_nonce_index_cf(), nonce.as_ref(), cursor, limit)
+    }
 
-        let iter = self
-            .0
-            .prefix_iterator_cf(self.spent_height_index_cf(), spent_height.to_be_bytes());
+    /// Coins with the puzzle hash that were created at the height.
+    pub fn coins_by_puzzle_hash_and_created_height(
+        &self,
+        puzzle_hash: Bytes32,
+        created_height: u32,
+        cursor: Option<Bytes32>,
+        limit: usize,
+    ) -> Result<Vec<Bytes32>> {
+        self.intersect_index_prefixes(
+            (self.puzzle_hash_index_cf(), puzzle_hash.as_ref()),
+            (
+                self.created_height_index_cf(),
+                &created_height.to_be_bytes(),
+            ),
+            cursor,
+            limit,
+        )
+    }
+
+    /// Reads the puzzle hash's totals, which are all zero if none of its coins
+    /// have been indexed. Totals that don't add up mean the index was written
+    /// to before it was built, and are an error rather than a wrong balance.
+    pub fn puzzle_hash_summary(&self, puzzle_hash: Bytes32) -> Result<PuzzleHashSummary> {
+        let cf = self.puzzle_hash_summary_index_cf();
+
+        let total = |tag: u8| -> Result<i128> {
+            let total = self
+                .inner
+                .get_cf(cf, [puzzle_hash.as_ref(), &[tag]].concat())?
+                .and_then(|value| value.as_slice().try_into().ok())
+                .map(i128::from_le_bytes)
+                .unwrap_or_default();
+
+            if total < 0 {
+                bail!("the puzzle hash summary of {puzzle_hash} has a negative total");
+            }
+
+            Ok(total)
+        };
+
+        // Heights whose coins were all rolled back are left with a count of
+        // zero, so they're skipped.
+        let prefix = [puzzle_hash.as_ref(), &[SUMMARY_ACTIVITY_TAG]].concat();
+        let active_height = |mode: IteratorMode<'_>| -> Result<Option<u32>> {
+            let mut options = ReadOptions::default();
+            options.set_total_order_seek(true);
+
+            for item in self.inner.iterator_cf_opt(cf, options, mode) {
+                let (key, value) = item?;
+
+                if !key.starts_with(&prefix) {
+                    break;
+                }
+
+                let count = value[..]
+                    .try_into()
+                    .map(i128::from_le_bytes)
+                    .unwrap_or_default();
+
+                if key.len() == 37 && count > 0 {
+                    return Ok(Some(u32::from_be_bytes(key[33..].try_into().unwrap())));
+                }
+            }
+
+            Ok(None)
+        };
+
+        let end = [prefix.as_slice(), &[0xff; 4]].concat();
+
+        let summary = PuzzleHashSummary {
+            received: total(SUMMARY_RECEIVED_TAG)? as u128,
+            sent: total(SUMMARY_SENT_TAG)? as u128,
+            coins: total(SUMMARY_COINS_TAG)? as u64,
+            spent_coins: total(SUMMARY_SPENT_COINS_TAG)? as u64,
+            first_height: active_height(IteratorMode::From(&prefix, Direction::Forward))?,
+            last_height: active_height(IteratorMode::From(&end, Direction::Reverse))?,
+        };
+
+        if summary.sent > summary.received || summary.spent_coins > summary.coins {
+            bail!("the puzzle hash summary of {puzzle_hash} has spent more than it received");
+        }
+
+        Ok(summary)
+    }
+
+    /// The puzzle hash's coins created at or below `max_height`, in ascending
+    /// created height and then coin id order. The cursor is the created
+    /// height and coin id of the last coin already seen.
+    pub fn coins_by_puzzle_hash_created_through(
+        &self,
+        puzzle_hash: Bytes32,
+        max_height: u32,
+        cursor: Option<(u32, Bytes32)>,
+        limit: usize,
+    ) -> Result<Vec<(u32, Bytes32)>> {
+        let start = match cursor {
+            Some((created_height, coin_id)) => [
+                puzzle_hash.as_ref(),
+                &created_height.to_be_bytes(),
+                coin_id.as_ref(),
+            ]
+            .concat(),
+            None => puzzle_hash.to_vec(),
+        };
+
+        let iter = self.inner.iterator_cf(
+            self.puzzle_hash_height_index_cf(),
+            IteratorMode::From(&start, Direction::Forward),
+        );
+
+        let mut result = Vec::new();
 
         for item in iter {
+            if result.len() >= limit {
+                break;
+            }
+
             let (key, _) = item?;
 
-            if key.len() == 36 {
-                let coin_id = Bytes32::try_from(&key[4..36]).unwrap();
-                result.push(coin_id);
+            if !key.starts_with(puzzle_hash.as_ref()) {
+                break;
+            }
+
+            if key.len() != 68 || key[..] == start[..] {
+                continue;
+            }
+
+            let created_height = u32::from_be_bytes(key[32..36].try_into().unwrap());
+
+            if created_height > max_height {
+                break;
             }
+
+            result.push((created_height, Bytes32::try_from(&key[36..68]).unwrap()));
         }
 
         Ok(result)
     }
 
-    pub(super) fn block_cf(&self) -> &ColumnFamily {
-        self.0.cf_handle("blocks").unwrap()
-    }
+    /// The puzzle hash's coins from the highest created height down, with ties
+    /// in descending coin id order. The cursor is the created height and coin
+    /// id of the last coin already seen.
+    pub fn newest_coins_by_puzzle_hash(
+        &self,
+        puzzle_hash: Bytes32,
+        cursor: Option<(u32, Bytes32)>,
+        limit: usize,
+    ) -> Result<Vec<(u32, Bytes32)>> {
+        let mut options = ReadOptions::default();
+        options.set_total_order_seek(true);
+
+        let start = match cursor {
+            Some((created_height, coin_id)) => [
+                puzzle_hash.as_ref(),
+                &created_height.to_be_bytes(),
+                coin_id.as_ref(),
+            ]
+            .concat(),
+            None => [puzzle_hash.as_ref(), &[0xff; 36]].concat(),
+        };
 
-    pub(super) fn block_hash_cf(&self) -> &ColumnFamily {
-        self.0.cf_handle("block_hash").unwrap()
-    }
+        let iter = self.inner.iterator_cf_opt(
+            self.puzzle_hash_height_index_cf(),
+            options,
+            IteratorMode::From(&start, Direction::Reverse),
+        );
 
-    pub(super) fn coin_cf(&self) -> &ColumnFamily {
-        self.0.cf_handle("coins").unwrap()
-    }
+        let mut result = Vec::new();
 
-    pub(super) fn tail_cf(&self) -> &ColumnFamily {
-        self.0.cf_handle("tails").unwrap()
-    }
+        for item in iter {
+            if result.len() >= limit {
+                break;
+            }
 
-    pub(super) fn coin_spend_cf(&self) -> &ColumnFamily {
-        self.0.cf_handle("coin_spends").unwrap()
-    }
+            let (key, _) = item?;
 
-    pub(super) fn puzzle_hash_index_cf(&self) -> &ColumnFamily {
-        self.0.cf_handle("puzzle_hash_index").unwrap()
-    }
+            if !key.starts_with(puzzle_hash.as_ref()) {
+                break;
+            }
 
-    pub(super) fn parent_coin_id_index_cf(&self) -> &ColumnFamily {
-        self.0.cf_handle("parent_coin_id_index").unwrap()
-    }
+            if key.len() != 68 || key[..] == start[..] {
+                continue;
+            }
 
-    pub(super) fn hint_index_cf(&self) -> &ColumnFamily {
-        self.0.cf_handle("hint_index").unwrap()
-    }
+            let created_height = u32::from_be_bytes(key[32..36].try_into().unwrap());
+            let coin_id = Bytes32::try_from(&key[36..68]).unwrap();
 
-    pub(super) fn created_height_index_cf(&self) -> &ColumnFamily {
-        self.0.cf_handle("created_height_index").unwrap()
-    }
+            result.push((created_height, coin_id));
+        }
 
-    pub(super) fn spent_height_index_cf(&self) -> &ColumnFamily {
-        self.0.cf_handle("spent_height_index").unwrap()
+        Ok(result)
     }
 
-    pub fn transaction(&self) -> Transaction {
-        Transaction::new(self)
-    }
-}
+    /// The puzzle hash's spent coins from the highest spent height down, with
+    /// ties in ascending coin id order. The cursor is the spent height and
+    /// coin id of the last coin already seen.
+    ///
+    /// Only the heights the puzzle hash was active at are read, found from its
+    /// summary, and the coins spent at each are the intersection of the puzzle
+    /// hash and spent height indexes.
+    pub fn spent_coins_by_puzzle_hash(
+        &self,
+        puzzle_hash: Bytes32,
+        cursor: Option<(u32, Bytes32)>,
+        limit: usize,
+    ) -> Result<Vec<(u32, Bytes32)>> {
+        let mut options = ReadOptions::default();
+        options.set_total_order_seek(true);
+
+        let prefix = [puzzle_hash.as_ref(), &[SUMMARY_ACTIVITY_TAG]].concat();
+
+        let start = match cursor {
+            Some((spent_height, _)) => summary_activity_key(puzzle_hash, spent_height),
+            None => [prefix.as_slice(), &[0xff; 4]].concat(),
+        };
 
-fn concat_merge(
-    _new_key: &[u8],
-    existing_val: Option<&[u8]>,
-    operands: &MergeOperands,
+        let iter = self.inner.iterator_cf_opt(
+            self.puzzle_hash_summary_index_cf(),
+            options,
+            IteratorMode::From(&start, Direction::Reverse),
+        );
+
+        let mut result = Vec::new();
+
+        for item in iter {
+            if result.len() >= limit {
+                break;
+            }
+
+            let (key, value) = item?;
+
+            if !key.starts_with(&prefix) {
+                break;
+            }
+
+            let count = value[..]
+                .try_into()
+                .map(i128::from_le_bytes)
+                .unwrap_or_default();
+
+            if key.len() != 37 || count <= 0 {
+                continue;
+            }
+
+            let spent_height = u32::from_be_bytes(key[33..].try_into().unwrap());
+
+            let coin_ids = self.intersect_index_prefixes(
+                (self.puzzle_hash_index_cf(), puzzle_hash.as_ref()),
+                (self.spent_height_index_cf(), &spent_height.to_be_bytes()),
+                None,
+                usize::MAX,
+            )?;
+
+            for coin_id in coin_ids {
+                if result.len() >= limit {
+                    break;
+                }
+
+                if cursor.is_some_and(|(cursor_height, cursor_id)| {
+                    spent_height == cursor_height && coin_id <= cursor_id
+                }) {
+                    continue;
+                }
+
+                result.push((spent_height, coin_id));
+            }
+        }
+
+        Ok(result)
+    }
+
+    pub fn coins_by_created_height(&self, created_height: u32) -> Result<Vec<Bytes32>> {
+        self.index_page(
+            self.created_height_index_cf(),
+            &created_height.to_be_bytes(),
+            None,
+            usize::MAX,
+        )
+    }
+
+    pub fn coins_by_created_height_page(
+        &self,
+        created_height: u32,
+        cursor: Option<Bytes32>,
+        limit: usize,
+    ) -> Result<Vec<Bytes32>> {
+        self.index_page(
+            self.created_height_index_cf(),
+            &created_height.to_be_bytes(),
+            cursor,
+            limit,
+        )
+    }
+
+    pub fn count_coins_by_created_height(&self, created_height: u32) -> Result<usize> {
+        self.count_index_prefix(
+            self.created_height_index_cf(),
+            &created_height.to_be_bytes(),
+        )
+    }
+
+    pub fn count_coins_by_spent_height(&self, spent_height: u32) -> Result<usize> {
+        self.count_index_prefix(self.spent_height_index_cf(), &spent_height.to_be_bytes())
+    }
+
+    pub fn coins_by_spent_height(&self, spent_height: u32) -> Result<Vec<Bytes32>> {
+        self.index_page(
+            self.spent_height_index_cf(),
+            &spent_height.to_be_bytes(),
+            None,
+            usize::MAX,
+        )
+    }
+
+    pub fn coins_by_spent_height_page(
+        &self,
+        spent_height: u32,
+        cursor: Option<Bytes32>,
+        limit: usize,
+    ) -> Result<Vec<Bytes32>> {
+        self.index_page(
+            self.spent_height_index_cf(),
+            &spent_height.to_be_bytes(),
+            cursor,
+            limit,
+        )
+    }
+
+    pub fn coins_created_between(&self, from: u32, to: u32) -> Result<Vec<(u32, Bytes32)>> {
+        self.height_index_range(
+            self.created_height_index_cf(),
+            &from.to_be_bytes(),
+            to,
+            usize::MAX,
+        )
+    }
+
+    pub fn coins_spent_between(&self, from: u32, to: u32) -> Result<Vec<(u32, Bytes32)>> {
+        self.height_index_range(
+            self.spent_height_index_cf(),
+            &from.to_be_bytes(),
+            to,
+            usize::MAX,
+        )
+    }
+
+    /// Spends at heights `from..=to` in key order. The cursor is the
+    /// `spent_height || coin_id` key of the last spend already seen.
+    pub fn coins_spent_between_page(
+        &self,
+        from: u32,
+        to: u32,
+        cursor: Option<&[u8]>,
+        limit: usize,
+    ) -> Result<Vec<(u32, Bytes32)>> {
+        let from = from.to_be_bytes();
+        let start = cursor
+            .filter(|cursor| cursor[..] > from[..])
+            .unwrap_or(&from);
+        self.height_index_range(self.spent_height_index_cf(), start, to, limit)
+    }
+
+    /// Walks the spent height index backwards, newest spends first. The cursor
+    /// is the `spent_height || coin_id` key of the last spend already seen.
+    pub fn recently_spent(
+        &self,
+        cursor: Option<&[u8]>,
+        limit: usize,
+    ) -> Result<Vec<(u32, Bytes32)>> {
+        let mut options = ReadOptions::default();
+        options.set_total_order_seek(true);
+
+        let mode = match cursor {
+            Some(cursor) => IteratorMode::From(cursor, Direction::Reverse),
+            None => IteratorMode::End,
+        };
+
+        let iter = self
+            .inner
+            .iterator_cf_opt(self.spent_height_index_cf(), options, mode);
+
+        let mut result = Vec::new();
+
+        for item in iter {
+            if result.len() >= limit {
+                break;
+            }
+
+            let (key, _) = item?;
+
+            if key.len() != 36 || Some(&key[..]) == cursor {
+                continue;
+            }
+
+            let spent_height = u32::from_be_bytes(key[..4].try_into().unwrap());
+            let coin_id = Bytes32::try_from(&key[4..36]).unwrap();
+
+            result.push((spent_height, coin_id));
+        }
+
+        Ok(result)
+    }
+
+    /// The total value of the reward coins and of the block fees that haven't
+    /// been rolled back, or `None` if the database predates the totals and
+    /// `backfill-supply` hasn't been run.
+    pub fn supply(&self) -> Result<Option<Supply>> {
+        if !self.supply_backfilled.load(atomic::Ordering::Relaxed) {
+            return Ok(None);
+        }
+
+        let total = |key: &[u8]| -> Result<u128> {
+            let total = self
+                .inner
+                .get_cf(self.supply_cf(), key)?
+                .and_then(|value| value.as_slice().try_into().ok())
+                .map(i128::from_le_bytes)
+                .unwrap_or_default();
+            Ok(total.max(0) as u128)
+        };
+
+        Ok(Some(Supply {
+            issued: total(SUPPLY_ISSUED_KEY)?,
+            burned: total(SUPPLY_BURNED_KEY)?,
+        }))
+    }
+
+    /// Asset ids in ascending order after the cursor, with the number of CAT
+    /// coins created with each that haven't been rolled back. Coins indexed
+    /// before the counts were recorded aren't included.
+    pub fn cat_coin_counts(
+        &self,
+        cursor: Option<Bytes32>,
+        limit: usize,
+    ) -> Result<Vec<(Bytes32, u64)>> {
+        let mode = match &cursor {
+            Some(cursor) => IteratorMode::From(cursor.as_ref(), Direction::Forward),
+            None => IteratorMode::Start,
+        };
+
+        let mut result = Vec::new();
+
+        for item in self.inner.iterator_cf(self.cat_coin_count_cf(), mode) {
+            if result.len() >= limit {
+                break;
+            }
+
+            let (key, value) = item?;
+
+            let Ok(asset_id) = Bytes32::try_from(key.as_ref()) else {
+                continue;
+            };
+
+            if Some(asset_id) == cursor {
+                continue;
+            }
+
+            let count = value
+                .as_ref()
+                .try_into()
+                .map(i64::from_le_bytes)
+                .unwrap_or_default();
+
+            if count > 0 {
+                result.push((asset_id, count as u64));
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Launcher ids and the original names of singletons whose name starts with
+    /// the query. A singleton renamed by a later spend matches both names, but
+    /// a name is dropped once every spend that revealed it is rolled back.
+    pub fn singletons_by_name(&self, query: &str, limit: usize) -> Result<Vec<(Bytes32, String)>> {
+        let prefix = name_index_prefix(query);
+
+        let mut result = Vec::new();
+
+        let iter = self.inner.iterator_cf(
+            self.name_index_cf(),
+            IteratorMode::From(&prefix, Direction::Forward),
+        );
+
+        for item in iter {
+            if result.len() >= limit {
+                break;
+            }
+
+            let (key, value) = item?;
+
+            if !key.starts_with(&prefix) {
+                break;
+            }
+
+            let count = value
+                .as_ref()
+                .try_into()
+                .map(i64::from_le_bytes)
+                .unwrap_or_default();
+
+            if count <= 0 {
+                continue;
+            }
+
+            // The normalized name can't contain the terminator, so the first
+            // one ends it.
+            let Some(end) = key[prefix.len()..]
+                .iter()
+                .position(|&byte| byte == NAME_TERMINATOR)
+                .map(|position| prefix.len() + position)
+            else {
+                continue;
+            };
+
+            let Some(launcher_id) = key
+                .get(end + 1..end + 33)
+                .and_then(|bytes| Bytes32::try_from(bytes).ok())
+            else {
+                continue;
+            };
+
+            let name = String::from_utf8_lossy(&key[end + 33..]).into_owned();
+
+            result.push((launcher_id, name));
+        }
+
+        Ok(result)
+    }
+
+    /// Reads the `height || coin_id` keys of a height index up to height `to`,
+    /// starting at `start` (exclusive if it's a full key rather than a height).
+    fn height_index_range(
+        &self,
+        cf: &ColumnFamily,
+        start: &[u8],
+        to: u32,
+        limit: usize,
+    ) -> Result<Vec<(u32, Bytes32)>> {
+        let mut options = ReadOptions::default();
+        options.set_total_order_seek(true);
+
+        let iter =
+            self.inner
+                .iterator_cf_opt(cf, options, IteratorMode::From(start, Direction::Forward));
+
+        let mut result = Vec::new();
+
+        for item in iter {
+            if result.len() >= limit {
+                break;
+            }
+
+            let (key, _) = item?;
+
+            if key.len() != 36 || key[..] == *start {
+                continue;
+            }
+
+            let height = u32::from_be_bytes(key[..4].try_into().unwrap());
+
+            if height > to {
+                break;
+            }
+
+            result.push((height, Bytes32::try_from(&key[4..36]).unwrap()));
+        }
+
+        Ok(result)
+    }
+
+    fn multi_get<T>(
+        &self,
+        cf: &ColumnFamily,
+        keys: impl IntoIterator<Item = impl AsRef<[u8]>>,
+    ) -> Result<Vec<Option<T>>>
+    where
+        T: DeserializeOwned,
+    {
+        self.inner
+            .multi_get_cf(keys.into_iter().map(|key| (cf, key)))
+            .into_iter()
+            .map(|value| -> Result<Option<T>> {
+                Ok(value?
+                    .map(|bytes| pot::from_slice::<T>(&bytes))
+                    .transpose()?)
+            })
+            .collect()
+    }
+
+    /// Reads coin ids from an index keyed by `prefix || coin_id`, starting
+    /// after the cursor coin id if there is one.
+    ///
+    /// Every lookup of coin ids by index prefix goes through here, so they're
+    /// always returned in ascending byte order. That's what makes the coin id
+    /// cursors stable, and clients can rely on it when diffing responses.
+    fn index_page(
+        &self,
+        cf: &ColumnFamily,
+        prefix: &[u8],
+        cursor: Option<Bytes32>,
+        limit: usize,
+    ) -> Result<Vec<Bytes32>> {
+        let start_key = match cursor {
+            Some(cursor) => [prefix, cursor.as_ref()].concat(),
+            None => prefix.to_vec(),
+        };
+
+        let mut result = Vec::new();
+
+        let iter = self.inner.prefix_iterator_cf(cf, start_key);
+
+        for item in iter {
+            if result.len() >= limit {
+                break;
+            }
+
+            let (key, _) = item?;
+
+            if !key.starts_with(prefix) {
+                break;
+            }
+
+            if key.len() != prefix.len() + 32 {
+                continue;
+            }
+
+            let coin_id = Bytes32::try_from(&key[prefix.len()..]).unwrap();
+
+            if Some(coin_id) == cursor {
+                continue;
+            }
+
+            result.push(coin_id);
+        }
+
+        Ok(result)
+    }
+
+    /// The coin ids under both index prefixes, in ascending order and after
+    /// the cursor coin id if there is one, like `index_page`.
+    ///
+    /// Both indexes are ordered by coin id, so each side seeks straight to the
+    /// other's current coin id. That skips every key of the larger set that
+    /// can't match, and the smaller set bounds the number of seeks.
+    fn intersect_index_prefixes(
+        &self,
+        (cf_a, prefix_a): (&ColumnFamily, &[u8]),
+        (cf_b, prefix_b): (&ColumnFamily, &[u8]),
+        cursor: Option<Bytes32>,
+        limit: usize,
+    ) -> Result<Vec<Bytes32>> {
+        let mut a = self.inner.raw_iterator_cf(cf_a);
+        let mut b = self.inner.raw_iterator_cf(cf_b);
+
+        match cursor {
+            Some(cursor) => {
+                a.seek([prefix_a, cursor.as_ref()].concat());
+                b.seek([prefix_b, cursor.as_ref()].concat());
+            }
+            None => {
+                a.seek(prefix_a);
+                b.seek(prefix_b);
+            }
+        }
+
+        let mut result = Vec::new();
+
+        while let (Some(coin_id_a), Some(coin_id_b)) = (
+            raw_index_coin_id(&mut a, prefix_a),
+            raw_index_coin_id(&mut b, prefix_b),
+        ) {
+            if result.len() >= limit {
+                break;
+            }
+
+            match coin_id_a.cmp(&coin_id_b) {
+                Ordering::Equal if Some(coin_id_a) == cursor => {
+                    a.next();
+                    b.next();
+                }
+                Ordering::Equal => {
+                    result.push(coin_id_a);
+                    a.next();
+                    b.next();
+                }
+                Ordering::Less => a.seek([prefix_a, coin_id_b.as_ref()].concat()),
+                Ordering::Greater => b.seek([prefix_b, coin_id_a.as_ref()].concat()),
+            }
+        }
+
+        a.status()?;
+        b.status()?;
+
+        Ok(result)
+    }
+
+    /// Counts the `prefix || coin_id` keys of an index without decoding them.
+    fn count_index_prefix(&self, cf: &ColumnFamily, prefix: &[u8]) -> Result<usize> {
+        let mut count = 0;
+
+        for item in self.inner.prefix_iterator_cf(cf, prefix) {
+            let (key, _) = item?;
+
+            if !key.starts_with(prefix) {
+                break;
+            }
+
+            if key.len() == prefix.len() + 32 {
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Whether an index has any `prefix || coin_id` key, without reading past
+    /// the first one.
+    fn has_index_prefix(&self, cf: &ColumnFamily, prefix: &[u8]) -> Result<bool> {
+        let Some(item) = self.inner.prefix_iterator_cf(cf, prefix).next() else {
+            return Ok(false);
+        };
+
+        let (key, _) = item?;
+
+        Ok(key.starts_with(prefix) && key.len() == prefix.len() + 32)
+    }
+
+    fn scan_prefix(
+        &self,
+        cf: &ColumnFamily,
+        prefix: &[u8],
+        limit: usize,
+    ) -> Result<Vec<(Box<[u8]>, Box<[u8]>)>> {
+        let mut result = Vec::new();
+
+        let iter = self
+            .inner
+            .iterator_cf(cf, IteratorMode::From(prefix, Direction::Forward));
+
+        for item in iter {
+            if result.len() >= limit {
+                break;
+            }
+
+            let (key, value) = item?;
+
+            if !key.starts_with(prefix) {
+                break;
+            }
+
+            result.push((key, value));
+        }
+
+        Ok(result)
+    }
+
+    pub(super) fn block_cf(&self) -> &ColumnFamily {
+        self.inner.cf_handle("blocks").unwrap()
+    }
+
+    pub(super) fn block_hash_cf(&self) -> &ColumnFamily {
+        self.inner.cf_handle("block_hash").unwrap()
+    }
+
+    pub(super) fn coin_cf(&self) -> &ColumnFamily {
+        self.inner.cf_handle("coins").unwrap()
+    }
+
+    pub(super) fn tail_cf(&self) -> &ColumnFamily {
+        self.inner.cf_handle("tails").unwrap()
+    }
+
+    pub(super) fn coin_spend_cf(&self) -> &ColumnFamily {
+        self.inner.cf_handle("coin_spends").unwrap()
+    }
+
+    pub(super) fn puzzle_hash_index_cf(&self) -> &ColumnFamily {
+        self.inner.cf_handle("puzzle_hash_index").unwrap()
+    }
+
+    pub(super) fn puzzle_hash_height_index_cf(&self) -> &ColumnFamily {
+        self.inner.cf_handle("puzzle_hash_height_index").unwrap()
+    }
+
+    pub(super) fn puzzle_hash_summary_index_cf(&self) -> &ColumnFamily {
+        self.inner.cf_handle("puzzle_hash_summary_index").unwrap()
+    }
+
+    pub(super) fn parent_coin_id_index_cf(&self) -> &ColumnFamily {
+        self.inner.cf_handle("parent_coin_id_index").unwrap()
+    }
+
+    pub(super) fn hint_index_cf(&self) -> &ColumnFamily {
+        self.inner.cf_handle("hint_index").unwrap()
+    }
+
+    pub(super) fn amount_index_cf(&self) -> &ColumnFamily {
+        self.inner.cf_handle("amount_index").unwrap()
+    }
+
+    pub(super) fn created_height_index_cf(&self) -> &ColumnFamily {
+        self.inner.cf_handle("created_height_index").unwrap()
+    }
+
+    pub(super) fn spent_height_index_cf(&self) -> &ColumnFamily {
+        self.inner.cf_handle("spent_height_index").unwrap()
+    }
+
+    pub(super) fn offer_nonce_index_cf(&self) -> &ColumnFamily {
+        self.inner.cf_handle("offer_nonce_index").unwrap()
+    }
+
+    pub(super) fn name_index_cf(&self) -> &ColumnFamily {
+        self.inner.cf_handle("name_index").unwrap()
+    }
+
+    pub(super) fn cat_coin_count_cf(&self) -> &ColumnFamily {
+        self.inner.cf_handle("cat_coin_counts").unwrap()
+    }
+
+    pub(super) fn supply_cf(&self) -> &ColumnFamily {
+        self.inner.cf_handle("supply").unwrap()
+    }
+
+    pub(super) fn write_options(&self) -> WriteOptions {
+        let mut options = WriteOptions::default();
+        options.set_sync(self.durability == Durability::Sync);
+        options
+    }
+
+    /// Writes the memtables out and syncs the WAL, so that batches committed
+    /// with `Durability::Async` survive the process exiting.
+    pub fn flush(&self) -> Result<()> {
+        // A secondary has nothing of its own to flush.
+        if self.read_only {
+            return Ok(());
+        }
+
+        self.inner.flush_wal(true)?;
+        self.inner.flush()?;
+        Ok(())
+    }
+
+    pub fn transaction(&self) -> Transaction {
+        Transaction::new(self)
+    }
+}
+
+/// The options shared by primary and secondary instances, with a descriptor
+/// for each column family.
+fn open_options(
+    index_bloom_bits_per_key: u32,
+    spend_dictionary_bytes: u32,
+) -> Result<(Options, Vec<ColumnFamilyDescriptor>)> {
+    let dictionary_bytes = i32::try_from(spend_dictionary_bytes)
+        .context("`spend_dictionary_bytes` is too large for RocksDB")?;
+
+    let mut options = Options::default();
+    options.set_max_background_jobs(8);
+    options.increase_parallelism(num_cpus::get() as i32);
+    options.set_write_buffer_size(512 * 1024 * 1024);
+    options.set_max_write_buffer_number(6);
+    options.set_max_background_jobs(4);
+    options.set_target_file_size_base(64 * 1024 * 1024);
+    options.set_disable_auto_compactions(false);
+    options.prepare_for_bulk_load();
+
+    // Create column family descriptors with custom options
+    let cf_descriptors: Vec<ColumnFamilyDescriptor> = COLUMNS
+        .iter()
+        .map(|column| {
+            let mut cf_opts = Options::default();
+
+            // Optimize index column families
+            if let Some(prefix) = column.prefix {
+                cf_opts.set_prefix_extractor(rocksdb::SliceTransform::create_fixed_prefix(prefix));
+                cf_opts.set_memtable_prefix_bloom_ratio(0.1);
+
+                // Prefix blooms in each SST let a prefix scan skip files
+                // that don't contain the prefix at all. Index keys are
+                // only ever looked up by prefix, so whole keys aren't
+                // added to the filter.
+                if index_bloom_bits_per_key > 0 {
+                    let mut table_opts = BlockBasedOptions::default();
+                    table_opts.set_bloom_filter(f64::from(index_bloom_bits_per_key), false);
+                    table_opts.set_whole_key_filtering(false);
+                    cf_opts.set_block_based_table_factory(&table_opts);
+                }
+            }
+
+            cf_opts.set_compression_type(rocksdb::DBCompressionType::Lz4);
+
+            // Use different settings for coin data vs indexes
+            match column.merge {
+                Merge::Count => {
+                    cf_opts.set_merge_operator_associative("add operator", add_merge);
+                }
+                Merge::WideCount => {
+                    cf_opts.set_merge_operator_associative("wide add operator", wide_add_merge);
+                }
+                Merge::Concat => {
+                    cf_opts.set_merge_operator_associative("test operator", concat_merge);
+                }
+                Merge::None => {
+                    cf_opts.set_bottommost_compression_type(rocksdb::DBCompressionType::Zstd);
+                }
+            }
+
+            // Puzzle reveals of the same kind share most of their bytes, so
+            // zstd is given a dictionary trained on each bottommost file's
+            // own blocks rather than only seeing one block at a time.
+            if column.name == "coin_spends" && dictionary_bytes > 0 {
+                // The window bits, level and strategy are RocksDB's defaults.
+                cf_opts.set_bottommost_compression_options(-14, 32767, 0, dictionary_bytes, true);
+                cf_opts.set_bottommost_zstd_max_train_bytes(
+                    dictionary_bytes.saturating_mul(100),
+                    true,
+                );
+            }
+
+            ColumnFamilyDescriptor::new(column.name.to_string(), cf_opts)
+        })
+        .collect();
+
+    Ok((options, cf_descriptors))
+}
+
+/// The coin id of the iterator's current `prefix || coin_id` key, skipping any
+/// malformed keys, or `None` once it's past the prefix.
+fn raw_index_coin_id(iter: &mut DBRawIterator<'_>, prefix: &[u8]) -> Option<Bytes32> {
+    while let Some(key) = iter.key() {
+        if !key.starts_with(prefix) {
+            return None;
+        }
+
+        if key.len() == prefix.len() + 32 {
+            return Some(Bytes32::try_from(&key[prefix.len()..]).unwrap());
+        }
+
+        iter.next();
+    }
+
+    None
+}
+
+fn concat_merge(
+    _new_key: &[u8],
+    existing_val: Option<&[u8]>,
+    operands: &MergeOperands,
 ) -> Option<Vec<u8>> {
     let mut result: Vec<u8> = Vec::with_capacity(operands.len());
     if let Some(v) = existing_val {
@@ -347,3 +1869,303 @@ fn concat_merge(
     }
     Some(result)
 }
+
+fn add_merge(
+    _new_key: &[u8],
+    existing_val: Option<&[u8]>,
+    operands: &MergeOperands,
+) -> Option<Vec<u8>> {
+    let total = existing_val
+        .into_iter()
+        .chain(operands)
+        .filter_map(|value| value.try_into().ok().map(i64::from_le_bytes))
+        .sum::<i64>();
+    Some(total.to_le_bytes().to_vec())
+}
+
+fn wide_add_merge(
+    _new_key: &[u8],
+    existing_val: Option<&[u8]>,
+    operands: &MergeOperands,
+) -> Option<Vec<u8>> {
+    let total = existing_val
+        .into_iter()
+        .chain(operands)
+        .filter_map(|value| value.try_into().ok().map(i128::from_le_bytes))
+        .sum::<i128>();
+    Some(total.to_le_bytes().to_vec())
+}
+
+pub(super) fn summary_activity_key(puzzle_hash: Bytes32, height: u32) -> Vec<u8> {
+    [
+        puzzle_hash.as_ref(),
+        &[SUMMARY_ACTIVITY_TAG],
+        &height.to_be_bytes(),
+    ]
+    .concat()
+}
+
+const MAX_NAME_INDEX_LEN: usize = 64;
+
+/// Ends the normalized name in a name index key, so that a name's launcher id
+/// can't be mistaken for more of the name.
+const NAME_TERMINATOR: u8 = 0;
+
+/// The normalized name that name index keys start with, which is lowercased,
+/// stripped of the terminator and truncated so lookups are case insensitive
+/// prefix matches.
+pub(super) fn name_index_prefix(name: &str) -> Vec<u8> {
+    let name = name
+        .trim()
+        .to_lowercase()
+        .replace(char::from(NAME_TERMINATOR), "");
+
+    let mut end = name.len().min(MAX_NAME_INDEX_LEN);
+
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    name.as_bytes()[..end].to_vec()
+}
+
+/// Keyed by `normalized name || terminator || launcher_id || name`, with the
+/// original name kept for the response. Values count the spends revealing it.
+pub(super) fn name_index_key(name: &str, launcher_id: Bytes32) -> Vec<u8> {
+    [
+        name_index_prefix(name).as_slice(),
+        &[NAME_TERMINATOR],
+        launcher_id.as_ref(),
+        name.as_bytes(),
+    ]
+    .concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use chia::protocol::Bytes;
+
+    use crate::db::{insert_test_chain, test_coin, test_hash, CoinSpendRow};
+
+    use super::*;
+
+    fn naming_spend(spent_height: u32, name: &str, launcher_id: Bytes32) -> CoinSpendRow {
+        CoinSpendRow {
+            spent_height,
+            puzzle_reveal: Bytes::default(),
+            solution: Bytes::default(),
+            mod_hash: None,
+            offer_coins: Vec::new(),
+            singleton_name: Some((name.to_string(), launcher_id)),
+        }
+    }
+
+    #[test]
+    fn name_index_prefixes_are_normalized() {
+        assert_eq!(name_index_prefix("  Alice "), b"alice");
+        assert_eq!(name_index_prefix("a\0b"), b"ab");
+        assert_eq!(
+            name_index_prefix(&"x".repeat(100)).len(),
+            MAX_NAME_INDEX_LEN
+        );
+
+        // Truncation doesn't split a character.
+        let name = format!("{}é", "x".repeat(MAX_NAME_INDEX_LEN - 1));
+        assert_eq!(
+            name_index_prefix(&name),
+            "x".repeat(MAX_NAME_INDEX_LEN - 1).as_bytes()
+        );
+    }
+
+    #[test]
+    fn name_search_doesnt_read_launcher_ids_as_names() -> Result<()> {
+        let db = Database::open_temp()?;
+        let coin_ids = insert_test_chain(&db, 2, 2, Default::default())?;
+
+        // Without a terminator, "Ab" with this launcher id would read as a
+        // name starting with "abc".
+        let mut launcher_id = [0; 32];
+        launcher_id[0] = b'c';
+        let launcher_id = Bytes32::new(launcher_id);
+
+        let mut tx = db.transaction();
+        tx.put_coin_spend(coin_ids[0], &naming_spend(1, "Ab", launcher_id))?;
+        tx.put_coin_spend(coin_ids[1], &naming_spend(1, "Abc", test_hash(1, 1)))?;
+        tx.commit()?;
+
+        assert_eq!(
+            db.singletons_by_name("abc", 10)?,
+            vec![(test_hash(1, 1), "Abc".to_string())]
+        );
+        assert_eq!(db.singletons_by_name("ab", 10)?.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn names_are_dropped_once_every_spend_revealing_them_is_rolled_back() -> Result<()> {
+        let db = Database::open_temp()?;
+        let coin_ids = insert_test_chain(&db, 3, 1, Default::default())?;
+        let launcher_id = test_hash(5, 5);
+
+        let mut tx = db.transaction();
+        tx.put_coin_spend(coin_ids[0], &naming_spend(1, "Alice", launcher_id))?;
+        tx.put_coin_spend(coin_ids[1], &naming_spend(2, "Alice", launcher_id))?;
+        tx.commit()?;
+
+        let alice = vec![(launcher_id, "Alice".to_string())];
+        assert_eq!(db.singletons_by_name("ali", 10)?, alice);
+
+        db.rollback_to(1)?;
+        assert_eq!(db.singletons_by_name("ali", 10)?, alice);
+
+        db.rollback_to(0)?;
+        assert_eq!(db.singletons_by_name("ali", 10)?, Vec::new());
+
+        Ok(())
+    }
+
+    #[test]
+    fn index_lookups_are_ordered_the_same_across_calls() -> Result<()> {
+        let db = Database::open_temp()?;
+        let puzzle_hash = test_hash(7, 7);
+        let mut coin_ids = insert_test_chain(&db, 4, 5, puzzle_hash)?;
+
+        let first = db.coins_by_puzzle_hash(puzzle_hash, PuzzleHashOrder::CoinId)?;
+        assert_eq!(
+            first,
+            db.coins_by_puzzle_hash(puzzle_hash, PuzzleHashOrder::CoinId)?
+        );
+
+        coin_ids.sort();
+        assert_eq!(first, coin_ids);
+
+        let mut paged = Vec::new();
+        let mut cursor = None;
+
+        loop {
+            let page = db.coins_by_puzzle_hash_page(puzzle_hash, cursor, 3)?;
+
+            let Some(&last) = page.last() else {
+                break;
+            };
+
+            paged.extend(page);
+            cursor = Some(last);
+        }
+
+        assert_eq!(paged, coin_ids);
+
+        Ok(())
+    }
+
+    #[test]
+    fn indexes_enabled_on_an_existing_database_are_built_by_a_rebuild() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("db");
+        let open =
+            |indexes: &[IndexKind]| Database::new(&path, Durability::Async, indexes, 4, 10, 0, 16);
+
+        let db = open(&[IndexKind::PuzzleHash])?;
+        assert!(db.is_index_built(IndexKind::PuzzleHash));
+        insert_test_chain(&db, 2, 2, test_hash(7, 7))?;
+        drop(db);
+
+        let db = open(&[IndexKind::PuzzleHash, IndexKind::PuzzleHashHeight])?;
+        assert!(db.is_index_built(IndexKind::PuzzleHash));
+        assert!(db.is_index_enabled(IndexKind::PuzzleHashHeight));
+        assert!(!db.is_index_built(IndexKind::PuzzleHashHeight));
+
+        db.rebuild_index(IndexKind::PuzzleHashHeight)?;
+        assert!(db.is_index_built(IndexKind::PuzzleHashHeight));
+        drop(db);
+
+        let db = open(&[IndexKind::PuzzleHashHeight])?;
+        assert!(!db.is_index_built(IndexKind::PuzzleHash));
+        assert!(db.is_index_built(IndexKind::PuzzleHashHeight));
+        drop(db);
+
+        let db = open(&[IndexKind::PuzzleHash, IndexKind::PuzzleHashHeight])?;
+        assert!(!db.is_index_built(IndexKind::PuzzleHash));
+
+        Ok(())
+    }
+
+    #[test]
+    fn an_interrupted_puzzle_hash_summary_rebuild_starts_over() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("db");
+        let open =
+            |indexes: &[IndexKind]| Database::new(&path, Durability::Async, indexes, 4, 10, 0, 16);
+
+        let puzzle_hash = test_hash(7, 7);
+
+        let db = open(&[])?;
+        let coin_ids = insert_test_chain(&db, 2, 2, puzzle_hash)?;
+        drop(db);
+
+        let db = open(&[IndexKind::PuzzleHashSummary])?;
+
+        // A rebuild that committed its first coin and was then interrupted.
+        let covered = coin_ids.iter().copied().min().unwrap();
+        let covered_row = db.coin(covered)?.unwrap();
+
+        let mut tx = db.transaction();
+        tx.add_to_puzzle_hash_summary(&covered_row, 1)?;
+        tx.set_rebuild_progress(IndexKind::PuzzleHashSummary, covered)?;
+        tx.commit()?;
+
+        // Syncing in the meantime adds coins to the unbuilt summary, but skips
+        // the spends.
+        let (coin_id, row) = test_coin(test_hash(8, 8), puzzle_hash, 1000, 2);
+
+        let mut tx = db.transaction();
+        tx.put_coin(coin_id, &row)?;
+        tx.set_spent_height(covered, &covered_row, Some(2))?;
+        tx.commit()?;
+
+        db.rebuild_index(IndexKind::PuzzleHashSummary)?;
+
+        let summary = db.puzzle_hash_summary(puzzle_hash)?;
+        assert_eq!(summary.coins, 5);
+        assert_eq!(summary.received, 5000);
+        assert_eq!(summary.spent_coins, 1);
+        assert_eq!(summary.sent, 1000);
+
+        Ok(())
+    }
+
+    #[test]
+    fn puzzle_hash_summary_is_built_by_a_rebuild_and_checked_when_read() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("db");
+        let open =
+            |indexes: &[IndexKind]| Database::new(&path, Durability::Async, indexes, 4, 10, 0, 16);
+
+        let puzzle_hash = test_hash(7, 7);
+
+        let db = open(&[])?;
+        insert_test_chain(&db, 2, 2, puzzle_hash)?;
+        drop(db);
+
+        let db = open(&[IndexKind::PuzzleHashSummary])?;
+        assert!(!db.is_index_built(IndexKind::PuzzleHashSummary));
+
+        db.rebuild_index(IndexKind::PuzzleHashSummary)?;
+        assert!(db.is_index_built(IndexKind::PuzzleHashSummary));
+
+        let summary = db.puzzle_hash_summary(puzzle_hash)?;
+        assert_eq!(summary.received, 4000);
+        assert_eq!(summary.coins, 4);
+        assert_eq!(summary.sent, 0);
+
+        db.inner.merge_cf(
+            db.puzzle_hash_summary_index_cf(),
+            [puzzle_hash.as_ref(), &[SUMMARY_SENT_TAG]].concat(),
+            5000i128.to_le_bytes(),
+        )?;
+        assert!(db.puzzle_hash_summary(puzzle_hash).is_err());
+
+        Ok(())
+    }
+}
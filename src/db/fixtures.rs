@@ -0,0 +1,168 @@
+use std::ops::Deref;
+
+use anyhow::Result;
+use chia::protocol::{Bytes32, Coin};
+use tempfile::TempDir;
+
+use super::{BlockRow, CoinRow, CoinType, Database, Durability, IndexKind, TransactionInfo};
+
+/// A database in its own temporary directory, which is deleted once this is
+/// dropped.
+pub struct TempDatabase {
+    db: Database,
+    _dir: TempDir,
+}
+
+impl Deref for TempDatabase {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        &self.db
+    }
+}
+
+impl Database {
+    /// Opens an empty database with every index enabled, for tests.
+    pub fn open_temp() -> Result<TempDatabase> {
+        let dir = tempfile::tempdir()?;
+
+        let db = Database::new(
+            dir.path().join("db"),
+            Durability::Async,
+            &IndexKind::ALL,
+            4,
+            10,
+            0,
+            16,
+        )?;
+
+        Ok(TempDatabase { db, _dir: dir })
+    }
+}
+
+/// A transaction block at `height`, with a header hash derived from it.
+pub fn test_block(height: u32) -> BlockRow {
+    BlockRow {
+        header_hash: test_hash(height, 0),
+        weight: u128::from(height) + 1,
+        total_iters: u128::from(height) * 1000,
+        prev_block_hash: test_hash(height.wrapping_sub(1), 0),
+        farmer_puzzle_hash: Bytes32::default(),
+        pool_puzzle_hash: None,
+        transaction_info: Some(TransactionInfo {
+            timestamp: 1_700_000_000 + u64::from(height) * 20,
+            fees: 0,
+            cost: 0,
+            additions: 0,
+            removals: 0,
+            prev_transaction_block_hash: test_hash(height.wrapping_sub(1), 0),
+            additions_root: None,
+            removals_root: None,
+            created_value: None,
+            spent_value: None,
+        }),
+        signage: None,
+    }
+}
+
+/// An unspent coin created at `height`, with its id.
+pub fn test_coin(
+    parent_coin_id: Bytes32,
+    puzzle_hash: Bytes32,
+    amount: u64,
+    height: u32,
+) -> (Bytes32, CoinRow) {
+    let coin_id = Coin::new(parent_coin_id, puzzle_hash, amount).coin_id();
+
+    let row = CoinRow {
+        parent_coin_id,
+        puzzle_hash,
+        amount,
+        hint: None,
+        memos: None,
+        kind: CoinType::Unknown,
+        created_height: height,
+        creator_type: None,
+        spent_height: None,
+        created_index: None,
+    };
+
+    (coin_id, row)
+}
+
+/// A distinct hash for each pair of numbers.
+pub fn test_hash(a: u32, b: u32) -> Bytes32 {
+    let mut bytes = [0; 32];
+    bytes[..4].copy_from_slice(&a.to_be_bytes());
+    bytes[4..8].copy_from_slice(&b.to_be_bytes());
+    bytes[31] = 1;
+    Bytes32::new(bytes)
+}
+
+/// Writes blocks `0..count`, each creating `coins_per_block` coins with the
+/// puzzle hash, and moves the peak to the last of them. Returns the coin ids
+/// in the order they were created.
+pub fn insert_test_chain(
+    db: &Database,
+    count: u32,
+    coins_per_block: u32,
+    puzzle_hash: Bytes32,
+) -> Result<Vec<Bytes32>> {
+    let mut tx = db.transaction();
+    let mut coin_ids = Vec::new();
+
+    for height in 0..count {
+        tx.put_block(height, &test_block(height))?;
+
+        for index in 0..coins_per_block {
+            let (coin_id, row) = test_coin(test_hash(height, index + 1), puzzle_hash, 1000, height);
+            tx.put_coin(coin_id, &row)?;
+            coin_ids.push(coin_id);
+        }
+    }
+
+    if count > 0 {
+        tx.set_peak_height(count - 1)?;
+    }
+
+    tx.commit()?;
+
+    Ok(coin_ids)
+}
+
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_a_block_and_reads_it_back() -> Result<()> {
+        let db = Database::open_temp()?;
+
+        assert_eq!(db.peak_height()?, None);
+
+        let mut tx = db.transaction();
+        tx.put_block(0, &test_block(0))?;
+        tx.set_peak_height(0)?;
+        tx.commit()?;
+
+        assert_eq!(db.peak_height()?, Some(0));
+        assert_eq!(db.block(0)?, Some(test_block(0)));
+        assert_eq!(db.block(1)?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_chain_coins_are_readable() -> Result<()> {
+        let db = Database::open_temp()?;
+        let coin_ids = insert_test_chain(&db, 3, 2, Bytes32::default())?;
+
+        assert_eq!(coin_ids.len(), 6);
+        assert_eq!(db.peak_height()?, Some(2));
+
+        for coin_id in coin_ids {
+            assert!(db.coin(coin_id)?.is_some());
+        }
+
+        Ok(())
+    }
+}
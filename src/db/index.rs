@@ -0,0 +1,306 @@
+use std::{str::FromStr, sync::atomic::Ordering};
+
+use anyhow::{bail, Result};
+use chia::protocol::Bytes32;
+use rocksdb::{ColumnFamily, Direction, IteratorMode, DB};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use super::{CoinRow, CoinSpendRow, Database};
+
+const REBUILD_BATCH_SIZE: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexKind {
+    PuzzleHash,
+    PuzzleHashHeight,
+    /// Running totals and activity heights for each puzzle hash, rather than
+    /// coin ids.
+    PuzzleHashSummary,
+    ParentCoinId,
+    Hint,
+    CreatedHeight,
+    SpentHeight,
+    Amount,
+}
+
+impl IndexKind {
+    pub const ALL: [Self; 8] = [
+        Self::PuzzleHash,
+        Self::PuzzleHashHeight,
+        Self::PuzzleHashSummary,
+        Self::ParentCoinId,
+        Self::Hint,
+        Self::CreatedHeight,
+        Self::SpentHeight,
+        Self::Amount,
+    ];
+
+    /// The height indexes are needed to roll back reorgs, so they're always
+    /// maintained regardless of which indexes are enabled.
+    pub fn is_required(self) -> bool {
+        matches!(self, Self::CreatedHeight | Self::SpentHeight)
+    }
+
+    /// Whether the index was written whenever it was enabled from before
+    /// indexes were marked as built, so a database opened without markers is
+    /// trusted to have it. The others were added later (or, for the hint
+    /// index, changed to include the created height), and an existing
+    /// database only has them once they're rebuilt.
+    fn predates_built_markers(self) -> bool {
+        matches!(self, Self::PuzzleHash | Self::ParentCoinId)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::PuzzleHash => "puzzle_hash",
+            Self::PuzzleHashHeight => "puzzle_hash_height",
+            Self::PuzzleHashSummary => "puzzle_hash_summary",
+            Self::ParentCoinId => "parent_coin_id",
+            Self::Hint => "hint",
+            Self::CreatedHeight => "created_height",
+            Self::SpentHeight => "spent_height",
+            Self::Amount => "amount",
+        }
+    }
+}
+
+impl FromStr for IndexKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "puzzle_hash" => Self::PuzzleHash,
+            "puzzle_hash_height" => Self::PuzzleHashHeight,
+            "puzzle_hash_summary" => Self::PuzzleHashSummary,
+            "parent_coin_id" => Self::ParentCoinId,
+            "hint" => Self::Hint,
+            "created_height" => Self::CreatedHeight,
+            "spent_height" => Self::SpentHeight,
+            "amount" => Self::Amount,
+            _ => bail!("unknown index kind: {s}"),
+        })
+    }
+}
+
+impl Database {
+    /// Repopulates a secondary index from the coin (or coin spend) column family.
+    ///
+    /// The puzzle hash summary counts a coin as spent from its row's spent
+    /// height, so on a database that predates those it should be rebuilt
+    /// after `backfill-spent-heights` (or the backfill run after it).
+    ///
+    /// Progress is committed alongside each batch, so an interrupted rebuild
+    /// picks up after the last committed coin id the next time it's run. The
+    /// puzzle hash summary always starts over instead, since its totals are
+    /// merged rather than overwritten, and coins synced in the meantime would
+    /// be counted twice (or their spends missed) by a resumed rebuild.
+    pub fn rebuild_index(&self, kind: IndexKind) -> Result<()> {
+        if !self.is_index_enabled(kind) {
+            bail!("the {} index is disabled in the config", kind.name());
+        }
+
+        // The summary counts spends from the coin rows' spent heights.
+        if kind == IndexKind::PuzzleHashSummary
+            && !self.spent_heights_backfilled.load(Ordering::Relaxed)
+        {
+            bail!("the spent heights need to be backfilled with `backfill-spent-heights` first");
+        }
+
+        let resume_from = match kind {
+            IndexKind::PuzzleHashSummary => None,
+            _ => self.rebuild_progress(kind)?,
+        };
+
+        let mut tx = self.transaction();
+
+        if let Some(coin_id) = resume_from {
+            info!("Resuming {} index rebuild after {}", kind.name(), coin_id);
+        } else {
+            info!("Rebuilding {} index", kind.name());
+            tx.clear_index(kind)?;
+            tx.set_index_built(kind, false)?;
+        }
+
+        let source_cf = match kind {
+            IndexKind::SpentHeight => self.coin_spend_cf(),
+            _ => self.coin_cf(),
+        };
+
+        let start_key = resume_from
+            .map(|coin_id| coin_id.to_vec())
+            .unwrap_or_default();
+
+        let iter = self.inner.iterator_cf(
+            source_cf,
+            IteratorMode::From(&start_key, Direction::Forward),
+        );
+
+        let mut processed = 0;
+        let mut pending = 0;
+
+        for item in iter {
+            let (key, value) = item?;
+
+            let coin_id = Bytes32::try_from(&key[..])?;
+
+            if Some(coin_id) == resume_from {
+                continue;
+            }
+
+            if kind == IndexKind::SpentHeight {
+                let spend = pot::from_slice::<CoinSpendRow>(&value)?;
+                tx.add_to_spent_height_index(spend.spent_height, coin_id)?;
+            } else {
+                let coin = pot::from_slice::<CoinRow>(&value)?;
+
+                match kind {
+                    IndexKind::PuzzleHash => {
+                        tx.add_to_puzzle_hash_index(coin.puzzle_hash, coin_id)?;
+                    }
+                    IndexKind::PuzzleHashHeight => {
+                        tx.add_to_puzzle_hash_height_index(
+                            coin.puzzle_hash,
+                            coin.created_height,
+                            coin_id,
+                        )?;
+                    }
+                    IndexKind::PuzzleHashSummary => {
+                        tx.add_to_puzzle_hash_summary(&coin, 1)?;
+                    }
+                    IndexKind::ParentCoinId => {
+                        tx.add_to_parent_coin_id_index(coin.parent_coin_id, coin_id)?;
+                    }
+                    IndexKind::Hint => {
+                        if let Some(hint) = coin.hint {
+                            tx.add_to_hint_index(hint, coin.created_height, coin_id)?;
+                        }
+                    }
+                    IndexKind::CreatedHeight => {
+                        tx.add_to_created_height_index(coin.created_height, coin_id)?;
+                    }
+                    IndexKind::Amount => {
+                        tx.add_to_amount_index(coin.amount, coin_id)?;
+                    }
+                    IndexKind::SpentHeight => unreachable!(),
+                }
+            }
+
+            processed += 1;
+            pending += 1;
+
+            if pending >= REBUILD_BATCH_SIZE {
+                tx.set_rebuild_progress(kind, coin_id)?;
+                tx.commit()?;
+
+                tx = self.transaction();
+                pending = 0;
+
+                info!("Rebuilt {} entries of {} index", processed, kind.name());
+            }
+        }
+
+        tx.clear_rebuild_progress(kind)?;
+        tx.set_index_built(kind, true)?;
+        tx.commit()?;
+
+        let mut built_indexes = self.built_indexes.write().unwrap();
+
+        if !built_indexes.contains(&kind) {
+            built_indexes.push(kind);
+        }
+
+        info!(
+            "Finished rebuilding {} index with {} entries",
+            kind.name(),
+            processed
+        );
+
+        Ok(())
+    }
+
+    pub fn is_index_enabled(&self, kind: IndexKind) -> bool {
+        kind.is_required() || self.enabled_indexes.contains(&kind)
+    }
+
+    /// Whether the index is enabled and has every coin in it, so it can be
+    /// read. An enabled index is still written before then, but it's only
+    /// complete on a database created with it or once it's been rebuilt.
+    pub fn is_index_built(&self, kind: IndexKind) -> bool {
+        kind.is_required()
+            || (self.is_index_enabled(kind) && self.built_indexes.read().unwrap().contains(&kind))
+    }
+
+    /// Reads which indexes are built from their markers. A new database marks
+    /// every enabled index, and opening with an index disabled clears its
+    /// marker, since entries are missed for as long as it's off.
+    pub(super) fn read_built_indexes(
+        db: &DB,
+        enabled_indexes: &[IndexKind],
+        read_only: bool,
+    ) -> Result<Vec<IndexKind>> {
+        let new_database = db.get(b"peak_height")?.is_none();
+        let has_markers = db.get(BUILT_MARKERS_KEY)?.is_some();
+
+        let mut built = Vec::new();
+
+        for kind in IndexKind::ALL {
+            let enabled = kind.is_required() || enabled_indexes.contains(&kind);
+            let marked = db.get(built_marker_key(kind))?.is_some();
+
+            let is_built = enabled
+                && (marked || new_database || (!has_markers && kind.predates_built_markers()));
+
+            if !read_only {
+                if is_built && !marked {
+                    db.put(built_marker_key(kind), [])?;
+                } else if !enabled && marked {
+                    db.delete(built_marker_key(kind))?;
+                }
+            }
+
+            if is_built {
+                built.push(kind);
+            }
+        }
+
+        if !read_only && !has_markers {
+            db.put(BUILT_MARKERS_KEY, [])?;
+        }
+
+        Ok(built)
+    }
+
+    pub fn rebuild_progress(&self, kind: IndexKind) -> Result<Option<Bytes32>> {
+        let coin_id = self.inner.get(rebuild_progress_key(kind))?;
+        Ok(coin_id
+            .map(|bytes| Bytes32::try_from(bytes.as_slice()))
+            .transpose()?)
+    }
+
+    pub(super) fn index_cf(&self, kind: IndexKind) -> &ColumnFamily {
+        match kind {
+            IndexKind::PuzzleHash => self.puzzle_hash_index_cf(),
+            IndexKind::PuzzleHashHeight => self.puzzle_hash_height_index_cf(),
+            IndexKind::PuzzleHashSummary => self.puzzle_hash_summary_index_cf(),
+            IndexKind::ParentCoinId => self.parent_coin_id_index_cf(),
+            IndexKind::Hint => self.hint_index_cf(),
+            IndexKind::CreatedHeight => self.created_height_index_cf(),
+            IndexKind::SpentHeight => self.spent_height_index_cf(),
+            IndexKind::Amount => self.amount_index_cf(),
+        }
+    }
+}
+
+pub(super) fn rebuild_progress_key(kind: IndexKind) -> Vec<u8> {
+    format!("rebuild_index:{}", kind.name()).into_bytes()
+}
+
+/// Set once the database has markers for the indexes that were built before
+/// they were recorded.
+const BUILT_MARKERS_KEY: &[u8] = b"index_built_markers";
+
+pub(super) fn built_marker_key(kind: IndexKind) -> Vec<u8> {
+    format!("index_built:{}", kind.name()).into_bytes()
+}
@@ -0,0 +1,66 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use anyhow::Result;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use super::Database;
+
+/// Bounds how many reads run against RocksDB at once, so that a burst of
+/// range scans queues up rather than contending with each other for the disk.
+#[derive(Debug)]
+pub struct ReadLimiter {
+    semaphore: Arc<Semaphore>,
+    limit: usize,
+    waiting: AtomicUsize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadStats {
+    pub limit: usize,
+    pub in_flight: usize,
+    pub waiting: usize,
+}
+
+impl ReadLimiter {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(limit)),
+            limit,
+            waiting: AtomicUsize::new(0),
+        }
+    }
+}
+
+struct WaitingGuard<'a>(&'a AtomicUsize);
+
+impl Drop for WaitingGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Database {
+    /// Waits for one of the read permits, which should be held for the
+    /// duration of a range scan.
+    pub async fn read_permit(&self) -> Result<OwnedSemaphorePermit> {
+        let limiter = &self.read_limiter;
+
+        limiter.waiting.fetch_add(1, Ordering::Relaxed);
+        let _waiting = WaitingGuard(&limiter.waiting);
+
+        Ok(limiter.semaphore.clone().acquire_owned().await?)
+    }
+
+    pub fn read_stats(&self) -> ReadStats {
+        let limiter = &self.read_limiter;
+
+        ReadStats {
+            limit: limiter.limit,
+            in_flight: limiter.limit - limiter.semaphore.available_permits(),
+            waiting: limiter.waiting.load(Ordering::Relaxed),
+        }
+    }
+}
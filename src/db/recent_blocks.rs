@@ -0,0 +1,106 @@
+use std::collections::VecDeque;
+
+use super::BlockRow;
+
+/// The newest block rows, kept in memory so that the first page of blocks
+/// in reverse doesn't have to read RocksDB. The heights are contiguous and
+/// ascending, and only ever describe committed blocks.
+#[derive(Debug)]
+pub(super) struct RecentBlocks {
+    capacity: usize,
+    blocks: VecDeque<(u32, BlockRow)>,
+}
+
+impl RecentBlocks {
+    pub(super) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            blocks: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub(super) fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Replaces every block held here with a contiguous run of them.
+    pub(super) fn reset(&mut self, blocks: &[(u32, BlockRow)]) {
+        self.blocks.clear();
+        self.apply(&[], blocks);
+    }
+
+    /// The blocks above `start_height` up to and including `end_height`,
+    /// newest first, or `None` unless every one of them is held here.
+    pub(super) fn reverse_range(
+        &self,
+        start_height: u32,
+        end_height: u32,
+    ) -> Option<Vec<BlockRow>> {
+        let &(first, _) = self.blocks.front()?;
+        let &(last, _) = self.blocks.back()?;
+
+        if end_height != last || start_height.saturating_add(1) < first {
+            return None;
+        }
+
+        Some(
+            self.blocks
+                .iter()
+                .rev()
+                .take_while(|(height, _)| *height > start_height)
+                .map(|(_, block)| *block)
+                .collect(),
+        )
+    }
+
+    /// Applies a committed transaction's deleted and written blocks. A block
+    /// that was deleted without being written again drops it and everything
+    /// above it, which keeps the heights contiguous.
+    pub(super) fn apply(&mut self, deleted: &[u32], written: &[(u32, BlockRow)]) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let deleted_from = deleted
+            .iter()
+            .copied()
+            .filter(|height| !written.iter().any(|(written, _)| written == height))
+            .min();
+
+        if let Some(deleted_from) = deleted_from {
+            while self
+                .blocks
+                .back()
+                .is_some_and(|(height, _)| *height >= deleted_from)
+            {
+                self.blocks.pop_back();
+            }
+        }
+
+        for &(height, block) in written {
+            self.insert(height, block);
+        }
+    }
+
+    fn insert(&mut self, height: u32, block: BlockRow) {
+        match self.blocks.back() {
+            Some(&(last, _)) if height <= last => {
+                // A block rewritten in place, such as by a reindex.
+                if let Some(&(first, _)) = self.blocks.front() {
+                    if height >= first {
+                        self.blocks[(height - first) as usize].1 = block;
+                    }
+                }
+                return;
+            }
+            Some(&(last, _)) if height != last + 1 => self.blocks.clear(),
+            _ => {}
+        }
+
+        if self.blocks.len() == self.capacity {
+            self.blocks.pop_front();
+        }
+
+        self.blocks.push_back((height, block));
+    }
+}
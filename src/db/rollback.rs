@@ -0,0 +1,89 @@
+use anyhow::Result;
+use tracing::info;
+
+use super::Database;
+
+impl Database {
+    /// Removes every block above `height`, along with the coins created and
+    /// the spends made in them, and makes `height` the new peak.
+    pub fn rollback_to(&self, height: u32) -> Result<()> {
+        let Some(peak_height) = self.peak_height()? else {
+            return Ok(());
+        };
+
+        let mut tx = self.transaction();
+
+        for rollback_height in (height + 1)..=peak_height {
+            for coin_id in self.coins_by_spent_height(rollback_height)? {
+                if let Some(spend) = self.coin_spend(coin_id)? {
+                    tx.delete_coin_spend(coin_id, &spend)?;
+                }
+
+                // Coins created above `height` are deleted below instead.
+                if let Some(coin) = self
+                    .coin(coin_id)?
+                    .filter(|coin| coin.created_height <= height)
+                {
+                    tx.set_spent_height(coin_id, &coin, None)?;
+                }
+            }
+
+            for coin_id in self.coins_by_created_height(rollback_height)? {
+                if let Some(coin) = self.coin(coin_id)? {
+                    tx.delete_coin(coin_id, &coin)?;
+                }
+            }
+
+            if let Some(block) = self.block(rollback_height)? {
+                tx.delete_block(rollback_height, &block)?;
+            }
+        }
+
+        tx.set_peak_height(height)?;
+        tx.commit()?;
+
+        info!("Rolled back from height {} to {}", peak_height, height);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chia::protocol::Bytes;
+
+    use crate::db::{insert_test_chain, test_hash, CoinSpendRow};
+
+    use super::*;
+
+    #[test]
+    fn rollback_removes_the_offer_coins_of_rolled_back_spends() -> Result<()> {
+        let db = Database::open_temp()?;
+        let coin_ids = insert_test_chain(&db, 3, 1, Default::default())?;
+        let nonce = test_hash(9, 9);
+
+        let mut tx = db.transaction();
+        tx.put_coin_spend(
+            coin_ids[1],
+            &CoinSpendRow {
+                spent_height: 2,
+                puzzle_reveal: Bytes::default(),
+                solution: Bytes::default(),
+                mod_hash: None,
+                offer_coins: vec![(nonce, coin_ids[2])],
+                singleton_name: None,
+            },
+        )?;
+        tx.commit()?;
+
+        assert_eq!(db.coins_by_offer_nonce(nonce, None, 10)?, vec![coin_ids[2]]);
+
+        db.rollback_to(1)?;
+
+        assert_eq!(db.coins_by_offer_nonce(nonce, None, 10)?, Vec::new());
+        assert_eq!(db.coin_spend(coin_ids[1])?, None);
+        assert_eq!(db.coins_by_spent_height(2)?, Vec::new());
+
+        Ok(())
+    }
+}
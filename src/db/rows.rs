@@ -1,6 +1,10 @@
 use chia::protocol::{Bytes, Bytes32};
 use serde::{Deserialize, Serialize};
 
+/// Bumped whenever the rows or indexes gain data that clients can gate
+/// features on, such as a new index or a newly recorded field.
+pub const DATA_VERSION: u32 = 5;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BlockRow {
     pub header_hash: Bytes32,
@@ -10,6 +14,25 @@ pub struct BlockRow {
     pub farmer_puzzle_hash: Bytes32,
     pub pool_puzzle_hash: Option<Bytes32>,
     pub transaction_info: Option<TransactionInfo>,
+    /// `None` for blocks indexed before this was recorded, until they're
+    /// reindexed.
+    #[serde(default)]
+    pub signage: Option<SignageInfo>,
+}
+
+/// Where the block was infused in its sub-slot, from its reward chain block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignageInfo {
+    pub signage_point_index: u8,
+    /// The challenge of the sub-slot the proof of space was found in.
+    pub sub_slot_challenge: Bytes32,
+    /// The sub-slots that ended between the previous block and this one.
+    pub finished_sub_slots: u32,
+    /// The challenge chain VDF iterations to the signage point, which is
+    /// `None` for a signage point at the start of a sub-slot.
+    pub sp_iterations: Option<u64>,
+    /// The challenge chain VDF iterations to the infusion point.
+    pub ip_iterations: u64,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -20,6 +43,16 @@ pub struct TransactionInfo {
     pub additions: u32,
     pub removals: u32,
     pub prev_transaction_block_hash: Bytes32,
+    #[serde(default)]
+    pub additions_root: Option<Bytes32>,
+    #[serde(default)]
+    pub removals_root: Option<Bytes32>,
+    /// The total amount of the coins created in the block, including rewards.
+    #[serde(default, with = "optional_amount")]
+    pub created_value: Option<u128>,
+    /// The total amount of the coins spent in the block.
+    #[serde(default, with = "optional_amount")]
+    pub spent_value: Option<u128>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -32,6 +65,35 @@ pub struct CoinRow {
     #[serde(rename = "type", flatten)]
     pub kind: CoinType,
     pub created_height: u32,
+    /// The kind of puzzle that created the coin, which is `None` for reward
+    /// coins and for coins indexed before this was recorded.
+    #[serde(default)]
+    pub creator_type: Option<CreatorType>,
+    /// Kept in step with the coin spend column family, so that a single read
+    /// yields the spent status. It's `None` on rows written before this was
+    /// recorded, until `backfill-spent-heights` is run.
+    #[serde(default)]
+    pub spent_height: Option<u32>,
+    /// The coin's position among the coins its block created, in the order
+    /// the block created them, with the reward coins first. It's `None` for
+    /// coins indexed before this was recorded.
+    #[serde(default)]
+    pub created_index: Option<u32>,
+}
+
+/// Classifies the parent puzzle by its outer layers alone. Anything that isn't
+/// a recognized layer, including custom inner puzzles wrapped by a standard
+/// layer further in, is classified as `Unknown`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CreatorType {
+    Standard,
+    Cat,
+    Nft,
+    Did,
+    /// A singleton that isn't an NFT or DID, or a singleton launcher.
+    Singleton,
+    Unknown,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -62,4 +124,149 @@ pub struct CoinSpendRow {
     pub spent_height: u32,
     pub puzzle_reveal: Bytes,
     pub solution: Bytes,
+    /// The mod hash of the puzzle's outermost layer (its tree hash if it isn't
+    /// curried), which is `None` for spends indexed before this was recorded.
+    #[serde(default)]
+    pub mod_hash: Option<Bytes32>,
+    /// The `(nonce, coin_id)` entries the spend added to the offer nonce
+    /// index, kept so that rolling the spend back can remove them. Spends
+    /// indexed before these were recorded leave theirs behind.
+    #[serde(default)]
+    pub offer_coins: Vec<(Bytes32, Bytes32)>,
+    /// The `(name, launcher_id)` the spend added to the name index, for the
+    /// same reason.
+    #[serde(default)]
+    pub singleton_name: Option<(String, Bytes32)>,
+}
+
+/// Amount totals are written as strings in JSON, since JavaScript numbers
+/// can't represent them exactly, but as integers in the database.
+mod optional_amount {
+    use std::fmt;
+
+    use serde::{
+        de::{self, Visitor},
+        Deserializer, Serializer,
+    };
+
+    pub fn serialize<S>(value: &Option<u128>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match value {
+            Some(value) if serializer.is_human_readable() => {
+                serializer.serialize_some(&value.to_string())
+            }
+            Some(value) => serializer.serialize_some(value),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<u128>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_option(OptionalAmountVisitor)
+    }
+
+    struct OptionalAmountVisitor;
+
+    impl<'de> Visitor<'de> for OptionalAmountVisitor {
+        type Value = Option<u128>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+            formatter.write_str("an optional amount")
+        }
+
+        fn visit_none<E>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_unit<E>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_any(AmountVisitor).map(Some)
+        }
+    }
+
+    struct AmountVisitor;
+
+    impl Visitor<'_> for AmountVisitor {
+        type Value = u128;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+            formatter.write_str("an amount as an integer or a string")
+        }
+
+        fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E> {
+            Ok(value.into())
+        }
+
+        fn visit_u128<E>(self, value: u128) -> Result<Self::Value, E> {
+            Ok(value)
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            value.parse().map_err(E::custom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::db::test_block;
+
+    fn transaction_info(created_value: Option<u128>) -> TransactionInfo {
+        TransactionInfo {
+            created_value,
+            spent_value: None,
+            ..test_block(1).transaction_info.unwrap()
+        }
+    }
+
+    #[test]
+    fn amounts_are_strings_in_json() {
+        let info = transaction_info(Some(u128::MAX));
+        let value = serde_json::to_value(&info).unwrap();
+
+        assert_eq!(value["created_value"], json!(u128::MAX.to_string()));
+        assert_eq!(value["spent_value"], json!(null));
+        assert_eq!(
+            serde_json::from_value::<TransactionInfo>(value).unwrap(),
+            info
+        );
+    }
+
+    #[test]
+    fn amounts_are_read_from_integers_and_missing_fields() {
+        let mut value = serde_json::to_value(transaction_info(None)).unwrap();
+        value["created_value"] = json!(1000);
+        value.as_object_mut().unwrap().remove("spent_value");
+
+        let info = serde_json::from_value::<TransactionInfo>(value).unwrap();
+
+        assert_eq!(info.created_value, Some(1000));
+        assert_eq!(info.spent_value, None);
+    }
+
+    #[test]
+    fn amounts_round_trip_through_the_database_encoding() {
+        for created_value in [None, Some(0), Some(u128::MAX)] {
+            let info = transaction_info(created_value);
+            let bytes = pot::to_vec(&info).unwrap();
+
+            assert_eq!(pot::from_slice::<TransactionInfo>(&bytes).unwrap(), info);
+        }
+    }
 }
@@ -0,0 +1,84 @@
+use anyhow::Result;
+use serde::Serialize;
+
+use super::{Database, COLUMNS};
+
+/// RocksDB's own estimates, summed over every column family.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DbStats {
+    pub estimated_keys: u64,
+    pub memtable_bytes: u64,
+    pub live_sst_bytes: u64,
+    pub sst_files: u64,
+    pub pending_compaction_bytes: u64,
+    pub running_compactions: u64,
+}
+
+/// RocksDB's estimates for a single column family.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ColumnStats {
+    pub name: &'static str,
+    pub estimated_keys: u64,
+    pub memtable_bytes: u64,
+    pub live_sst_bytes: u64,
+    pub sst_files: u64,
+    pub pending_compaction_bytes: u64,
+}
+
+const MAX_LEVELS: u32 = 7;
+
+impl Database {
+    /// Reads RocksDB's properties for each column family, which are kept in
+    /// memory, so this is cheap enough to call on every metrics scrape.
+    pub fn column_stats(&self) -> Result<Vec<ColumnStats>> {
+        let mut result = Vec::new();
+
+        for column in COLUMNS {
+            let Some(cf) = self.inner.cf_handle(column.name) else {
+                continue;
+            };
+
+            let property = |name: &str| -> Result<u64> {
+                Ok(self.inner.property_int_value_cf(cf, name)?.unwrap_or(0))
+            };
+
+            let mut sst_files = 0;
+
+            for level in 0..MAX_LEVELS {
+                sst_files += property(&format!("rocksdb.num-files-at-level{level}"))?;
+            }
+
+            result.push(ColumnStats {
+                name: column.name,
+                estimated_keys: property("rocksdb.estimate-num-keys")?,
+                memtable_bytes: property("rocksdb.cur-size-all-mem-tables")?,
+                live_sst_bytes: property("rocksdb.live-sst-files-size")?,
+                sst_files,
+                pending_compaction_bytes: property("rocksdb.estimate-pending-compaction-bytes")?,
+            });
+        }
+
+        Ok(result)
+    }
+
+    /// The column stats summed, along with database-wide compaction activity.
+    pub fn stats(&self) -> Result<DbStats> {
+        let mut stats = DbStats::default();
+
+        for column in self.column_stats()? {
+            stats.estimated_keys += column.estimated_keys;
+            stats.memtable_bytes += column.memtable_bytes;
+            stats.live_sst_bytes += column.live_sst_bytes;
+            stats.sst_files += column.sst_files;
+            stats.pending_compaction_bytes += column.pending_compaction_bytes;
+        }
+
+        // Compactions are scheduled for the whole database, not per column.
+        stats.running_compactions = self
+            .inner
+            .property_int_value("rocksdb.num-running-compactions")?
+            .unwrap_or(0);
+
+        Ok(stats)
+    }
+}
@@ -1,12 +1,24 @@
+use std::collections::HashMap;
+
 use anyhow::Result;
 use chia::protocol::{Bytes, Bytes32};
 use rocksdb::WriteBatch;
 
-use super::{BlockRow, CoinRow, CoinSpendRow, Database};
+use super::{
+    built_marker_key, name_index_key, rebuild_progress_key, summary_activity_key, BlockRow,
+    CoinRow, CoinSpendRow, CoinType, Database, IndexKind, Supply, SUMMARY_COINS_TAG,
+    SUMMARY_RECEIVED_TAG, SUMMARY_SENT_TAG, SUMMARY_SPENT_COINS_TAG, SUPPLY_BURNED_KEY,
+    SUPPLY_ISSUED_KEY,
+};
 
 pub struct Transaction<'a> {
     db: &'a Database,
     batch: WriteBatch,
+    deleted_blocks: Vec<u32>,
+    written_blocks: Vec<(u32, BlockRow)>,
+    /// Spent heights set in this transaction, which reads of the coin rows
+    /// don't see until it's committed.
+    spent_heights: HashMap<Bytes32, Option<u32>>,
 }
 
 impl<'a> Transaction<'a> {
@@ -14,6 +26,9 @@ impl<'a> Transaction<'a> {
         Self {
             db,
             batch: WriteBatch::new(),
+            deleted_blocks: Vec::new(),
+            written_blocks: Vec::new(),
+            spent_heights: HashMap::new(),
         }
     }
 
@@ -28,6 +43,7 @@ impl<'a> Transaction<'a> {
             height.to_be_bytes(),
             pot::to_vec(block)?,
         );
+        self.written_blocks.push((height, *block));
 
         self.batch.put_cf(
             self.db.block_hash_cf(),
@@ -35,6 +51,10 @@ impl<'a> Transaction<'a> {
             height.to_be_bytes(),
         );
 
+        if let Some(transaction_info) = block.transaction_info {
+            self.add_to_supply(SUPPLY_BURNED_KEY, transaction_info.fees.into())?;
+        }
+
         Ok(())
     }
 
@@ -42,15 +62,89 @@ impl<'a> Transaction<'a> {
         self.batch
             .put_cf(self.db.coin_cf(), coin_id, pot::to_vec(coin)?);
 
-        self.add_to_puzzle_hash_index(coin.puzzle_hash, coin_id)?;
-        self.add_to_parent_coin_id_index(coin.parent_coin_id, coin_id)?;
+        if self.db.is_index_enabled(IndexKind::PuzzleHash) {
+            self.add_to_puzzle_hash_index(coin.puzzle_hash, coin_id)?;
+        }
 
-        if let Some(hint) = coin.hint {
-            self.add_to_hint_index(hint, coin_id)?;
+        if self.db.is_index_enabled(IndexKind::PuzzleHashHeight) {
+            self.add_to_puzzle_hash_height_index(coin.puzzle_hash, coin.created_height, coin_id)?;
+        }
+
+        if self.db.is_index_enabled(IndexKind::PuzzleHashSummary) {
+            self.add_to_puzzle_hash_summary(coin, 1)?;
+        }
+
+        if self.db.is_index_enabled(IndexKind::ParentCoinId) {
+            self.add_to_parent_coin_id_index(coin.parent_coin_id, coin_id)?;
+        }
+
+        if let Some(hint) = coin
+            .hint
+            .filter(|_| self.db.is_index_enabled(IndexKind::Hint))
+        {
+            self.add_to_hint_index(hint, coin.created_height, coin_id)?;
         }
 
         self.add_to_created_height_index(coin.created_height, coin_id)?;
 
+        if self.db.is_index_enabled(IndexKind::Amount) {
+            self.add_to_amount_index(coin.amount, coin_id)?;
+        }
+
+        if let CoinType::Cat { asset_id, .. } = coin.kind {
+            self.add_to_cat_coin_count(asset_id, 1)?;
+        }
+
+        if coin.kind == CoinType::Reward {
+            self.add_to_supply(SUPPLY_ISSUED_KEY, coin.amount.into())?;
+        }
+
+        Ok(())
+    }
+
+    /// Overwrites a coin's row without touching the indexes, for changes to
+    /// fields that aren't indexed.
+    fn update_coin(&mut self, coin_id: Bytes32, coin: &CoinRow) -> Result<()> {
+        self.batch
+            .put_cf(self.db.coin_cf(), coin_id, pot::to_vec(coin)?);
+        Ok(())
+    }
+
+    /// Overwrites the spent height of a coin's committed row, moving the coin
+    /// between spent and unspent in its puzzle hash's summary. A summary that
+    /// isn't built yet is left alone, since the coin may not be in it, and
+    /// its rebuild reads the spent height from the row.
+    pub fn set_spent_height(
+        &mut self,
+        coin_id: Bytes32,
+        coin: &CoinRow,
+        spent_height: Option<u32>,
+    ) -> Result<()> {
+        let old_spent_height = self
+            .spent_heights
+            .insert(coin_id, spent_height)
+            .unwrap_or(coin.spent_height);
+
+        if self.db.is_index_built(IndexKind::PuzzleHashSummary) {
+            if let Some(old_spent_height) = old_spent_height {
+                self.add_spend_to_puzzle_hash_summary(coin, old_spent_height, -1);
+            }
+
+            if let Some(spent_height) = spent_height {
+                self.add_spend_to_puzzle_hash_summary(coin, spent_height, 1);
+            }
+        }
+
+        let coin = CoinRow {
+            spent_height,
+            ..coin.clone()
+        };
+
+        self.update_coin(coin_id, &coin)
+    }
+
+    pub fn set_spent_heights_backfilled(&mut self) -> Result<()> {
+        self.batch.put(b"spent_heights_backfilled", []);
         Ok(())
     }
 
@@ -65,6 +159,106 @@ impl<'a> Transaction<'a> {
 
         self.add_to_spent_height_index(coin_spend.spent_height, coin_id)?;
 
+        for &(nonce, offer_coin_id) in &coin_spend.offer_coins {
+            self.add_to_offer_nonce_index(nonce, offer_coin_id)?;
+        }
+
+        if let Some((name, launcher_id)) = &coin_spend.singleton_name {
+            self.add_to_name_index(name, *launcher_id, 1)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn delete_block(&mut self, height: u32, block: &BlockRow) -> Result<()> {
+        self.batch
+            .delete_cf(self.db.block_cf(), height.to_be_bytes());
+        self.deleted_blocks.push(height);
+        self.batch
+            .delete_cf(self.db.block_hash_cf(), block.header_hash.as_ref());
+
+        if let Some(transaction_info) = block.transaction_info {
+            self.add_to_supply(SUPPLY_BURNED_KEY, -i128::from(transaction_info.fees))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn delete_coin(&mut self, coin_id: Bytes32, coin: &CoinRow) -> Result<()> {
+        self.batch.delete_cf(self.db.coin_cf(), coin_id);
+
+        self.batch.delete_cf(
+            self.db.puzzle_hash_index_cf(),
+            [coin.puzzle_hash.as_ref(), coin_id.as_ref()].concat(),
+        );
+        self.batch.delete_cf(
+            self.db.puzzle_hash_height_index_cf(),
+            [
+                coin.puzzle_hash.as_ref(),
+                &coin.created_height.to_be_bytes(),
+                coin_id.as_ref(),
+            ]
+            .concat(),
+        );
+        self.batch.delete_cf(
+            self.db.parent_coin_id_index_cf(),
+            [coin.parent_coin_id.as_ref(), coin_id.as_ref()].concat(),
+        );
+
+        if self.db.is_index_enabled(IndexKind::PuzzleHashSummary) {
+            self.add_to_puzzle_hash_summary(coin, -1)?;
+        }
+
+        if let Some(hint) = coin.hint {
+            self.batch.delete_cf(
+                self.db.hint_index_cf(),
+                [
+                    hint.as_ref(),
+                    &coin.created_height.to_be_bytes(),
+                    coin_id.as_ref(),
+                ]
+                .concat(),
+            );
+        }
+
+        self.batch.delete_cf(
+            self.db.created_height_index_cf(),
+            [&coin.created_height.to_be_bytes(), coin_id.as_ref()].concat(),
+        );
+        self.batch.delete_cf(
+            self.db.amount_index_cf(),
+            [&coin.amount.to_be_bytes(), coin_id.as_ref()].concat(),
+        );
+
+        if let CoinType::Cat { asset_id, .. } = coin.kind {
+            self.add_to_cat_coin_count(asset_id, -1)?;
+        }
+
+        if coin.kind == CoinType::Reward {
+            self.add_to_supply(SUPPLY_ISSUED_KEY, -i128::from(coin.amount))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn delete_coin_spend(&mut self, coin_id: Bytes32, coin_spend: &CoinSpendRow) -> Result<()> {
+        self.batch.delete_cf(self.db.coin_spend_cf(), coin_id);
+        self.batch.delete_cf(
+            self.db.spent_height_index_cf(),
+            [&coin_spend.spent_height.to_be_bytes(), coin_id.as_ref()].concat(),
+        );
+
+        for (nonce, offer_coin_id) in &coin_spend.offer_coins {
+            self.batch.delete_cf(
+                self.db.offer_nonce_index_cf(),
+                [nonce.as_ref(), offer_coin_id.as_ref()].concat(),
+            );
+        }
+
+        if let Some((name, launcher_id)) = &coin_spend.singleton_name {
+            self.add_to_name_index(name, *launcher_id, -1)?;
+        }
+
         Ok(())
     }
 
@@ -78,6 +272,69 @@ impl<'a> Transaction<'a> {
         Ok(())
     }
 
+    /// Keyed by `puzzle_hash || created_height || coin_id`, so that a puzzle
+    /// hash's coins can be read in height order.
+    pub fn add_to_puzzle_hash_height_index(
+        &mut self,
+        puzzle_hash: Bytes32,
+        created_height: u32,
+        coin_id: Bytes32,
+    ) -> Result<()> {
+        let key = [
+            puzzle_hash.as_ref(),
+            &created_height.to_be_bytes(),
+            coin_id.as_ref(),
+        ]
+        .concat();
+        self.batch
+            .put_cf(self.db.puzzle_hash_height_index_cf(), &key, []);
+        Ok(())
+    }
+
+    /// Adds a coin, and its spend if it has one, to its puzzle hash's summary,
+    /// or takes them back out with a `sign` of `-1`.
+    pub fn add_to_puzzle_hash_summary(&mut self, coin: &CoinRow, sign: i128) -> Result<()> {
+        let puzzle_hash = coin.puzzle_hash;
+
+        self.merge_summary(
+            &[puzzle_hash.as_ref(), &[SUMMARY_RECEIVED_TAG]].concat(),
+            sign * i128::from(coin.amount),
+        );
+        self.merge_summary(&[puzzle_hash.as_ref(), &[SUMMARY_COINS_TAG]].concat(), sign);
+        self.merge_summary(
+            &summary_activity_key(puzzle_hash, coin.created_height),
+            sign,
+        );
+
+        if let Some(spent_height) = coin.spent_height {
+            self.add_spend_to_puzzle_hash_summary(coin, spent_height, sign);
+        }
+
+        Ok(())
+    }
+
+    fn add_spend_to_puzzle_hash_summary(&mut self, coin: &CoinRow, spent_height: u32, sign: i128) {
+        let puzzle_hash = coin.puzzle_hash;
+
+        self.merge_summary(
+            &[puzzle_hash.as_ref(), &[SUMMARY_SENT_TAG]].concat(),
+            sign * i128::from(coin.amount),
+        );
+        self.merge_summary(
+            &[puzzle_hash.as_ref(), &[SUMMARY_SPENT_COINS_TAG]].concat(),
+            sign,
+        );
+        self.merge_summary(&summary_activity_key(puzzle_hash, spent_height), sign);
+    }
+
+    fn merge_summary(&mut self, key: &[u8], delta: i128) {
+        self.batch.merge_cf(
+            self.db.puzzle_hash_summary_index_cf(),
+            key,
+            delta.to_le_bytes(),
+        );
+    }
+
     pub fn add_to_parent_coin_id_index(
         &mut self,
         parent_coin_id: Bytes32,
@@ -89,8 +346,20 @@ impl<'a> Transaction<'a> {
         Ok(())
     }
 
-    pub fn add_to_hint_index(&mut self, hint: Bytes32, coin_id: Bytes32) -> Result<()> {
-        let key = [hint.as_ref(), coin_id.as_ref()].concat();
+    /// Keyed by `hint || created_height || coin_id`, so that a hint's coins
+    /// can be paged in height order.
+    pub fn add_to_hint_index(
+        &mut self,
+        hint: Bytes32,
+        created_height: u32,
+        coin_id: Bytes32,
+    ) -> Result<()> {
+        let key = [
+            hint.as_ref(),
+            &created_height.to_be_bytes(),
+            coin_id.as_ref(),
+        ]
+        .concat();
         self.batch.put_cf(self.db.hint_index_cf(), &key, []);
         Ok(())
     }
@@ -112,8 +381,98 @@ impl<'a> Transaction<'a> {
         Ok(())
     }
 
+    pub fn add_to_amount_index(&mut self, amount: u64, coin_id: Bytes32) -> Result<()> {
+        let key = [&amount.to_be_bytes(), coin_id.as_ref()].concat();
+        self.batch.put_cf(self.db.amount_index_cf(), &key, []);
+        Ok(())
+    }
+
+    pub fn add_to_offer_nonce_index(&mut self, nonce: Bytes32, coin_id: Bytes32) -> Result<()> {
+        let key = [nonce.as_ref(), coin_id.as_ref()].concat();
+        self.batch.put_cf(self.db.offer_nonce_index_cf(), &key, []);
+        Ok(())
+    }
+
+    /// Counts the spends that revealed the name for the singleton, so that a
+    /// rollback only drops the entry once no remaining spend revealed it.
+    pub fn add_to_name_index(
+        &mut self,
+        name: &str,
+        launcher_id: Bytes32,
+        delta: i64,
+    ) -> Result<()> {
+        self.batch.merge_cf(
+            self.db.name_index_cf(),
+            name_index_key(name, launcher_id),
+            delta.to_le_bytes(),
+        );
+        Ok(())
+    }
+
+    pub fn add_to_cat_coin_count(&mut self, asset_id: Bytes32, delta: i64) -> Result<()> {
+        self.batch
+            .merge_cf(self.db.cat_coin_count_cf(), asset_id, delta.to_le_bytes());
+        Ok(())
+    }
+
+    pub fn add_to_supply(&mut self, key: &[u8], delta: i128) -> Result<()> {
+        self.batch
+            .merge_cf(self.db.supply_cf(), key, delta.to_le_bytes());
+        Ok(())
+    }
+
+    /// Overwrites the supply totals, for backfills that recount them.
+    pub fn set_supply(&mut self, supply: Supply) -> Result<()> {
+        self.batch.put_cf(
+            self.db.supply_cf(),
+            SUPPLY_ISSUED_KEY,
+            (supply.issued as i128).to_le_bytes(),
+        );
+        self.batch.put_cf(
+            self.db.supply_cf(),
+            SUPPLY_BURNED_KEY,
+            (supply.burned as i128).to_le_bytes(),
+        );
+        self.batch.put(b"supply_backfilled", []);
+        Ok(())
+    }
+
+    pub fn clear_index(&mut self, kind: IndexKind) -> Result<()> {
+        self.batch
+            .delete_range_cf(self.db.index_cf(kind), &[][..], &[0xff; 69][..]);
+        Ok(())
+    }
+
+    pub fn set_rebuild_progress(&mut self, kind: IndexKind, coin_id: Bytes32) -> Result<()> {
+        self.batch.put(rebuild_progress_key(kind), coin_id);
+        Ok(())
+    }
+
+    pub fn clear_rebuild_progress(&mut self, kind: IndexKind) -> Result<()> {
+        self.batch.delete(rebuild_progress_key(kind));
+        Ok(())
+    }
+
+    pub fn set_index_built(&mut self, kind: IndexKind, built: bool) -> Result<()> {
+        if built {
+            self.batch.put(built_marker_key(kind), []);
+        } else {
+            self.batch.delete(built_marker_key(kind));
+        }
+        Ok(())
+    }
+
     pub fn commit(self) -> Result<()> {
-        self.db.0.write(self.batch)?;
+        self.db
+            .inner
+            .write_opt(self.batch, &self.db.write_options())?;
+
+        self.db
+            .recent_blocks
+            .write()
+            .unwrap()
+            .apply(&self.deleted_blocks, &self.written_blocks);
+
         Ok(())
     }
 }
@@ -0,0 +1,167 @@
+use anyhow::{bail, Result};
+use rocksdb::IteratorMode;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use super::Database;
+
+/// How many blocks below the peak are checked for contiguity.
+const VERIFY_DEPTH: u32 = 100;
+
+/// What to do at startup if the database fails its integrity check.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntegrityCheck {
+    /// Skip the check entirely.
+    Off,
+    /// Log any problems and start anyway.
+    #[default]
+    Warn,
+    /// Roll back to the highest consistent height, which sync then re-indexes.
+    Repair,
+    /// Refuse to start until the database is fixed.
+    Refuse,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct IntegrityReport {
+    pub checked: bool,
+    pub peak_height: Option<u32>,
+    pub highest_block: Option<u32>,
+    /// The highest height up to which the checked blocks are contiguous.
+    pub consistent_height: Option<u32>,
+    pub problems: Vec<String>,
+    pub repaired: bool,
+}
+
+impl IntegrityReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl Database {
+    /// Checks that the stored peak has a block, isn't ahead of the highest
+    /// block row, and that the blocks just below it link up by header hash.
+    pub fn verify(&self) -> Result<IntegrityReport> {
+        let peak_height = self.peak_height()?;
+        let highest_block = self.highest_block_height()?;
+
+        let mut report = IntegrityReport {
+            checked: true,
+            peak_height,
+            highest_block,
+            consistent_height: peak_height,
+            ..Default::default()
+        };
+
+        let Some(peak_height) = peak_height else {
+            if let Some(highest_block) = highest_block {
+                report.problems.push(format!(
+                    "Block {highest_block} exists but no peak height is stored"
+                ));
+            }
+            return Ok(report);
+        };
+
+        let Some(highest_block) = highest_block else {
+            report
+                .problems
+                .push(format!("Peak height is {peak_height} but no blocks exist"));
+            report.consistent_height = None;
+            return Ok(report);
+        };
+
+        if peak_height > highest_block {
+            report.problems.push(format!(
+                "Peak height {peak_height} is ahead of the highest block {highest_block}"
+            ));
+        }
+
+        let start = peak_height.min(highest_block).saturating_sub(VERIFY_DEPTH);
+
+        let Some(mut previous) = self.block(start)? else {
+            report.problems.push(format!("Block {start} is missing"));
+            report.consistent_height = None;
+            return Ok(report);
+        };
+
+        let mut consistent_height = start;
+
+        for height in (start + 1)..=peak_height {
+            let Some(block) = self.block(height)? else {
+                report.problems.push(format!("Block {height} is missing"));
+                break;
+            };
+
+            if block.prev_block_hash != previous.header_hash {
+                report.problems.push(format!(
+                    "Block {height} doesn't follow block {}",
+                    height - 1
+                ));
+                break;
+            }
+
+            consistent_height = height;
+            previous = block;
+        }
+
+        report.consistent_height = Some(consistent_height);
+
+        Ok(report)
+    }
+
+    /// Runs `verify` and applies the configured policy to any problems found.
+    pub fn check_integrity(&self, mode: IntegrityCheck) -> Result<IntegrityReport> {
+        if mode == IntegrityCheck::Off {
+            return Ok(IntegrityReport::default());
+        }
+
+        let mut report = self.verify()?;
+
+        if report.is_ok() {
+            info!("Integrity check passed");
+            return Ok(report);
+        }
+
+        for problem in &report.problems {
+            warn!("Integrity check: {}", problem);
+        }
+
+        match mode {
+            IntegrityCheck::Off | IntegrityCheck::Warn => {}
+            IntegrityCheck::Repair if self.is_read_only() => {
+                warn!("Not repairing, since the database is read-only");
+            }
+            IntegrityCheck::Repair => {
+                let Some(consistent_height) = report.consistent_height else {
+                    bail!("integrity check failed and there is no consistent height to repair to");
+                };
+
+                self.rollback_to(consistent_height)?;
+                report.repaired = true;
+            }
+            IntegrityCheck::Refuse => bail!("integrity check failed"),
+        }
+
+        Ok(report)
+    }
+
+    fn highest_block_height(&self) -> Result<Option<u32>> {
+        let Some(item) = self
+            .inner
+            .iterator_cf(self.block_cf(), IteratorMode::End)
+            .next()
+        else {
+            return Ok(None);
+        };
+
+        let (key, _) = item?;
+
+        if key.len() != 4 {
+            bail!("invalid block height key");
+        }
+
+        Ok(Some(u32::from_be_bytes(key[..].try_into().unwrap())))
+    }
+}
@@ -0,0 +1,54 @@
+use anyhow::Result;
+use rocksdb::{Direction, IteratorMode};
+
+use super::Database;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WarmUp {
+    pub blocks: usize,
+    pub coins: usize,
+    pub bytes: usize,
+}
+
+impl Database {
+    /// Reads the most recent blocks and the coins created in them, so that the
+    /// first requests after a restart aren't served from a cold cache.
+    pub fn warm_up(&self, blocks: u32) -> Result<WarmUp> {
+        let mut warm_up = WarmUp::default();
+
+        let Some(peak_height) = self.peak_height()? else {
+            return Ok(warm_up);
+        };
+
+        if blocks == 0 {
+            return Ok(warm_up);
+        }
+
+        let start = peak_height.saturating_sub(blocks - 1);
+        let start_key = start.to_be_bytes();
+
+        for item in self.inner.iterator_cf(
+            self.block_cf(),
+            IteratorMode::From(&start_key, Direction::Forward),
+        ) {
+            let (key, value) = item?;
+            warm_up.blocks += 1;
+            warm_up.bytes += key.len() + value.len();
+        }
+
+        let coin_ids = self.coins_created_between(start, peak_height)?;
+
+        for chunk in coin_ids.chunks(1000) {
+            let values = self
+                .inner
+                .multi_get_cf(chunk.iter().map(|(_, coin_id)| (self.coin_cf(), coin_id)));
+
+            for value in values.into_iter().flatten().flatten() {
+                warm_up.coins += 1;
+                warm_up.bytes += value.len();
+            }
+        }
+
+        Ok(warm_up)
+    }
+}
@@ -0,0 +1,99 @@
+use std::time::Duration;
+
+use chia::protocol::Bytes32;
+use serde::Serialize;
+use tokio::{
+    sync::{broadcast, mpsc},
+    time::{sleep_until, Instant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// The timestamp (and how far behind the server clock it is) is only
+    /// present for transaction blocks.
+    Block {
+        height: u32,
+        header_hash: Bytes32,
+        timestamp: Option<u64>,
+        lag_seconds: Option<u64>,
+    },
+    /// Everything above `fork_height` was rolled back, and `peak_hash` is the
+    /// header hash of the block at `fork_height` that is now the peak.
+    Reorg {
+        fork_height: u32,
+        depth: u32,
+        peak_hash: Bytes32,
+    },
+}
+
+enum Next {
+    Event(Option<Event>),
+    Flush,
+}
+
+/// Forwards events to `events`, sending block events at most once per
+/// `interval`. A block that arrives too soon after the last one replaces any
+/// pending block, and the pending block is sent once the interval is up, so
+/// the latest peak is always delivered. Blocks further apart than `interval`,
+/// like those at the chain's usual pace, pass straight through.
+///
+/// Reorgs are never held back, but the pending block is sent before one to
+/// keep events in order.
+pub fn coalesce_blocks(
+    interval: Duration,
+    events: broadcast::Sender<Event>,
+) -> mpsc::UnboundedSender<Event> {
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        let mut last_sent: Option<Instant> = None;
+        let mut pending = None;
+
+        loop {
+            let next = match (&pending, last_sent) {
+                (Some(_), Some(last_sent)) => tokio::select! {
+                    event = receiver.recv() => Next::Event(event),
+                    () = sleep_until(last_sent + interval) => Next::Flush,
+                },
+                _ => Next::Event(receiver.recv().await),
+            };
+
+            let event = match next {
+                Next::Event(Some(event)) => event,
+                Next::Event(None) => break,
+                Next::Flush => {
+                    if let Some(event) = pending.take() {
+                        events.send(event).ok();
+                        last_sent = Some(Instant::now());
+                    }
+                    continue;
+                }
+            };
+
+            match event {
+                Event::Block { .. }
+                    if last_sent.is_some_and(|last_sent| last_sent.elapsed() < interval) =>
+                {
+                    pending = Some(event);
+                }
+                Event::Block { .. } => {
+                    events.send(event).ok();
+                    last_sent = Some(Instant::now());
+                }
+                Event::Reorg { .. } => {
+                    if let Some(block) = pending.take() {
+                        events.send(block).ok();
+                    }
+                    events.send(event).ok();
+                }
+            }
+        }
+
+        if let Some(event) = pending {
+            events.send(event).ok();
+        }
+    });
+
+    sender
+}
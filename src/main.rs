@@ -1,22 +1,35 @@
+mod clvm;
 mod config;
 mod db;
+mod events;
+mod merkle;
 mod process;
+mod replica;
 mod routes;
 mod sync;
 
-use std::{fs, io::Cursor};
+use std::{
+    env, fs,
+    io::Cursor,
+    net::SocketAddr,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use chia::{protocol::FullBlock, traits::Streamable};
 use chia_wallet_sdk::coinset::FullNodeClient;
 use config::Config;
 use db::Database;
+use events::coalesce_blocks;
+use itertools::Itertools;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use routes::{router, App};
+use replica::Replica;
+use routes::{router, App, Flights, IpLimiter};
 use sqlx::SqlitePool;
-use sync::Sync;
-use tokio::net::TcpListener;
-use tracing::{error, info};
+use sync::{IngestionErrorLog, ReorgLog, Sync, SyncStatus};
+use tokio::{net::TcpListener, signal, sync::broadcast};
+use tracing::{debug, error, info, warn};
 use tracing_subscriber::EnvFilter;
 use zstd::decode_all;
 
@@ -29,39 +42,236 @@ async fn main() -> Result<()> {
 
     let config = Config::load()?;
 
-    fs::create_dir_all(config.db_path.parent().unwrap())?;
+    #[cfg(feature = "sentry")]
+    let _sentry = match &config.sentry_dsn {
+        Some(dsn) => Some(sentry::init(sentry::ClientOptions {
+            dsn: Some(dsn.parse()?),
+            release: sentry::release_name!(),
+            ..Default::default()
+        })),
+        None => None,
+    };
 
-    let db = Database::new(&config.db_path)?;
+    #[cfg(not(feature = "sentry"))]
+    if config.sentry_dsn.is_some() {
+        warn!("`sentry_dsn` is set, but errors are only reported with the `sentry` feature");
+    }
 
-    let sqlite = SqlitePool::connect(&format!(
-        "sqlite://{}",
-        config.blockchain_db_path.to_str().unwrap()
-    ))
-    .await?;
-    let cert = fs::read(&config.cert_path)?;
-    let key = fs::read_to_string(&config.key_path)?;
-    let key = topk8::from_pkcs1_pem(&key).unwrap_or(key);
-    let rpc = FullNodeClient::new(&cert, key.as_bytes());
+    let db = if config.read_only {
+        let secondary_path = config
+            .secondary_path
+            .clone()
+            .unwrap_or_else(|| config.db_path.with_extension("secondary"));
+
+        fs::create_dir_all(&secondary_path)?;
+
+        Database::new_secondary(
+            &config.db_path,
+            &secondary_path,
+            &config.enabled_indexes,
+            config.max_concurrent_reads,
+            config.index_bloom_bits_per_key,
+            config.spend_dictionary_bytes,
+            config.recent_blocks,
+        )?
+    } else {
+        fs::create_dir_all(config.db_path.parent().unwrap())?;
 
-    let sync = Sync::new(db.clone(), config.clone(), sqlite, rpc);
+        Database::new(
+            &config.db_path,
+            config.durability,
+            &config.enabled_indexes,
+            config.max_concurrent_reads,
+            config.index_bloom_bits_per_key,
+            config.spend_dictionary_bytes,
+            config.recent_blocks,
+        )?
+    };
 
-    tokio::spawn(async move {
-        if let Err(error) = sync.start().await {
-            error!("Sync error: {}", error);
+    let args = env::args().skip(1).collect_vec();
+
+    match args.iter().map(String::as_str).collect_vec().as_slice() {
+        [] => {}
+        [_, ..] if config.read_only => {
+            bail!("commands write to the database, so they can't be run with `read_only`")
         }
-    });
+        ["rebuild-index", kind] => {
+            db.rebuild_index(kind.parse()?)?;
+            return Ok(());
+        }
+        ["compact-spends"] => {
+            db.compact_spends()?;
+            return Ok(());
+        }
+        ["backfill-spent-heights"] => {
+            db.backfill_spent_heights()?;
+            return Ok(());
+        }
+        ["backfill-supply"] => {
+            db.backfill_supply()?;
+            return Ok(());
+        }
+        _ => bail!(
+            "usage: postgresy [rebuild-index <kind> | compact-spends | backfill-spent-heights | backfill-supply]"
+        ),
+    }
+
+    let integrity = db.check_integrity(config.integrity_check)?;
+
+    if config.warm_up_blocks > 0 {
+        let start = Instant::now();
+        let warm_up = db.warm_up(config.warm_up_blocks)?;
+
+        info!(
+            "Warmed up {} blocks and {} coins ({} bytes) in {:?}",
+            warm_up.blocks,
+            warm_up.coins,
+            warm_up.bytes,
+            start.elapsed()
+        );
+    }
+
+    let sync_status = Arc::new(RwLock::new(SyncStatus::default()));
+    let reorgs = Arc::new(RwLock::new(ReorgLog::default()));
+    let ingestion_errors = Arc::new(RwLock::new(IngestionErrorLog::default()));
+    let (events, _) = broadcast::channel(1024);
+    let block_events = coalesce_blocks(
+        Duration::from_millis(config.event_coalesce_ms),
+        events.clone(),
+    );
+
+    let sync = if config.read_only {
+        tokio::spawn(
+            Replica::new(db.clone(), sync_status.clone(), block_events)
+                .start(Duration::from_millis(config.catch_up_interval_ms.max(1))),
+        );
+
+        None
+    } else {
+        let sqlite = SqlitePool::connect(&format!(
+            "sqlite://{}",
+            config.blockchain_db_path.to_str().unwrap()
+        ))
+        .await?;
+        let cert = fs::read(&config.cert_path)?;
+        let key = fs::read_to_string(&config.key_path)?;
+        let key = topk8::from_pkcs1_pem(&key).unwrap_or(key);
+        let rpc = FullNodeClient::new(&cert, key.as_bytes());
+
+        let sync = Arc::new(Sync::new(
+            db.clone(),
+            config.clone(),
+            sqlite,
+            rpc,
+            sync_status.clone(),
+            reorgs.clone(),
+            ingestion_errors.clone(),
+            block_events,
+        ));
+        tokio::spawn({
+            let sync = sync.clone();
+            async move {
+                if let Err(error) = sync.start().await {
+                    error!("Sync error: {}", error);
+                }
+            }
+        });
+
+        Some(sync)
+    };
+
+    let flights = Arc::new(Flights::default());
 
-    let app = router(App { db });
+    if config.single_flight_prune_seconds > 0 {
+        tokio::spawn({
+            let flights = flights.clone();
+            let period = Duration::from_secs(config.single_flight_prune_seconds);
+            async move {
+                let mut interval = tokio::time::interval(period);
+
+                loop {
+                    interval.tick().await;
+
+                    let pruned = flights.prune();
+
+                    if pruned > 0 {
+                        debug!("Pruned {} abandoned single-flight entries", pruned);
+                    }
+                }
+            }
+        });
+    }
+
+    let app = router(App {
+        db: db.clone(),
+        config: Arc::new(config.clone()),
+        sync_status,
+        reorgs,
+        ingestion_errors,
+        events,
+        integrity: Arc::new(integrity),
+        flights,
+        sync,
+        ip_limiter: Arc::new(IpLimiter::default()),
+    });
     let listener = TcpListener::bind(format!("0.0.0.0:{}", config.port)).await?;
     info!("Listening on {}", listener.local_addr().unwrap());
-    axum::serve(listener, app).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal())
+    .await?;
+
+    info!("Shutting down");
+
+    // A stuck disk shouldn't keep the process from exiting. Dropping the
+    // runtime would still wait for the blocking flush, so the process exits
+    // right away instead. With `Durability::Async` the WAL may not have been
+    // synced, which loses the newest batches, and those are synced again from
+    // the node on the next start.
+    let timeout = Duration::from_millis(config.shutdown_flush_timeout_ms);
+
+    match tokio::time::timeout(timeout, tokio::task::spawn_blocking(move || db.flush())).await {
+        Ok(result) => result??,
+        Err(_) => {
+            warn!("Database flush didn't finish within {:?}, exiting", timeout);
+            std::process::exit(1);
+        }
+    }
 
     Ok(())
 }
 
-fn parse_blocks(blocks: Vec<Vec<u8>>) -> Vec<FullBlock> {
-    blocks
-        .into_par_iter()
-        .map(|data| FullBlock::from_bytes(&decode_all(Cursor::new(data)).unwrap()).unwrap())
-        .collect()
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        signal::ctrl_c().await.ok();
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(_) => std::future::pending().await,
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = ctrl_c => {}
+        () = terminate => {}
+    }
+}
+
+fn parse_blocks(blocks: Vec<Vec<u8>>) -> Result<Vec<FullBlock>> {
+    blocks.into_par_iter().map(parse_block).collect()
+}
+
+/// Decodes a block blob from the node's database, which is zstd compressed.
+fn parse_block(data: Vec<u8>) -> Result<FullBlock> {
+    Ok(FullBlock::from_bytes(&decode_all(Cursor::new(data))?)?)
 }
@@ -0,0 +1,95 @@
+use chia::{
+    consensus::merkle_tree::MerkleSet,
+    protocol::{Bytes, Bytes32, Coin},
+    sha2::Sha256,
+};
+use indexmap::IndexMap;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AdditionsProof {
+    pub root: Bytes32,
+    pub puzzle_hash_proof: Bytes,
+    pub coin_ids_proof: Bytes,
+    pub coin_ids: Vec<Bytes32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RemovalsProof {
+    pub root: Bytes32,
+    pub proof: Bytes,
+}
+
+/// Builds the additions Merkle set the same way the full node does, with a
+/// leaf for each puzzle hash and a leaf for the hash of its coin ids, then
+/// proves inclusion of the given puzzle hash group.
+pub fn additions_proof(additions: &[Coin], puzzle_hash: Bytes32) -> Option<AdditionsProof> {
+    let mut groups = IndexMap::<Bytes32, Vec<Bytes32>>::new();
+
+    for coin in additions {
+        groups
+            .entry(coin.puzzle_hash)
+            .or_default()
+            .push(coin.coin_id());
+    }
+
+    let mut coin_ids = groups.get(&puzzle_hash)?.clone();
+    let coin_ids_hash = hash_coin_ids(&mut coin_ids);
+
+    let mut leafs: Vec<[u8; 32]> = Vec::new();
+
+    for (puzzle_hash, mut coin_ids) in groups {
+        leafs.push(puzzle_hash.into());
+        leafs.push(hash_coin_ids(&mut coin_ids).into());
+    }
+
+    let set = MerkleSet::from_leafs(&mut leafs);
+
+    let (true, puzzle_hash_proof) = set.generate_proof(&puzzle_hash.into()).ok()? else {
+        return None;
+    };
+
+    let (true, coin_ids_proof) = set.generate_proof(&coin_ids_hash.into()).ok()? else {
+        return None;
+    };
+
+    Some(AdditionsProof {
+        root: set.get_root().into(),
+        puzzle_hash_proof: Bytes::new(puzzle_hash_proof),
+        coin_ids_proof: Bytes::new(coin_ids_proof),
+        coin_ids,
+    })
+}
+
+pub fn removals_proof(removals: &[Bytes32], coin_id: Bytes32) -> Option<RemovalsProof> {
+    let mut leafs: Vec<[u8; 32]> = removals.iter().map(|&coin_id| coin_id.into()).collect();
+
+    let set = MerkleSet::from_leafs(&mut leafs);
+
+    let (true, proof) = set.generate_proof(&coin_id.into()).ok()? else {
+        return None;
+    };
+
+    Some(RemovalsProof {
+        root: set.get_root().into(),
+        proof: Bytes::new(proof),
+    })
+}
+
+fn hash_coin_ids(coin_ids: &mut [Bytes32]) -> Bytes32 {
+    if let [coin_id] = coin_ids {
+        let mut hasher = Sha256::new();
+        hasher.update(coin_id);
+        return hasher.finalize().into();
+    }
+
+    coin_ids.sort_unstable_by(|a, b| b.cmp(a));
+
+    let mut hasher = Sha256::new();
+
+    for coin_id in coin_ids {
+        hasher.update(coin_id);
+    }
+
+    hasher.finalize().into()
+}
@@ -13,7 +13,7 @@ use chia_wallet_sdk::types::run_puzzle;
 use clvmr::{serde::node_from_bytes_backrefs, Allocator, NodePtr};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
-use crate::db::{BlockRow, CoinRow, CoinType, TransactionInfo};
+use crate::db::{BlockRow, CoinRow, CoinType, SignageInfo, TransactionInfo};
 
 use super::{process_coin_spend, Insertion};
 
@@ -38,6 +38,9 @@ fn process_block(block: FullBlock, refs: &HashMap<u32, FullBlock>) -> Vec<Insert
                 hint: None,
                 memos: None,
                 kind: CoinType::Reward,
+                creator_type: None,
+                spent_height: None,
+                created_index: None,
             }),
             coin_id: coin.coin_id(),
         });
@@ -45,6 +48,7 @@ fn process_block(block: FullBlock, refs: &HashMap<u32, FullBlock>) -> Vec<Insert
 
     let mut additions = 0;
     let mut removals = 0;
+    let mut spent_value = 0;
 
     if let Some(generator_blob) = &block.transactions_generator {
         let mut allocator = Allocator::new();
@@ -68,6 +72,7 @@ fn process_block(block: FullBlock, refs: &HashMap<u32, FullBlock>) -> Vec<Insert
             let puzzle_hash = tree_hash(&allocator, puzzle);
 
             removals += 1;
+            spent_value += u128::from(amount);
             additions += process_coin_spend(
                 &mut insertions,
                 &mut allocator,
@@ -81,6 +86,25 @@ fn process_block(block: FullBlock, refs: &HashMap<u32, FullBlock>) -> Vec<Insert
 
     let height = block.height();
 
+    // The indexes order a block's coins by coin id, so the order they were
+    // created in is only kept here.
+    let mut created_index = 0;
+
+    for insertion in &mut insertions {
+        if let Insertion::Coin { coin, .. } = insertion {
+            coin.created_index = Some(created_index);
+            created_index += 1;
+        }
+    }
+
+    let created_value = insertions
+        .iter()
+        .map(|insertion| match insertion {
+            Insertion::Coin { coin, .. } => u128::from(coin.amount),
+            _ => 0,
+        })
+        .sum();
+
     insertions.push(Insertion::Block {
         block: Box::new(BlockRow {
             header_hash: block.header_hash(),
@@ -103,10 +127,28 @@ fn process_block(block: FullBlock, refs: &HashMap<u32, FullBlock>) -> Vec<Insert
                     additions,
                     removals,
                     prev_transaction_block_hash: tx_block.prev_transaction_block_hash,
+                    additions_root: Some(tx_block.additions_root),
+                    removals_root: Some(tx_block.removals_root),
+                    created_value: Some(created_value),
+                    spent_value: Some(spent_value),
                 })
             } else {
                 None
             },
+            signage: Some(SignageInfo {
+                signage_point_index: block.reward_chain_block.signage_point_index,
+                sub_slot_challenge: block.reward_chain_block.pos_ss_cc_challenge_hash,
+                finished_sub_slots: block.finished_sub_slots.len() as u32,
+                sp_iterations: block
+                    .reward_chain_block
+                    .challenge_chain_sp_vdf
+                    .as_ref()
+                    .map(|vdf| vdf.number_of_iterations),
+                ip_iterations: block
+                    .reward_chain_block
+                    .challenge_chain_ip_vdf
+                    .number_of_iterations,
+            }),
         }),
         height,
     });
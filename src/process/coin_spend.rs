@@ -1,12 +1,21 @@
 use chia::{
     clvm_traits::FromClvm,
-    protocol::{Bytes32, Coin, Program},
+    protocol::{Bytes, Bytes32, Coin, Program},
     puzzles::{
         cat::{CatArgs, CatSolution},
+        did::DidArgs,
+        nft::{
+            NftOwnershipLayerArgs, NftOwnershipLayerSolution, NftStateLayerArgs,
+            NftStateLayerSolution,
+        },
         singleton::{SingletonArgs, SingletonSolution},
     },
 };
-use chia_puzzles::{CAT_PUZZLE_HASH, SINGLETON_LAUNCHER_HASH, SINGLETON_TOP_LAYER_V1_1_HASH};
+use chia_puzzles::{
+    CAT_PUZZLE_HASH, DID_INNERPUZ_HASH, NFT_OWNERSHIP_LAYER_HASH, NFT_STATE_LAYER_HASH,
+    P2_DELEGATED_PUZZLE_OR_HIDDEN_PUZZLE_HASH, SETTLEMENT_PAYMENT_HASH, SINGLETON_LAUNCHER_HASH,
+    SINGLETON_TOP_LAYER_V1_1_HASH,
+};
 use chia_wallet_sdk::{
     driver::Puzzle,
     prelude::CreateCoin,
@@ -15,10 +24,15 @@ use chia_wallet_sdk::{
 use clvmr::{serde::node_to_bytes, Allocator, NodePtr};
 use itertools::Itertools;
 
-use crate::db::{CoinRow, CoinType, LineageProof};
+use crate::db::{CoinRow, CoinType, CreatorType, LineageProof};
 
 use super::Insertion;
 
+/// How many layers can wrap a settlement payments puzzle before it's no longer
+/// looked for. An NFT's is the deepest, under the singleton, state and
+/// ownership layers.
+const MAX_SETTLEMENT_DEPTH: usize = 4;
+
 struct SpendState<'a> {
     allocator: &'a mut Allocator,
     coin_id: Bytes32,
@@ -26,10 +40,19 @@ struct SpendState<'a> {
     height: u32,
     insertions: &'a mut Vec<Insertion>,
     additions: u32,
+    creator_type: CreatorType,
+    /// The `(nonce, coin_id)` pairs of the offer settlement outputs created.
+    offer_coins: Vec<(Bytes32, Bytes32)>,
+    /// The `(name, launcher_id)` of the NFT or DID spent, if it has a name.
+    singleton_name: Option<(String, Bytes32)>,
 }
 
 impl SpendState<'_> {
     fn parse(&mut self, puzzle: Puzzle, solution: NodePtr) {
+        self.creator_type = self.classify(puzzle);
+
+        let first_insertion = self.insertions.len();
+
         match puzzle.mod_hash().to_bytes() {
             SINGLETON_LAUNCHER_HASH => {
                 self.launcher(puzzle, solution);
@@ -46,6 +69,35 @@ impl SpendState<'_> {
                 self.insert_coin_rows(rows);
             }
         }
+
+        if let Some((settlement, settlement_solution)) =
+            self.settlement_layer(puzzle, solution, MAX_SETTLEMENT_DEPTH)
+        {
+            self.settlement(settlement, settlement_solution, first_insertion);
+        }
+    }
+
+    fn classify(&self, puzzle: Puzzle) -> CreatorType {
+        match puzzle.mod_hash().to_bytes() {
+            P2_DELEGATED_PUZZLE_OR_HIDDEN_PUZZLE_HASH => CreatorType::Standard,
+            CAT_PUZZLE_HASH => CreatorType::Cat,
+            SINGLETON_LAUNCHER_HASH => CreatorType::Singleton,
+            SINGLETON_TOP_LAYER_V1_1_HASH => {
+                let inner_puzzle = puzzle
+                    .as_curried()
+                    .and_then(|puzzle| {
+                        SingletonArgs::<Puzzle>::from_clvm(self.allocator, puzzle.args).ok()
+                    })
+                    .map(|args| args.inner_puzzle);
+
+                match inner_puzzle.map(|puzzle| puzzle.mod_hash().to_bytes()) {
+                    Some(NFT_STATE_LAYER_HASH) => CreatorType::Nft,
+                    Some(DID_INNERPUZ_HASH) => CreatorType::Did,
+                    _ => CreatorType::Singleton,
+                }
+            }
+            _ => CreatorType::Unknown,
+        }
     }
 
     fn launcher(&mut self, puzzle: Puzzle, solution: NodePtr) {
@@ -104,6 +156,48 @@ impl SpendState<'_> {
         }
 
         self.insert_coin_rows(rows);
+
+        self.singleton_name = self
+            .singleton_name(singleton_args.inner_puzzle)
+            .map(|name| (name, singleton_args.singleton_struct.launcher_id));
+    }
+
+    /// The `name` entry in an NFT or DID's metadata, if it has one on chain.
+    /// Most names only exist in off-chain metadata, which isn't fetched. The
+    /// NFT1 metadata standard has no `name` key at all (just the URIs, hashes
+    /// and edition numbers), so only NFTs minted with nonstandard metadata are
+    /// ever named here, and in practice it's DIDs that are.
+    fn singleton_name(&self, inner_puzzle: Puzzle) -> Option<String> {
+        let curried = inner_puzzle.as_curried()?;
+
+        let metadata = match inner_puzzle.mod_hash().to_bytes() {
+            NFT_STATE_LAYER_HASH => {
+                NftStateLayerArgs::<NodePtr, NodePtr>::from_clvm(self.allocator, curried.args)
+                    .ok()?
+                    .metadata
+            }
+            DID_INNERPUZ_HASH => {
+                DidArgs::<NodePtr, NodePtr>::from_clvm(self.allocator, curried.args)
+                    .ok()?
+                    .metadata
+            }
+            _ => return None,
+        };
+
+        let pairs = Vec::<(NodePtr, NodePtr)>::from_clvm(self.allocator, metadata).ok()?;
+
+        pairs.into_iter().find_map(|(key, value)| {
+            let key = Bytes::from_clvm(self.allocator, key).ok()?;
+
+            if key.as_ref() != b"name" {
+                return None;
+            }
+
+            let value = Bytes::from_clvm(self.allocator, value).ok()?;
+            let name = String::from_utf8(value.to_vec()).ok()?;
+
+            Some(name).filter(|name| !name.trim().is_empty())
+        })
     }
 
     fn cat(&mut self, puzzle: Puzzle, solution: NodePtr) {
@@ -164,6 +258,113 @@ impl SpendState<'_> {
         self.insert_coin_rows(rows);
     }
 
+    /// The settlement payments puzzle and its solution, if the puzzle is one
+    /// or wraps one in CAT, singleton and NFT layers. Only the current
+    /// settlement payments puzzle (used by offers since Chia 1.6) is
+    /// recognized.
+    fn settlement_layer(
+        &self,
+        puzzle: Puzzle,
+        solution: NodePtr,
+        depth: usize,
+    ) -> Option<(Puzzle, NodePtr)> {
+        let mod_hash = puzzle.mod_hash().to_bytes();
+
+        if mod_hash == SETTLEMENT_PAYMENT_HASH {
+            return Some((puzzle, solution));
+        }
+
+        let curried = puzzle.as_curried().filter(|_| depth > 0)?;
+
+        let (inner_puzzle, inner_solution) = match mod_hash {
+            CAT_PUZZLE_HASH => (
+                CatArgs::<NodePtr>::from_clvm(self.allocator, curried.args)
+                    .ok()?
+                    .inner_puzzle,
+                CatSolution::<NodePtr>::from_clvm(self.allocator, solution)
+                    .ok()?
+                    .inner_puzzle_solution,
+            ),
+            SINGLETON_TOP_LAYER_V1_1_HASH => (
+                SingletonArgs::<NodePtr>::from_clvm(self.allocator, curried.args)
+                    .ok()?
+                    .inner_puzzle,
+                SingletonSolution::<NodePtr>::from_clvm(self.allocator, solution)
+                    .ok()?
+                    .inner_solution,
+            ),
+            NFT_STATE_LAYER_HASH => (
+                NftStateLayerArgs::<NodePtr, NodePtr>::from_clvm(self.allocator, curried.args)
+                    .ok()?
+                    .inner_puzzle,
+                NftStateLayerSolution::<NodePtr>::from_clvm(self.allocator, solution)
+                    .ok()?
+                    .inner_solution,
+            ),
+            NFT_OWNERSHIP_LAYER_HASH => (
+                NftOwnershipLayerArgs::<NodePtr, NodePtr>::from_clvm(self.allocator, curried.args)
+                    .ok()?
+                    .inner_puzzle,
+                NftOwnershipLayerSolution::<NodePtr>::from_clvm(self.allocator, solution)
+                    .ok()?
+                    .inner_solution,
+            ),
+            _ => return None,
+        };
+
+        let inner_puzzle = Puzzle::parse(self.allocator, inner_puzzle);
+
+        self.settlement_layer(inner_puzzle, inner_solution, depth - 1)
+    }
+
+    /// Links the coins created by an offer settlement spend to the nonces of
+    /// the notarized payments they fulfill. Payments name the settlement's own
+    /// outputs, so they're matched against what the settlement puzzle alone
+    /// creates. Every layer around it turns each of those into one of the
+    /// spend's coins in the same order, so the two line up one to one.
+    fn settlement(&mut self, settlement: Puzzle, solution: NodePtr, first_insertion: usize) {
+        let Ok(notarized_payments) =
+            Vec::<(Bytes32, Vec<(Bytes32, (u64, NodePtr))>)>::from_clvm(self.allocator, solution)
+        else {
+            return;
+        };
+
+        let created = self.insertions[first_insertion..]
+            .iter()
+            .filter_map(|insertion| match insertion {
+                Insertion::Coin { coin, coin_id } => Some((coin.amount, *coin_id)),
+                _ => None,
+            })
+            .collect_vec();
+
+        let conditions = self.conditions(settlement.ptr(), solution);
+        let payments = conditions
+            .iter()
+            .filter_map(Condition::as_create_coin)
+            .collect_vec();
+
+        if payments.len() != created.len() {
+            return;
+        }
+
+        let outputs = payments
+            .iter()
+            .zip(created)
+            .filter(|(payment, (amount, _))| payment.amount == *amount)
+            .map(|(payment, (amount, coin_id))| (payment.puzzle_hash, amount, coin_id))
+            .collect_vec();
+
+        for (nonce, payments) in notarized_payments {
+            for (puzzle_hash, (amount, _)) in payments {
+                for &(output_puzzle_hash, output_amount, coin_id) in &outputs {
+                    if output_puzzle_hash == puzzle_hash && output_amount == amount {
+                        self.offer_coins.push((nonce, coin_id));
+                    }
+                }
+            }
+        }
+    }
+
     fn coin_rows(&mut self, conditions: &[Condition]) -> Vec<CoinRow> {
         let mut coins = Vec::new();
 
@@ -192,6 +393,9 @@ impl SpendState<'_> {
                 hint,
                 memos,
                 kind: CoinType::Unknown,
+                creator_type: Some(self.creator_type),
+                spent_height: None,
+                created_index: None,
             });
         }
 
@@ -200,7 +404,7 @@ impl SpendState<'_> {
 
     fn insert_coin_rows(&mut self, coins: Vec<CoinRow>) {
         for coin in coins {
-            let coin_id = Coin::new(coin.parent_coin_id, coin.puzzle_hash, coin.amount).coin_id();
+            let coin_id = coin_row_id(&coin);
 
             self.insertions.push(Insertion::Coin {
                 coin: Box::new(coin),
@@ -216,6 +420,10 @@ impl SpendState<'_> {
     }
 }
 
+fn coin_row_id(coin: &CoinRow) -> Bytes32 {
+    Coin::new(coin.parent_coin_id, coin.puzzle_hash, coin.amount).coin_id()
+}
+
 pub fn process_coin_spend(
     insertions: &mut Vec<Insertion>,
     allocator: &mut Allocator,
@@ -234,12 +442,18 @@ pub fn process_coin_spend(
         height,
         insertions,
         additions: 0,
+        creator_type: CreatorType::Unknown,
+        offer_coins: Vec::new(),
+        singleton_name: None,
     };
 
     spend_state.parse(puzzle, solution);
 
     let additions = spend_state.additions;
+    let offer_coins = spend_state.offer_coins;
+    let singleton_name = spend_state.singleton_name;
 
+    let mod_hash = puzzle.mod_hash().into();
     let puzzle_reveal = node_to_bytes(allocator, puzzle.ptr()).unwrap();
     let solution = node_to_bytes(allocator, solution).unwrap();
 
@@ -248,6 +462,9 @@ pub fn process_coin_spend(
         puzzle_reveal,
         solution,
         spent_height: height,
+        mod_hash,
+        offer_coins,
+        singleton_name,
     });
 
     additions
@@ -23,6 +23,13 @@ pub enum Insertion {
         puzzle_reveal: Vec<u8>,
         solution: Vec<u8>,
         spent_height: u32,
+        mod_hash: Bytes32,
+        /// The `(nonce, coin_id)` pairs of the offer settlement outputs the
+        /// spend created.
+        offer_coins: Vec<(Bytes32, Bytes32)>,
+        /// The `(name, launcher_id)` of the NFT or DID spent, if its revealed
+        /// metadata names it.
+        singleton_name: Option<(String, Bytes32)>,
     },
 }
 
@@ -0,0 +1,169 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use chia::protocol::Bytes32;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use crate::db::Database;
+use crate::events::Event;
+use crate::sync::{lag_seconds, SyncStatus};
+
+/// How many of the newest block hashes are remembered to find where a reorg
+/// replayed from the primary forked.
+const RECENT_HASHES: usize = 100;
+
+/// Follows the writes of the instance syncing into the database, for a
+/// read-only instance. The sync status and events are derived from what each
+/// catch up changed, since there's no sync of its own to report them.
+pub struct Replica {
+    db: Database,
+    status: Arc<RwLock<SyncStatus>>,
+    events: mpsc::UnboundedSender<Event>,
+    /// The heights and header hashes of the newest blocks seen so far.
+    seen: VecDeque<(u32, Bytes32)>,
+    started: bool,
+}
+
+impl Replica {
+    pub fn new(
+        db: Database,
+        status: Arc<RwLock<SyncStatus>>,
+        events: mpsc::UnboundedSender<Event>,
+    ) -> Self {
+        Self {
+            db,
+            status,
+            events,
+            seen: VecDeque::new(),
+            started: false,
+        }
+    }
+
+    pub async fn start(mut self, interval: Duration) {
+        let mut interval = tokio::time::interval(interval);
+
+        loop {
+            interval.tick().await;
+
+            self = match tokio::task::spawn_blocking(move || {
+                let result = self.catch_up();
+                (self, result)
+            })
+            .await
+            {
+                Ok((replica, result)) => {
+                    if let Err(error) = result {
+                        warn!("Failed to catch up with the primary: {error}");
+                    }
+
+                    replica
+                }
+                Err(error) => {
+                    warn!("Catching up with the primary panicked: {error}");
+                    return;
+                }
+            };
+        }
+    }
+
+    fn catch_up(&mut self) -> Result<()> {
+        self.db.catch_up_with_primary()?;
+
+        let Some(peak_height) = self.db.peak_height()? else {
+            self.seen.clear();
+            return Ok(());
+        };
+
+        let mut start_height = 0;
+
+        if let Some(&(last_height, _)) = self.seen.back() {
+            let oldest_height = self.seen.front().map_or(0, |&(height, _)| height);
+
+            while let Some(&(height, header_hash)) = self.seen.back() {
+                if height <= peak_height
+                    && self
+                        .db
+                        .block(height)?
+                        .is_some_and(|block| block.header_hash == header_hash)
+                {
+                    break;
+                }
+
+                self.seen.pop_back();
+            }
+
+            // A reorg deeper than the hashes kept here is reported from just
+            // below the oldest of them.
+            let fork_height = self
+                .seen
+                .back()
+                .map_or(oldest_height.saturating_sub(1), |&(height, _)| height);
+
+            if fork_height < last_height {
+                if let Some(peak) = self.db.block(peak_height)? {
+                    self.events
+                        .send(Event::Reorg {
+                            fork_height,
+                            depth: last_height - fork_height,
+                            peak_hash: peak.header_hash,
+                        })
+                        .ok();
+                }
+            }
+
+            start_height = fork_height + 1;
+        }
+
+        // Only the newest blocks are reported after a long gap, the same as
+        // when block events are coalesced.
+        let start_height = start_height.max(peak_height.saturating_sub(RECENT_HASHES as u32 - 1));
+        let mut advanced = false;
+
+        for height in start_height..=peak_height {
+            let Some(block) = self.db.block(height)? else {
+                continue;
+            };
+
+            self.seen.push_back((height, block.header_hash));
+
+            if self.seen.len() > RECENT_HASHES {
+                self.seen.pop_front();
+            }
+
+            advanced = true;
+
+            if self.started {
+                let timestamp = block.transaction_info.map(|info| info.timestamp);
+
+                self.events
+                    .send(Event::Block {
+                        height,
+                        header_hash: block.header_hash,
+                        timestamp,
+                        lag_seconds: timestamp.map(lag_seconds),
+                    })
+                    .ok();
+            }
+        }
+
+        let mut status = self.status.write().unwrap();
+        status.synced = true;
+        status.indexed_peak = self.seen.back().copied();
+
+        if advanced {
+            let now = SystemTime::now();
+            status.peak_updated_at = Some(now);
+
+            if self.started {
+                status.last_block_ingested_at = Some(now);
+            }
+        }
+
+        self.started = true;
+
+        Ok(())
+    }
+}
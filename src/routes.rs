@@ -1,283 +1,344 @@
+mod address;
+mod admin;
+mod balances;
+mod blocks;
+mod cats;
+mod coin_sync;
+mod coins;
+mod data_version;
+mod dids;
+mod directory;
+mod error;
+#[cfg(feature = "sentry")]
+mod error_reporting;
+mod etag;
+mod events;
+mod extract;
+mod format;
+mod health;
+mod ip_limit;
+mod lineage;
+mod link;
+mod metrics;
+mod nfts;
+mod panic;
+mod peak;
+mod prefer;
+mod proof;
+mod search;
+mod select;
+mod signatures;
+mod simulate;
+mod single_flight;
+mod slow_requests;
+mod solution_tree;
+mod spends;
+mod state;
+mod stats;
+#[cfg(test)]
+mod testing;
+
+pub use address::*;
+pub use admin::*;
+pub use balances::*;
+pub use blocks::*;
+pub use cats::*;
+pub use coin_sync::*;
+pub use coins::*;
+pub use data_version::*;
+pub use dids::*;
+pub use directory::*;
+pub use error::*;
+#[cfg(feature = "sentry")]
+pub use error_reporting::*;
+pub use etag::*;
+pub use events::*;
+pub use extract::*;
+pub use format::*;
+pub use health::*;
+pub use ip_limit::*;
+pub use lineage::*;
+pub use link::*;
+pub use metrics::*;
+pub use nfts::*;
+pub use panic::*;
+pub use peak::*;
+pub use prefer::*;
+pub use proof::*;
+pub use search::*;
+pub use select::*;
+pub use signatures::*;
+pub use simulate::*;
+pub use single_flight::*;
+pub use slow_requests::*;
+pub use solution_tree::*;
+pub use spends::*;
+pub use state::*;
+pub use stats::*;
+#[cfg(test)]
+pub use testing::*;
+
+use std::sync::{Arc, RwLock};
+
 use axum::{
-    extract::{Path, Query, State},
-    http::{Method, StatusCode},
-    routing::get,
-    Json, Router,
+    http::Method,
+    middleware,
+    routing::{get, post},
+    Router,
+};
+use tokio::sync::broadcast;
+use tower_http::{
+    catch_panic::CatchPanicLayer,
+    cors::{Any, CorsLayer},
 };
-use chia::protocol::{Bytes, Bytes32};
-use indexmap::IndexMap;
-use itertools::Itertools;
-use rocksdb::Direction;
-use serde::{Deserialize, Serialize};
-use tower_http::cors::{Any, CorsLayer};
+use tracing::info;
 
-use crate::db::{BlockRow, CoinRow, Database};
+use crate::{
+    config::Config,
+    db::{Database, IndexKind, IntegrityReport},
+    events::Event,
+    sync::{IngestionErrorLog, ReorgLog, Sync, SyncStatus},
+};
 
 #[derive(Clone)]
 pub struct App {
     pub db: Database,
+    pub config: Arc<Config>,
+    pub sync_status: Arc<RwLock<SyncStatus>>,
+    pub reorgs: Arc<RwLock<ReorgLog>>,
+    pub ingestion_errors: Arc<RwLock<IngestionErrorLog>>,
+    pub events: broadcast::Sender<Event>,
+    pub integrity: Arc<IntegrityReport>,
+    pub flights: Arc<Flights>,
+    /// `None` on a read-only instance, which doesn't sync.
+    pub sync: Option<Arc<Sync>>,
+    pub ip_limiter: Arc<IpLimiter>,
 }
 
-pub fn router(app: App) -> Router {
-    let cors = CorsLayer::new()
-        .allow_methods([Method::GET, Method::POST])
-        .allow_origin(Any);
-
-    Router::new()
-        .route("/state", get(state))
-        .route("/blocks/latest", get(latest_block))
-        .route("/blocks/height/{height}", get(block_by_height))
-        .route("/blocks/hash/{hash}", get(block_by_hash))
-        .route("/blocks", get(blocks))
-        .route("/coins/block/{hash}", get(coins_by_block))
-        .route("/coins/children/{coin_id}", get(coins_by_parent))
-        .route("/coins/id/{coin_id}", get(coin_by_id))
-        .with_state(app)
-        .layer(cors)
-}
-
-#[derive(Serialize)]
-pub struct Coin {
-    pub coin_id: Bytes32,
-    #[serde(flatten)]
-    pub row: CoinRow,
-    pub spent_height: Option<u32>,
-}
-
-#[derive(Serialize)]
-pub struct Block {
-    pub height: u32,
-    #[serde(flatten)]
-    pub row: BlockRow,
-}
-
-#[derive(Serialize)]
-pub struct StateResponse {
-    pub peak_height: u32,
-}
-
-async fn state(State(app): State<App>) -> Result<Json<StateResponse>, StatusCode> {
-    let height = app.db.peak_height().unwrap().unwrap_or(0);
-
-    Ok(Json(StateResponse {
-        peak_height: height,
-    }))
-}
-
-#[derive(Serialize)]
-pub struct BlockResponse {
-    pub block: Block,
-}
-
-async fn latest_block(State(app): State<App>) -> Result<Json<BlockResponse>, StatusCode> {
-    let Some(height) = app.db.peak_height().unwrap() else {
-        return Err(StatusCode::NOT_FOUND);
-    };
-
-    let Some(block) = app.db.block(height).unwrap() else {
-        return Err(StatusCode::NOT_FOUND);
-    };
-
-    Ok(Json(BlockResponse {
-        block: Block { height, row: block },
-    }))
-}
-
-async fn block_by_height(
-    State(app): State<App>,
-    Path(height): Path<u32>,
-) -> Result<Json<BlockResponse>, StatusCode> {
-    let Some(block) = app.db.block(height).unwrap() else {
-        return Err(StatusCode::NOT_FOUND);
-    };
-
-    Ok(Json(BlockResponse {
-        block: Block { height, row: block },
-    }))
-}
-
-async fn block_by_hash(
-    State(app): State<App>,
-    Path(hash): Path<Bytes32>,
-) -> Result<Json<BlockResponse>, StatusCode> {
-    let Some(height) = app.db.block_height(hash).unwrap() else {
-        return Err(StatusCode::NOT_FOUND);
-    };
-
-    let Some(block) = app.db.block(height).unwrap() else {
-        return Err(StatusCode::NOT_FOUND);
-    };
-
-    Ok(Json(BlockResponse {
-        block: Block { height, row: block },
-    }))
-}
-
-#[derive(Deserialize)]
-pub struct BlocksRequest {
-    #[serde(default = "default_limit")]
-    pub limit: u32,
-    #[serde(default)]
-    pub start: Option<u32>,
-    #[serde(default)]
-    pub reverse: bool,
-}
-
-fn default_limit() -> u32 {
-    50
-}
+impl App {
+    /// Runs a handler's database reads on the blocking thread pool, since a
+    /// slow disk read would otherwise stall every request on the same worker.
+    pub async fn blocking<T>(
+        &self,
+        f: impl FnOnce(&App) -> Result<T, AppError> + Send + 'static,
+    ) -> Result<T, AppError>
+    where
+        T: Send + 'static,
+    {
+        let app = self.clone();
+        let format = FORMAT.try_with(|format| format.clone()).unwrap_or_default();
 
-#[derive(Serialize)]
-pub struct BlocksResponse {
-    pub blocks: Vec<Block>,
-}
+        tokio::task::spawn_blocking(move || FORMAT.sync_scope(format, || f(&app)))
+            .await
+            .map_err(anyhow::Error::from)?
+    }
 
-async fn blocks(
-    State(app): State<App>,
-    Query(query): Query<BlocksRequest>,
-) -> Result<Json<BlocksResponse>, StatusCode> {
-    let (start, end) = if query.reverse {
-        let end = query
-            .start
-            .unwrap_or(app.db.peak_height().unwrap().unwrap_or(0));
-        let start = end.saturating_sub(query.limit);
-        (start, end)
-    } else {
-        let start = query.start.unwrap_or(0);
-        let end = start + query.limit;
-        (start, end)
-    };
+    /// Like `blocking`, but holds a read permit for the range scans in `f`.
+    pub async fn read<T>(
+        &self,
+        f: impl FnOnce(&App) -> Result<T, AppError> + Send + 'static,
+    ) -> Result<T, AppError>
+    where
+        T: Send + 'static,
+    {
+        let permit = self.db.read_permit().await?;
 
-    let blocks = app
-        .db
-        .blocks_range(
-            start,
-            end,
-            if query.reverse {
-                Direction::Reverse
-            } else {
-                Direction::Forward
-            },
-        )
-        .unwrap();
+        self.blocking(move |app| {
+            let _permit = permit;
+            f(app)
+        })
+        .await
+    }
 
-    Ok(Json(BlocksResponse {
-        blocks: blocks
-            .into_iter()
-            .enumerate()
-            .map(|(offset, block)| Block {
-                height: if query.reverse {
-                    end - offset as u32
-                } else {
-                    start + offset as u32
-                },
-                row: block,
-            })
-            .collect_vec(),
-    }))
-}
+    /// How many coins a graph walk can visit, with a requested budget capped
+    /// at the configured `max_nodes`.
+    pub fn node_budget(&self, requested: Option<u32>) -> u32 {
+        requested.map_or(self.config.max_nodes, |requested| {
+            requested.min(self.config.max_nodes)
+        })
+    }
 
-#[derive(Serialize)]
-pub struct CoinsResponse {
-    pub coins: Vec<Coin>,
+    /// Endpoints backed by an optional index are unavailable while it's
+    /// disabled, or enabled but not yet built.
+    pub fn require_index(&self, kind: IndexKind) -> Result<(), AppError> {
+        if !self.db.is_index_enabled(kind) {
+            Err(AppError::NotImplemented(format!(
+                "The {} index is disabled",
+                kind.name()
+            )))
+        } else if !self.db.is_index_built(kind) {
+            Err(AppError::NotImplemented(format!(
+                "The {0} index hasn't been built yet, run `rebuild-index {0}`",
+                kind.name()
+            )))
+        } else {
+            Ok(())
+        }
+    }
 }
 
-async fn coins_by_block(
-    State(app): State<App>,
-    Path(hash): Path<Bytes32>,
-) -> Result<Json<CoinsResponse>, StatusCode> {
-    let Some(height) = app.db.block_height(hash).unwrap() else {
-        return Err(StatusCode::NOT_FOUND);
-    };
+pub fn router(app: App) -> Router {
+    let cors = CorsLayer::new()
+        .allow_methods([Method::GET, Method::HEAD, Method::POST])
+        .allow_headers(Any)
+        .expose_headers(Any)
+        .allow_origin(Any);
 
-    let mut coins = IndexMap::new();
+    let routes = [
+        ("/", get(route_directory)),
+        ("/health", get(health)),
+        ("/metrics", get(metrics)),
+        ("/admin/errors", get(ingestion_errors)),
+        ("/admin/reindex/{height}", post(reindex_height)),
+        ("/admin/storage", get(storage)),
+        ("/state", get(state).head(state_head)),
+        ("/ready", get(ready)),
+        ("/blocks/latest", get(latest_block)),
+        ("/blocks/height/{height}", get(block_by_height)),
+        ("/blocks/height/{height}/spends", get(block_spends)),
+        ("/blocks/height/{height}/additions", get(block_additions)),
+        ("/blocks/height/{height}/removals", get(block_removals)),
+        (
+            "/blocks/height/{height}/coins/count",
+            get(block_coin_counts),
+        ),
+        ("/blocks/hash/{hash}", get(block_by_hash)),
+        ("/blocks", get(blocks)),
+        ("/blocks/activity", get(block_activity)),
+        ("/blocks/range", get(block_range)),
+        ("/headers", get(block_headers)),
+        ("/blocks/heights", post(blocks_by_heights)),
+        ("/blocks/prefix/{prefix}", get(blocks_by_prefix)),
+        ("/blocks/export.ndjson", get(export_blocks)),
+        ("/coins/block/{hash}", get(coins_by_block)),
+        ("/coins/block/height/{height}", get(coins_by_block_height)),
+        ("/coins/children/{coin_id}", get(coins_by_parent)),
+        (
+            "/coins/children/{coin_id}/count",
+            get(count_coins_by_parent),
+        ),
+        ("/coins/hint/{hint}", get(coins_by_hint)),
+        ("/coins/amount/{amount}", get(coins_by_amount)),
+        ("/coins/amount-range", get(coins_by_amount_range)),
+        ("/coins/id/{coin_id}", get(coin_by_id)),
+        ("/coins/id/{coin_id}/proof", get(coin_proof)),
+        ("/coins/id/{coin_id}/spend-proof", get(coin_spend_proof)),
+        ("/coins/id/{coin_id}/creator-spend", get(creator_spend)),
+        ("/coins/id/{coin_id}/puzzle-hash", get(coin_puzzle_hash)),
+        ("/coins/id/{coin_id}/raw", get(coin_raw)),
+        ("/coins/id/{coin_id}/record", get(coin_record)),
+        ("/coins/id/{coin_id}/spendable", get(coin_spendable)),
+        ("/coins/id/{coin_id}/status", get(coin_confirmation_status)),
+        ("/coins/id/{coin_id}/created-in", get(coin_created_in)),
+        ("/coins/id/{coin_id}/spent-in", get(coin_spent_in)),
+        ("/coins/id/{coin_id}/timeline", get(coin_timeline)),
+        ("/coins/id/{coin_id}/verify-lineage", get(verify_lineage)),
+        ("/coins/id/{coin_id}/provenance", get(coin_provenance)),
+        ("/coins/id/{coin_id}/launcher", get(coin_launcher)),
+        ("/coins/id/{coin_id}/tree", get(coin_tree)),
+        ("/coins/id/{coin_id}/solution-tree", get(solution_tree)),
+        ("/coins/id/{coin_id}/signatures", get(coin_signatures)),
+        ("/coins/puzzles", post(coins_by_puzzle_hashes)),
+        ("/coins/select", post(select_coins)),
+        ("/coins/puzzle/{puzzle_hash}/exists", get(puzzle_hash_used)),
+        (
+            "/coins/puzzle/{puzzle_hash}/first",
+            get(first_coin_by_puzzle_hash),
+        ),
+        (
+            "/coins/puzzle/{puzzle_hash}/latest",
+            get(latest_coins_by_puzzle_hash),
+        ),
+        (
+            "/coins/puzzle/{puzzle_hash}/summary",
+            get(puzzle_hash_summary),
+        ),
+        ("/coins/puzzle/{puzzle_hash}/history", get(coin_history)),
+        (
+            "/coins/puzzle/{puzzle_hash}/spends",
+            get(spends_by_puzzle_hash),
+        ),
+        (
+            "/coins/puzzle/{puzzle_hash}/ids",
+            get(coin_ids_by_puzzle_hash),
+        ),
+        (
+            "/coins/puzzle/{puzzle_hash}/created/height/{height}",
+            get(coins_by_puzzle_hash_created_at),
+        ),
+        (
+            "/coins/puzzle/{puzzle_hash}/at/{height}",
+            get(coins_at_height),
+        ),
+        ("/coins/prefix/{prefix}", get(coins_by_prefix)),
+        ("/coins/diff", get(coin_diff)),
+        ("/coins/recent", get(recent_coins)),
+        ("/coins/spent", get(spent_coins_by_range)),
+        ("/coins/latest", get(latest_coins)),
+        ("/coins/status", post(coin_statuses)),
+        ("/coins/compute-id", post(compute_coin_id)),
+        ("/spends/range", get(spends_by_range)),
+        ("/balances", post(balances)),
+        ("/cats", get(cats)),
+        ("/search/name/{query}", get(singletons_by_name)),
+        ("/dids/{did_id}/recovery", get(did_recovery)),
+        ("/nfts/{launcher_id}/owner", get(nft_owner)),
+        ("/offers/{nonce}/coins", get(coins_by_offer_nonce)),
+        ("/stats/tx-series", get(tx_series)),
+        ("/stats/reorgs", get(reorg_stats)),
+        ("/stats/dust", get(dust_stats)),
+        ("/stats/supply", get(supply_stats)),
+        ("/stats/difficulty", get(difficulty_stats)),
+        ("/stats/puzzle-usage", get(puzzle_usage)),
+        ("/simulate", post(simulate_spend)),
+        ("/puzzle/hash", post(hash_puzzle)),
+        ("/encode/address/{puzzle_hash}", get(encode_address)),
+        ("/ws/events", get(events)),
+        ("/ws/coins/sync", get(coin_sync)),
+    ];
+
+    let mut router = Router::new();
+
+    for (path, method_router) in routes {
+        debug_assert!(
+            ROUTE_DIRECTORY.iter().any(|route| route.path == path),
+            "{path} is missing from the route directory"
+        );
 
-    for coin_id in [
-        app.db.coins_by_created_height(height).unwrap(),
-        app.db.coins_by_spent_height(height).unwrap(),
-    ]
-    .concat()
-    {
-        if coins.contains_key(&coin_id) {
+        if app.config.disabled_routes.iter().any(|route| route == path) {
+            info!("Route {path} is disabled");
             continue;
         }
 
-        let Some(coin) = app.db.coin(coin_id).unwrap() else {
-            continue;
-        };
-
-        let spend = app.db.coin_spend(coin_id).unwrap();
-
-        coins.insert(
-            coin_id,
-            Coin {
-                coin_id,
-                row: coin,
-                spent_height: spend.map(|spend| spend.spent_height),
-            },
-        );
+        router = router.route(path, method_router);
     }
 
-    Ok(Json(CoinsResponse {
-        coins: coins.into_values().collect_vec(),
-    }))
-}
-
-async fn coins_by_parent(
-    State(app): State<App>,
-    Path(coin_id): Path<Bytes32>,
-) -> Result<Json<CoinsResponse>, StatusCode> {
-    let coins = app.db.coins_by_parent_coin_id(coin_id).unwrap();
-
-    let coins = coins
-        .into_iter()
-        .filter_map(|coin_id| {
-            let row = app.db.coin(coin_id).unwrap()?;
-            let spend = app.db.coin_spend(coin_id).unwrap();
-            Some(Coin {
-                coin_id,
-                row,
-                spent_height: spend.map(|spend| spend.spent_height),
-            })
-        })
-        .collect_vec();
+    let mut router = router.fallback(fallback).with_state(app.clone());
 
-    Ok(Json(CoinsResponse { coins }))
-}
-
-#[derive(Serialize)]
-pub struct CoinResponse {
-    pub coin: Coin,
-    pub puzzle_reveal: Option<Bytes>,
-    pub solution: Option<Bytes>,
-}
+    // This is innermost, so the other layers still see the 500 response.
+    if app.config.catch_panics {
+        router = router.layer(CatchPanicLayer::custom(handle_panic));
+    }
 
-async fn coin_by_id(
-    State(app): State<App>,
-    Path(coin_id): Path<Bytes32>,
-) -> Result<Json<CoinResponse>, StatusCode> {
-    let Some(coin) = app.db.coin(coin_id).unwrap() else {
-        return Err(StatusCode::NOT_FOUND);
-    };
+    #[cfg(feature = "sentry")]
+    if app.config.sentry_dsn.is_some() {
+        router = router.layer(middleware::from_fn(report_errors));
+    }
 
-    let (puzzle_reveal, solution, spent_height) =
-        if let Some(spend) = app.db.coin_spend(coin_id).unwrap() {
-            (
-                Some(spend.puzzle_reveal),
-                Some(spend.solution),
-                Some(spend.spent_height),
-            )
-        } else {
-            (None, None, None)
-        };
+    if app.config.max_requests_per_ip > 0 {
+        router = router.layer(middleware::from_fn_with_state(
+            app.clone(),
+            limit_requests_per_ip,
+        ));
+    }
 
-    Ok(Json(CoinResponse {
-        coin: Coin {
-            coin_id,
-            row: coin,
-            spent_height,
-        },
-        puzzle_reveal,
-        solution,
-    }))
+    router
+        .layer(middleware::from_fn_with_state(
+            app.clone(),
+            log_slow_requests,
+        ))
+        .layer(middleware::from_fn_with_state(app.clone(), peak_headers))
+        .layer(middleware::from_fn(data_version_header))
+        .layer(middleware::from_fn_with_state(app, response_format))
+        .layer(cors)
 }
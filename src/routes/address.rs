@@ -0,0 +1,222 @@
+use std::borrow::Cow;
+
+use anyhow::{anyhow, Result};
+use axum::extract::State;
+use bech32::{FromBase32, ToBase32, Variant};
+use chia::protocol::Bytes32;
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+use super::{App, AppError, Json, Path, Query};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Network {
+    Mainnet,
+    Testnet11,
+}
+
+impl Network {
+    pub fn address_prefix(self) -> &'static str {
+        match self {
+            Self::Mainnet => "xch",
+            Self::Testnet11 => "txch",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct EncodeAddressRequest {
+    /// Overrides the configured `address_prefix`.
+    #[serde(default)]
+    pub network: Option<Network>,
+}
+
+#[derive(Serialize)]
+pub struct AddressResponse {
+    pub address: String,
+}
+
+/// Encodes the puzzle hash as a bech32m address.
+pub async fn encode_address(
+    State(app): State<App>,
+    Path(puzzle_hash): Path<Bytes32>,
+    Query(query): Query<EncodeAddressRequest>,
+) -> Result<Json<AddressResponse>, AppError> {
+    let prefix = query
+        .network
+        .map_or(app.config.address_prefix.as_str(), Network::address_prefix);
+
+    Ok(Json(AddressResponse {
+        address: puzzle_hash_address(prefix, puzzle_hash)?,
+    }))
+}
+
+pub fn puzzle_hash_address(prefix: &str, puzzle_hash: Bytes32) -> Result<String> {
+    bech32::encode(prefix, puzzle_hash.to_base32(), Variant::Bech32m)
+        .map_err(|error| anyhow!("Failed to encode address: {error}"))
+}
+
+const COIN_ID_PREFIX: &str = "coin";
+
+/// A coin id path parameter, given either as 64 hex characters (with or
+/// without a `0x` prefix) or as a bech32m string with the `coin` prefix, such
+/// as `coin1...`. Input that decodes both ways is rejected as ambiguous.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoinId(pub Bytes32);
+
+impl<'de> Deserialize<'de> for CoinId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Cow::<str>::deserialize(deserializer)?;
+        parse_id(&value, COIN_ID_PREFIX, "Coin id")
+            .map(Self)
+            .map_err(de::Error::custom)
+    }
+}
+
+const DID_ID_PREFIX: &str = "did:chia:";
+
+/// A DID's launcher id as a path parameter, given as hex like a `CoinId` or as
+/// a bech32m `did:chia:1...` string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DidId(pub Bytes32);
+
+impl<'de> Deserialize<'de> for DidId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Cow::<str>::deserialize(deserializer)?;
+        parse_id(&value, DID_ID_PREFIX, "DID id")
+            .map(Self)
+            .map_err(de::Error::custom)
+    }
+}
+
+const NFT_ID_PREFIX: &str = "nft";
+
+/// An NFT's launcher id as a path parameter, given as hex like a `CoinId` or
+/// as a bech32m `nft1...` string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NftId(pub Bytes32);
+
+impl<'de> Deserialize<'de> for NftId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Cow::<str>::deserialize(deserializer)?;
+        parse_id(&value, NFT_ID_PREFIX, "NFT id")
+            .map(Self)
+            .map_err(de::Error::custom)
+    }
+}
+
+fn parse_id(value: &str, prefix: &str, name: &str) -> Result<Bytes32, String> {
+    let hex = value.strip_prefix("0x").unwrap_or(value);
+
+    let from_hex = (hex.len() == 64)
+        .then(|| hex::decode(hex).ok())
+        .flatten()
+        .and_then(|bytes| Bytes32::try_from(bytes).ok());
+
+    let from_bech32 = bech32::decode(value)
+        .ok()
+        .filter(|(hrp, _, variant)| hrp == prefix && *variant == Variant::Bech32m)
+        .and_then(|(_, data, _)| Vec::<u8>::from_base32(&data).ok())
+        .and_then(|bytes| Bytes32::try_from(bytes).ok());
+
+    pick_id(value, prefix, name, from_hex, from_bech32)
+}
+
+/// Settles on whichever of the two readings of `value` decoded. No 32 byte
+/// bech32m string with a real prefix is also 64 hex characters, but a value
+/// that reads both ways is still refused rather than guessed at.
+fn pick_id(
+    value: &str,
+    prefix: &str,
+    name: &str,
+    from_hex: Option<Bytes32>,
+    from_bech32: Option<Bytes32>,
+) -> Result<Bytes32, String> {
+    match (from_hex, from_bech32) {
+        (Some(_), Some(_)) => Err(format!("{name} `{value}` is ambiguous")),
+        (Some(coin_id), None) | (None, Some(coin_id)) => Ok(coin_id),
+        (None, None) => Err(format!(
+            "{name} `{value}` must be 32 bytes of hex or a bech32m `{prefix}1...` string"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: [u8; 32] = [0xab; 32];
+
+    fn bech32m(prefix: &str, bytes: &[u8]) -> String {
+        bech32::encode(prefix, bytes.to_base32(), Variant::Bech32m).unwrap()
+    }
+
+    #[test]
+    fn parses_hex_with_and_without_prefix() {
+        let hex = hex::encode(ID);
+
+        assert_eq!(
+            parse_id(&hex, COIN_ID_PREFIX, "Coin id"),
+            Ok(Bytes32::new(ID))
+        );
+        assert_eq!(
+            parse_id(&format!("0x{hex}"), COIN_ID_PREFIX, "Coin id"),
+            Ok(Bytes32::new(ID))
+        );
+    }
+
+    #[test]
+    fn parses_bech32m_with_the_right_prefix() {
+        let value = bech32m(NFT_ID_PREFIX, &ID);
+
+        assert_eq!(
+            parse_id(&value, NFT_ID_PREFIX, "NFT id"),
+            Ok(Bytes32::new(ID))
+        );
+        assert!(parse_id(&value, COIN_ID_PREFIX, "Coin id").is_err());
+    }
+
+    #[test]
+    fn refuses_an_ambiguous_value() {
+        let error = pick_id(
+            "value",
+            COIN_ID_PREFIX,
+            "Coin id",
+            Some(Bytes32::new(ID)),
+            Some(Bytes32::new(ID)),
+        )
+        .unwrap_err();
+
+        assert!(error.contains("ambiguous"));
+    }
+
+    #[test]
+    fn refuses_invalid_values() {
+        let hex = hex::encode(ID);
+        let bech32 = bech32::encode(NFT_ID_PREFIX, ID.to_base32(), Variant::Bech32).unwrap();
+
+        for value in [
+            "",
+            "0x",
+            &hex[..62],
+            format!("{hex}00").as_str(),
+            format!("{}zz", &hex[..62]).as_str(),
+            bech32.as_str(),
+            bech32m(NFT_ID_PREFIX, &ID[..31]).as_str(),
+        ] {
+            assert!(
+                parse_id(value, NFT_ID_PREFIX, "NFT id").is_err(),
+                "`{value}` should be refused"
+            );
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use axum::{
+    extract::{FromRequestParts, State},
+    http::{header::AUTHORIZATION, request::Parts},
+};
+use subtle::ConstantTimeEq;
+
+use crate::{
+    db::ColumnStats,
+    sync::{IngestionError, Reindex},
+};
+
+use super::{App, AppError, Json, Path};
+
+/// Admin endpoints require `Authorization: Bearer <admin_token>`, and are
+/// refused entirely when no token is configured.
+#[derive(Debug, Clone, Copy)]
+pub struct Admin;
+
+impl FromRequestParts<App> for Admin {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, app: &App) -> Result<Self, Self::Rejection> {
+        let Some(admin_token) = &app.config.admin_token else {
+            return Err(AppError::Unauthorized(
+                "Admin endpoints are disabled".to_string(),
+            ));
+        };
+
+        let token = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+
+        // Compared in constant time, so response timing doesn't reveal how
+        // much of a guessed token was right.
+        if token
+            .is_some_and(|token| bool::from(token.trim().as_bytes().ct_eq(admin_token.as_bytes())))
+        {
+            Ok(Self)
+        } else {
+            Err(AppError::Unauthorized(
+                "Missing or invalid admin token".to_string(),
+            ))
+        }
+    }
+}
+
+/// The most recent errors that stopped a batch of blocks from being indexed,
+/// newest first.
+pub async fn ingestion_errors(State(app): State<App>, _admin: Admin) -> Json<Vec<IngestionError>> {
+    Json(
+        app.ingestion_errors
+            .read()
+            .unwrap()
+            .recent
+            .iter()
+            .cloned()
+            .collect(),
+    )
+}
+
+/// Re-reads the block at the height from the node and rewrites everything that
+/// was indexed from it, such as after a fix to how blocks are processed.
+pub async fn reindex_height(
+    State(app): State<App>,
+    _admin: Admin,
+    Path(height): Path<u32>,
+) -> Result<Json<Reindex>, AppError> {
+    let Some(sync) = &app.sync else {
+        return Err(AppError::NotImplemented(
+            "Blocks can't be reindexed by a read-only instance".to_string(),
+        ));
+    };
+
+    if app.db.block(height)?.is_none() {
+        return Err(AppError::NotFound(format!("Block {height} not found")));
+    }
+
+    Ok(Json(sync.reindex(height).await?))
+}
+
+/// RocksDB's size estimates for each column family, for judging which
+/// optional indexes are worth their disk space. The estimates are only as
+/// fresh as the last flush and compaction.
+pub async fn storage(
+    State(app): State<App>,
+    _admin: Admin,
+) -> Result<Json<Vec<ColumnStats>>, AppError> {
+    Ok(Json(app.db.column_stats()?))
+}
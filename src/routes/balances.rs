@@ -0,0 +1,107 @@
+use axum::extract::{Path, State};
+use chia::protocol::Bytes32;
+use serde::{Deserialize, Serialize};
+
+use crate::db::{IndexKind, PuzzleHashOrder};
+
+use super::{App, AppError, Json};
+
+const MAX_BALANCE_PUZZLE_HASHES: usize = 100;
+
+#[derive(Deserialize)]
+pub struct BalancesRequest {
+    pub puzzle_hashes: Vec<Bytes32>,
+}
+
+#[derive(Serialize)]
+pub struct Balance {
+    pub puzzle_hash: Bytes32,
+    pub balance: u128,
+    pub coin_count: usize,
+}
+
+#[derive(Serialize)]
+pub struct BalancesResponse {
+    pub balances: Vec<Balance>,
+}
+
+/// The total amount and number of unspent coins for each puzzle hash.
+pub async fn balances(
+    State(app): State<App>,
+    Json(request): Json<BalancesRequest>,
+) -> Result<Json<BalancesResponse>, AppError> {
+    app.read(move |app| {
+        app.require_index(IndexKind::PuzzleHash)?;
+
+        if request.puzzle_hashes.len() > MAX_BALANCE_PUZZLE_HASHES {
+            return Err(AppError::InvalidBody(format!(
+                "At most {MAX_BALANCE_PUZZLE_HASHES} puzzle hashes can be requested at once"
+            )));
+        }
+
+        let mut balances = Vec::with_capacity(request.puzzle_hashes.len());
+
+        for puzzle_hash in request.puzzle_hashes {
+            let coin_ids = app
+                .db
+                .coins_by_puzzle_hash(puzzle_hash, PuzzleHashOrder::CoinId)?;
+            let coins = app.db.coins(&coin_ids)?;
+            let spent_heights = app.db.coin_spent_heights(&coin_ids, &coins)?;
+
+            let mut balance = Balance {
+                puzzle_hash,
+                balance: 0,
+                coin_count: 0,
+            };
+
+            for (coin, spent_height) in coins.into_iter().zip(spent_heights) {
+                if let (Some(coin), None) = (coin, spent_height) {
+                    balance.balance += u128::from(coin.amount);
+                    balance.coin_count += 1;
+                }
+            }
+
+            balances.push(balance);
+        }
+
+        Ok(Json(BalancesResponse { balances }))
+    })
+    .await
+}
+
+#[derive(Serialize)]
+pub struct PuzzleHashSummaryResponse {
+    pub balance: u128,
+    pub received: u128,
+    pub sent: u128,
+    pub coin_count: u64,
+    pub unspent_coin_count: u64,
+    pub first_seen_height: Option<u32>,
+    pub last_active_height: Option<u32>,
+}
+
+/// A puzzle hash's balance and lifetime totals, read from the aggregates the
+/// summary index keeps up to date as coins are indexed rather than from its
+/// coins. The heights are the first and last blocks in which one of its
+/// coins was created or spent.
+pub async fn puzzle_hash_summary(
+    State(app): State<App>,
+    Path(puzzle_hash): Path<Bytes32>,
+) -> Result<Json<PuzzleHashSummaryResponse>, AppError> {
+    app.blocking(move |app| {
+        app.require_index(IndexKind::PuzzleHashSummary)?;
+
+        let summary = app.db.puzzle_hash_summary(puzzle_hash)?;
+
+        Ok(Json(PuzzleHashSummaryResponse {
+            balance: summary.received - summary.sent,
+            received: summary.received,
+            sent: summary.sent,
+            coin_count: summary.coins,
+            unspent_coin_count: summary.coins - summary.spent_coins,
+            first_seen_height: summary.first_height,
+            last_active_height: summary.last_height,
+        }))
+    })
+    .await
+}
@@ -0,0 +1,671 @@
+use std::io;
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header::CONTENT_TYPE, HeaderMap, Uri},
+    response::{IntoResponse, Response},
+};
+use chia::protocol::{self, Bytes, Bytes32};
+use itertools::Itertools;
+use rocksdb::Direction;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::error;
+
+use crate::db::BlockRow;
+
+use super::{
+    block_etag, conditional, strip_hex_prefixes, truncate_to_budget, App, AppError, BlockKey,
+    CoinPageRequest, Json, LinkHeader, Path, Query, FORMAT,
+};
+
+#[derive(Serialize)]
+pub struct Block {
+    pub height: u32,
+    #[serde(flatten)]
+    pub row: BlockRow,
+}
+
+#[derive(Serialize)]
+pub struct BlockResponse {
+    pub block: Block,
+}
+
+#[derive(Deserialize)]
+pub struct LatestBlockRequest {
+    /// Returns the block `reorg_buffer` blocks below the peak instead.
+    #[serde(default)]
+    pub finalized: bool,
+}
+
+pub async fn latest_block(
+    State(app): State<App>,
+    Query(query): Query<LatestBlockRequest>,
+) -> Result<Json<BlockResponse>, AppError> {
+    let key = BlockKey::Latest {
+        finalized: query.finalized,
+    };
+
+    let (height, row) = *app
+        .flights
+        .blocks
+        .run(key, || {
+            app.blocking(move |app| latest_block_row(app, query.finalized))
+        })
+        .await?;
+
+    Ok(Json(BlockResponse {
+        block: Block { height, row },
+    }))
+}
+
+fn latest_block_row(app: &App, finalized: bool) -> Result<(u32, BlockRow), AppError> {
+    let Some(peak_height) = app.db.peak_height()? else {
+        return Err(AppError::NotFound(
+            "No blocks have been indexed".to_string(),
+        ));
+    };
+
+    let height = if finalized {
+        let Some(height) = peak_height.checked_sub(app.config.reorg_buffer) else {
+            return Err(AppError::NotFound(
+                "No blocks have been finalized".to_string(),
+            ));
+        };
+        height
+    } else {
+        peak_height
+    };
+
+    let Some(block) = app.db.block(height)? else {
+        return Err(AppError::NotFound(format!("Block {height} not found")));
+    };
+
+    Ok((height, block))
+}
+
+pub async fn block_by_height(
+    State(app): State<App>,
+    headers: HeaderMap,
+    Path(height): Path<u32>,
+) -> Result<Response, AppError> {
+    let (height, row) = *app
+        .flights
+        .blocks
+        .run(BlockKey::Height(height), || {
+            app.blocking(move |app| {
+                let Some(block) = app.db.block(height)? else {
+                    return Err(AppError::NotFound(format!("Block {height} not found")));
+                };
+
+                Ok((height, block))
+            })
+        })
+        .await?;
+
+    let etag = block_etag(&app, height, row.header_hash)?;
+
+    Ok(conditional(
+        &headers,
+        etag,
+        Json(BlockResponse {
+            block: Block { height, row },
+        }),
+    ))
+}
+
+#[derive(Serialize)]
+pub struct BlockCoinCountsResponse {
+    pub created: usize,
+    pub spent: usize,
+}
+
+/// The number of coins the block created and spent, counted from the height
+/// index keys without reading any coin rows. The block's `additions` don't
+/// include its reward coins, which is why they aren't used for `created`.
+pub async fn block_coin_counts(
+    State(app): State<App>,
+    Path(height): Path<u32>,
+) -> Result<Json<BlockCoinCountsResponse>, AppError> {
+    app.read(move |app| {
+        if app.db.block(height)?.is_none() {
+            return Err(AppError::NotFound(format!("Block {height} not found")));
+        }
+
+        Ok(Json(BlockCoinCountsResponse {
+            created: app.db.count_coins_by_created_height(height)?,
+            spent: app.db.count_coins_by_spent_height(height)?,
+        }))
+    })
+    .await
+}
+
+pub async fn block_by_hash(
+    State(app): State<App>,
+    headers: HeaderMap,
+    Path(hash): Path<Bytes32>,
+) -> Result<Response, AppError> {
+    let (height, row) = *app
+        .flights
+        .blocks
+        .run(BlockKey::Hash(hash), || {
+            app.blocking(move |app| {
+                let Some(height) = app.db.block_height(hash)? else {
+                    return Err(AppError::NotFound(format!("Block {hash} not found")));
+                };
+
+                let Some(block) = app.db.block(height)? else {
+                    return Err(AppError::NotFound(format!("Block {hash} not found")));
+                };
+
+                Ok((height, block))
+            })
+        })
+        .await?;
+
+    let etag = block_etag(&app, height, row.header_hash)?;
+
+    Ok(conditional(
+        &headers,
+        etag,
+        Json(BlockResponse {
+            block: Block { height, row },
+        }),
+    ))
+}
+
+#[derive(Serialize)]
+pub struct BlockRangeResponse {
+    pub min_height: Option<u32>,
+    pub max_height: Option<u32>,
+}
+
+/// The heights that blocks can be read from, which are both `null` before the
+/// first block is indexed.
+pub async fn block_range(State(app): State<App>) -> Result<Json<BlockRangeResponse>, AppError> {
+    app.blocking(move |app| {
+        let range = app.db.block_height_range()?;
+
+        Ok(Json(BlockRangeResponse {
+            min_height: range.map(|(min_height, _)| min_height),
+            max_height: range.map(|(_, max_height)| max_height),
+        }))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+pub struct BlocksRequest {
+    #[serde(default)]
+    pub limit: Option<u32>,
+    #[serde(default)]
+    pub start: Option<u32>,
+    #[serde(default)]
+    pub reverse: bool,
+}
+
+#[derive(Serialize)]
+pub struct BlocksResponse {
+    pub blocks: Vec<Block>,
+    /// Only set for `limit=0`, which returns no blocks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_more: Option<bool>,
+}
+
+pub async fn blocks(
+    State(app): State<App>,
+    uri: Uri,
+    Query(query): Query<BlocksRequest>,
+) -> Result<(LinkHeader, Json<BlocksResponse>), AppError> {
+    app.read(move |app| {
+        let (links, blocks) = blocks_page(app, uri, &query)?;
+
+        // Reverse pages start at or below the peak, so they only come up
+        // empty before the first block is indexed.
+        let has_more = if app.config.block_pagination.limit(query.limit) == 0 {
+            let peak_height = app.db.peak_height()?;
+            Some(peak_height.is_some_and(|peak_height| {
+                query.reverse || query.start.unwrap_or(0) <= peak_height
+            }))
+        } else {
+            None
+        };
+
+        Ok((links, Json(BlocksResponse { blocks, has_more })))
+    })
+    .await
+}
+
+#[derive(Serialize)]
+pub struct BlockActivity {
+    pub height: u32,
+    pub created_coins: usize,
+    pub spent_coins: usize,
+}
+
+/// Per-block coin counts, paginated like `blocks`. They're counted from the
+/// height indexes like `block_coin_counts`, so `created_coins` includes the
+/// reward coins that a block's `additions` leave out.
+pub async fn block_activity(
+    State(app): State<App>,
+    uri: Uri,
+    Query(query): Query<BlocksRequest>,
+) -> Result<(LinkHeader, Json<Vec<BlockActivity>>), AppError> {
+    app.read(move |app| {
+        let (links, blocks) = blocks_page(app, uri, &query)?;
+
+        let activity = blocks
+            .into_iter()
+            .map(|block| {
+                Ok(BlockActivity {
+                    height: block.height,
+                    created_coins: app.db.count_coins_by_created_height(block.height)?,
+                    spent_coins: app.db.count_coins_by_spent_height(block.height)?,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok((links, Json(activity)))
+    })
+    .await
+}
+
+#[derive(Serialize)]
+pub struct BlockHeader {
+    pub height: u32,
+    pub hash: Bytes32,
+    pub prev_hash: Bytes32,
+    pub timestamp: Option<u64>,
+    pub has_transactions: bool,
+}
+
+/// Just enough of each block to follow the chain, paginated like `blocks`.
+/// A block has transactions if it spent any coins, and only transaction
+/// blocks have a timestamp.
+pub async fn block_headers(
+    State(app): State<App>,
+    uri: Uri,
+    Query(query): Query<BlocksRequest>,
+) -> Result<(LinkHeader, Json<Vec<BlockHeader>>), AppError> {
+    app.read(move |app| {
+        let (links, blocks) = blocks_page(app, uri, &query)?;
+
+        let headers = blocks
+            .into_iter()
+            .map(|block| BlockHeader {
+                height: block.height,
+                hash: block.row.header_hash,
+                prev_hash: block.row.prev_block_hash,
+                timestamp: block
+                    .row
+                    .transaction_info
+                    .map(|transaction_info| transaction_info.timestamp),
+                has_transactions: block
+                    .row
+                    .transaction_info
+                    .is_some_and(|transaction_info| transaction_info.removals > 0),
+            })
+            .collect_vec();
+
+        Ok((links, Json(headers)))
+    })
+    .await
+}
+
+fn blocks_page(
+    app: &App,
+    uri: Uri,
+    query: &BlocksRequest,
+) -> Result<(LinkHeader, Vec<Block>), AppError> {
+    let limit = app.config.block_pagination.limit(query.limit);
+
+    let mut links = LinkHeader::new(uri);
+
+    let Some(peak_height) = app.db.peak_height()? else {
+        return Ok((links, Vec::new()));
+    };
+
+    // An empty page can't move forward, so it doesn't link anywhere.
+    if limit == 0 {
+        return Ok((links, Vec::new()));
+    }
+
+    let (start, end) = if query.reverse {
+        let end = query.start.unwrap_or(peak_height);
+        let start = end.saturating_sub(limit);
+        (start, end)
+    } else {
+        let start = query.start.unwrap_or(0);
+        let end = start.saturating_add(limit);
+        (start, end)
+    };
+
+    let blocks = app.db.blocks_range(
+        start,
+        end,
+        if query.reverse {
+            Direction::Reverse
+        } else {
+            Direction::Forward
+        },
+    )?;
+
+    let full_page = blocks.len() >= limit as usize;
+
+    // Forward pages cover `start..end`, and reverse pages cover `end` down to
+    // but excluding `start`, so each page picks up where the previous one ended.
+    if query.reverse {
+        if full_page && start > 0 {
+            links = links.next(&[("start", start.to_string())]);
+        }
+
+        if end < peak_height {
+            let prev = end.saturating_add(limit).min(peak_height);
+            links = links.prev(&[("start", prev.to_string())]);
+        }
+    } else {
+        if full_page {
+            links = links.next(&[("start", end.to_string())]);
+        }
+
+        if start > 0 {
+            links = links.prev(&[("start", start.saturating_sub(limit).to_string())]);
+        }
+    }
+
+    let blocks = blocks
+        .into_iter()
+        .enumerate()
+        .map(|(offset, block)| Block {
+            height: if query.reverse {
+                end - offset as u32
+            } else {
+                start + offset as u32
+            },
+            row: block,
+        })
+        .collect_vec();
+
+    Ok((links, blocks))
+}
+
+#[derive(Deserialize)]
+pub struct BlocksByHeightsRequest {
+    pub heights: Vec<u32>,
+}
+
+pub async fn blocks_by_heights(
+    State(app): State<App>,
+    Json(request): Json<BlocksByHeightsRequest>,
+) -> Result<Json<BlocksResponse>, AppError> {
+    app.read(move |app| {
+        let max = app.config.block_pagination.max;
+
+        if request.heights.len() > max as usize {
+            return Err(AppError::InvalidBody(format!(
+                "At most {max} heights can be requested at once"
+            )));
+        }
+
+        let Some(peak_height) = app.db.peak_height()? else {
+            return Ok(Json(BlocksResponse {
+                blocks: Vec::new(),
+                has_more: None,
+            }));
+        };
+
+        if let Some(height) = request.heights.iter().find(|&&height| height > peak_height) {
+            return Err(AppError::InvalidBody(format!(
+                "Height {height} is above the peak height {peak_height}"
+            )));
+        }
+
+        let blocks = app.db.blocks(&request.heights)?;
+
+        Ok(Json(BlocksResponse {
+            blocks: request
+                .heights
+                .into_iter()
+                .zip(blocks)
+                .filter_map(|(height, block)| {
+                    Some(Block {
+                        height,
+                        row: block?,
+                    })
+                })
+                .collect_vec(),
+            has_more: None,
+        }))
+    })
+    .await
+}
+
+#[derive(Serialize)]
+pub struct BlockSpend {
+    pub coin: protocol::Coin,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub puzzle_reveal: Option<Bytes>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub solution: Option<Bytes>,
+}
+
+#[derive(Deserialize)]
+pub struct BlockSpendsRequest {
+    /// Leaves out each spend's puzzle reveal and solution, for when only the
+    /// spent coins are wanted.
+    #[serde(default)]
+    pub omit_blobs: bool,
+}
+
+#[derive(Serialize)]
+pub struct BlockSpendsResponse {
+    pub coin_spends: Vec<BlockSpend>,
+    pub next_cursor: Option<Bytes32>,
+}
+
+pub async fn block_spends(
+    State(app): State<App>,
+    uri: Uri,
+    Path(height): Path<u32>,
+    Query(query): Query<CoinPageRequest>,
+    Query(spends_query): Query<BlockSpendsRequest>,
+) -> Result<(LinkHeader, Json<BlockSpendsResponse>), AppError> {
+    // The page is made of spends rather than coins, so the coin filters and
+    // sorts don't apply.
+    if query.creator_type.is_some()
+        || query.has_hint.is_some()
+        || query.sort.is_some()
+        || query.order.is_some()
+    {
+        return Err(AppError::InvalidParam(
+            "`creator_type`, `has_hint`, `sort` and `order` can't be used with block spends"
+                .to_string(),
+        ));
+    }
+
+    app.read(move |app| {
+        if app.db.block(height)?.is_none() {
+            return Err(AppError::NotFound(format!("Block {height} not found")));
+        }
+
+        let limit = app.config.coin_pagination.limit(query.limit) as usize;
+
+        let coin_ids = app
+            .db
+            .coins_by_spent_height_page(height, query.cursor, limit)?;
+
+        let mut next_cursor = if coin_ids.len() >= limit {
+            coin_ids.last().copied()
+        } else {
+            None
+        };
+
+        let coins = app.db.coins(&coin_ids)?;
+        let spends = app.db.coin_spends(&coin_ids)?;
+
+        let mut coin_spends = coins
+            .into_iter()
+            .zip(spends)
+            .filter_map(|(coin, spend)| {
+                let coin = coin?;
+                let spend = spend?;
+
+                let (puzzle_reveal, solution) = if spends_query.omit_blobs {
+                    (None, None)
+                } else {
+                    (Some(spend.puzzle_reveal), Some(spend.solution))
+                };
+
+                Some(BlockSpend {
+                    coin: protocol::Coin::new(coin.parent_coin_id, coin.puzzle_hash, coin.amount),
+                    puzzle_reveal,
+                    solution,
+                })
+            })
+            .collect_vec();
+
+        // Puzzle reveals can be large enough that a full page doesn't fit.
+        if truncate_to_budget(&mut coin_spends)? {
+            next_cursor = coin_spends.last().map(|spend| spend.coin.coin_id());
+        }
+
+        Ok((
+            LinkHeader::new(uri).next_cursor(next_cursor),
+            Json(BlockSpendsResponse {
+                coin_spends,
+                next_cursor,
+            }),
+        ))
+    })
+    .await
+}
+
+const EXPORT_CHUNK_BLOCKS: u32 = 1000;
+
+#[derive(Deserialize)]
+pub struct BlockExportRequest {
+    #[serde(default)]
+    pub start: u32,
+}
+
+/// Every block from `start` through the peak as newline delimited JSON, one
+/// `Block` per line. Blocks are read and sent a chunk at a time, and reading
+/// waits while the client is behind, so memory use doesn't grow with the
+/// length of the chain. Blocks indexed while the export is running are
+/// included.
+pub async fn export_blocks(
+    State(app): State<App>,
+    Query(query): Query<BlockExportRequest>,
+) -> Response {
+    let hex_prefix = FORMAT.try_with(|format| format.hex_prefix).unwrap_or(true);
+    let (sender, receiver) = mpsc::channel::<Result<Vec<u8>, io::Error>>(4);
+
+    tokio::spawn(async move {
+        let mut start = query.start;
+
+        loop {
+            let chunk = app
+                .read(move |app| {
+                    let Some(peak_height) = app.db.peak_height()? else {
+                        return Ok((0, Vec::new()));
+                    };
+
+                    let end = start.saturating_add(EXPORT_CHUNK_BLOCKS);
+                    let mut blocks = app.db.blocks_range(start, end, Direction::Forward)?;
+
+                    // The range ends before the peak even when it's clamped
+                    // to it, so the peak block is read on its own.
+                    if (start..end).contains(&peak_height) {
+                        blocks.extend(app.db.block(peak_height)?);
+                    }
+
+                    let count = blocks.len() as u32;
+                    let mut chunk = Vec::new();
+
+                    for (offset, row) in blocks.into_iter().enumerate() {
+                        let block = Block {
+                            height: start + offset as u32,
+                            row,
+                        };
+
+                        let mut value =
+                            serde_json::to_value(&block).map_err(anyhow::Error::from)?;
+
+                        if !hex_prefix {
+                            strip_hex_prefixes(&mut value);
+                        }
+
+                        serde_json::to_writer(&mut chunk, &value).map_err(anyhow::Error::from)?;
+                        chunk.push(b'\n');
+                    }
+
+                    Ok((count, chunk))
+                })
+                .await;
+
+            let (count, chunk) = match chunk {
+                Ok(chunk) => chunk,
+                Err(error) => {
+                    error!("Block export from height {} failed: {:?}", start, error);
+                    sender
+                        .send(Err(io::Error::other("Block export failed")))
+                        .await
+                        .ok();
+                    break;
+                }
+            };
+
+            if count == 0 || sender.send(Ok(chunk)).await.is_err() {
+                break;
+            }
+
+            let Some(next) = start.checked_add(count) else {
+                break;
+            };
+
+            start = next;
+        }
+    });
+
+    (
+        [(CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(ReceiverStream::new(receiver)),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::{to_bytes, Body},
+        http::{Request, StatusCode},
+    };
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use crate::db::{insert_test_chain, Database};
+    use crate::routes::test_router;
+
+    #[tokio::test]
+    async fn export_ends_at_the_peak() {
+        let db = Database::open_temp().unwrap();
+        insert_test_chain(&db, 5, 0, Default::default()).unwrap();
+
+        let request = Request::get("/blocks/export.ndjson?start=1")
+            .body(Body::empty())
+            .unwrap();
+        let response = test_router(&db).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let heights = bytes
+            .split(|&byte| byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice::<Value>(line).unwrap()["height"].clone())
+            .collect::<Vec<_>>();
+
+        assert_eq!(heights, [1, 2, 3, 4]);
+    }
+}
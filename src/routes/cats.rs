@@ -0,0 +1,59 @@
+use axum::{extract::State, http::Uri};
+use chia::protocol::Bytes32;
+use serde::{Deserialize, Serialize};
+
+use super::{App, AppError, Json, LinkHeader, Query};
+
+#[derive(Deserialize)]
+pub struct CatsRequest {
+    #[serde(default)]
+    pub limit: Option<u32>,
+    #[serde(default)]
+    pub cursor: Option<Bytes32>,
+}
+
+#[derive(Serialize)]
+pub struct Cat {
+    pub asset_id: Bytes32,
+    pub coin_count: u64,
+}
+
+#[derive(Serialize)]
+pub struct CatsResponse {
+    pub cats: Vec<Cat>,
+    pub next_cursor: Option<Bytes32>,
+}
+
+/// Every asset id that CAT coins have been created with, in ascending order.
+pub async fn cats(
+    State(app): State<App>,
+    uri: Uri,
+    Query(query): Query<CatsRequest>,
+) -> Result<(LinkHeader, Json<CatsResponse>), AppError> {
+    app.read(move |app| {
+        let limit = app.config.coin_pagination.limit(query.limit) as usize;
+
+        let cats = app.db.cat_coin_counts(query.cursor, limit)?;
+
+        let next_cursor = if cats.len() >= limit {
+            cats.last().map(|(asset_id, _)| *asset_id)
+        } else {
+            None
+        };
+
+        Ok((
+            LinkHeader::new(uri).next_cursor(next_cursor),
+            Json(CatsResponse {
+                cats: cats
+                    .into_iter()
+                    .map(|(asset_id, coin_count)| Cat {
+                        asset_id,
+                        coin_count,
+                    })
+                    .collect(),
+                next_cursor,
+            }),
+        ))
+    })
+    .await
+}
@@ -0,0 +1,234 @@
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::{
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
+    response::Response,
+};
+use chia::protocol::Bytes32;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::error;
+
+use crate::events::Event;
+
+use super::{App, AppError};
+
+/// How many heights are read from the database at once while catching up.
+const SYNC_CHUNK_HEIGHTS: u32 = 1000;
+
+#[derive(Deserialize)]
+pub struct CoinSyncRequest {
+    /// The first height to send diffs for.
+    #[serde(default)]
+    pub since: u32,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum CoinSyncMessage {
+    /// The coins created and spent at one height. Heights without any are
+    /// skipped.
+    Diff {
+        height: u32,
+        created: Vec<Bytes32>,
+        spent: Vec<Bytes32>,
+    },
+    /// Every diff up to the peak at `height` has been sent, and diffs are
+    /// pushed as blocks are committed from here on.
+    CaughtUp { height: Option<u32> },
+    /// The diffs above `fork_height` were rolled back and should be dropped.
+    /// Diffs for the new chain follow from `fork_height + 1`.
+    Reverted { fork_height: u32 },
+}
+
+pub async fn coin_sync(
+    State(app): State<App>,
+    Query(query): Query<CoinSyncRequest>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    // Subscribing before the backfill starts means no commit can slip in
+    // between the two phases unnoticed.
+    let receiver = app.events.subscribe();
+    ws.on_upgrade(move |socket| stream_coin_sync(app, socket, receiver, query.since))
+}
+
+/// What has been sent to one client so far.
+struct SyncCursor {
+    /// The next height to send diffs for.
+    next: u32,
+    /// The height and header hash of the peak when diffs were last sent,
+    /// which tells whether a missed reorg touched anything already sent.
+    tip: Option<(u32, Bytes32)>,
+}
+
+async fn stream_coin_sync(
+    app: App,
+    mut socket: WebSocket,
+    mut receiver: broadcast::Receiver<Event>,
+    since: u32,
+) {
+    let mut cursor = SyncCursor {
+        next: since,
+        tip: None,
+    };
+
+    let reason = 'stream: {
+        let peak = match send_diffs(&app, &mut socket, &mut cursor).await {
+            Ok(Some(peak)) => peak,
+            Ok(None) => return,
+            Err(error) => break 'stream close_reason(error),
+        };
+
+        if !send(&mut socket, &CoinSyncMessage::CaughtUp { height: peak }).await {
+            return;
+        }
+
+        loop {
+            match receiver.recv().await {
+                Ok(Event::Reorg { fork_height, .. }) => {
+                    if fork_height < cursor.next.saturating_sub(1) {
+                        if !send(&mut socket, &CoinSyncMessage::Reverted { fork_height }).await {
+                            return;
+                        }
+
+                        cursor.next = fork_height + 1;
+                        cursor.tip = None;
+                    }
+                }
+                Ok(Event::Block { .. }) => {}
+                Err(RecvError::Lagged(_)) => match tip_unchanged(&app, cursor.tip).await {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        break 'stream "Missed a reorg, reconnect from an earlier height"
+                            .to_string()
+                    }
+                    Err(error) => break 'stream close_reason(error),
+                },
+                Err(RecvError::Closed) => return,
+            }
+
+            match send_diffs(&app, &mut socket, &mut cursor).await {
+                Ok(Some(_)) => {}
+                Ok(None) => return,
+                Err(error) => break 'stream close_reason(error),
+            }
+        }
+    };
+
+    socket
+        .send(Message::Close(Some(CloseFrame {
+            code: close_code::ERROR,
+            reason: reason.into(),
+        })))
+        .await
+        .ok();
+}
+
+/// Sends the diffs from the cursor up to the current peak, returning the
+/// peak, or `None` once the client has gone away.
+async fn send_diffs(
+    app: &App,
+    socket: &mut WebSocket,
+    cursor: &mut SyncCursor,
+) -> Result<Option<Option<u32>>, AppError> {
+    loop {
+        let from = cursor.next;
+
+        let (peak, chunk) = app
+            .read(move |app| {
+                let Some(peak) = app.db.peak_height()? else {
+                    return Ok((None, None));
+                };
+
+                if from > peak {
+                    return Ok((Some(peak), None));
+                }
+
+                let to = peak.min(from.saturating_add(SYNC_CHUNK_HEIGHTS - 1));
+
+                let mut diffs = BTreeMap::<u32, (Vec<Bytes32>, Vec<Bytes32>)>::new();
+
+                for (height, coin_id) in app.db.coins_created_between(from, to)? {
+                    diffs.entry(height).or_default().0.push(coin_id);
+                }
+
+                for (height, coin_id) in app.db.coins_spent_between(from, to)? {
+                    diffs.entry(height).or_default().1.push(coin_id);
+                }
+
+                let tip = app.db.block(to)?.map(|block| (to, block.header_hash));
+
+                Ok((Some(peak), Some((to, tip, diffs))))
+            })
+            .await?;
+
+        let Some((to, tip, diffs)) = chunk else {
+            return Ok(Some(peak));
+        };
+
+        for (height, (created, spent)) in diffs {
+            let message = CoinSyncMessage::Diff {
+                height,
+                created,
+                spent,
+            };
+
+            if !send(socket, &message).await {
+                return Ok(None);
+            }
+        }
+
+        cursor.next = to + 1;
+        cursor.tip = tip;
+
+        if Some(to) == peak {
+            return Ok(Some(peak));
+        }
+    }
+}
+
+/// Whether the block the client was last sent diffs up to is still on the
+/// chain. Any reorg below it would have replaced it.
+async fn tip_unchanged(app: &App, tip: Option<(u32, Bytes32)>) -> Result<bool, AppError> {
+    let Some((height, header_hash)) = tip else {
+        return Ok(true);
+    };
+
+    app.blocking(move |app| {
+        Ok(app
+            .db
+            .block(height)?
+            .is_some_and(|block| block.header_hash == header_hash))
+    })
+    .await
+}
+
+/// The reason sent when closing the socket over a failed read. Internal
+/// details stay in the logs, as they do for error responses.
+fn close_reason(error: AppError) -> String {
+    match error {
+        AppError::NotFound(message)
+        | AppError::Unauthorized(message)
+        | AppError::InvalidParam(message)
+        | AppError::InvalidBody(message)
+        | AppError::NotImplemented(message)
+        | AppError::ResponseTooLarge(message)
+        | AppError::TooManyRequests(message) => message,
+        AppError::Internal(error) => {
+            error!("Coin sync failed: {error:?}");
+            "Internal server error".to_string()
+        }
+    }
+}
+
+/// Sends one message, returning `false` once the socket is closed.
+async fn send(socket: &mut WebSocket, message: &CoinSyncMessage) -> bool {
+    let Ok(text) = serde_json::to_string(message) else {
+        return true;
+    };
+
+    socket.send(Message::Text(text.into())).await.is_ok()
+}
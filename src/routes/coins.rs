@@ -0,0 +1,2803 @@
+use anyhow::{anyhow, Result};
+use axum::{
+    extract::State,
+    http::{header::CONTENT_TYPE, Uri},
+    response::{IntoResponse, Response},
+};
+use chia::{
+    protocol::{self, Bytes, Bytes32},
+    traits::Streamable,
+};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clvm::{simulate, uncurry, Uncurried, MAX_REPLAY_COST},
+    db::{CoinRow, CoinType, CreatorType, Database, IndexKind, PuzzleHashOrder},
+};
+
+use super::{
+    address_prefix, amount_xch, mojos_to_xch, puzzle_hash_address, truncate_to_budget, App,
+    AppError, Block, BlockResponse, CoinId, Json, LinkHeader, Path, Prefer, Query, SpendRecord,
+    FORMAT,
+};
+
+/// Heights and timestamps are always written, as `null` when there isn't one,
+/// like every optional height in the responses. Fields are only left out when
+/// they don't apply to the coin or weren't asked for.
+#[derive(Serialize)]
+pub struct Coin {
+    pub coin_id: Bytes32,
+    #[serde(flatten)]
+    pub row: CoinRow,
+    /// The puzzle hash as a bech32m address with the configured prefix, left
+    /// out with `address=false`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    /// The amount in XCH as a decimal string, only with `xch=true` and left
+    /// out for CATs, whose amounts aren't in mojos of XCH.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount_xch: Option<String>,
+    pub created_timestamp: Option<u64>,
+    pub spent_timestamp: Option<u64>,
+    /// Only set for singletons. A singleton spend always recreates it exactly
+    /// once (or melts it), so the unspent coin in a launcher chain is its tip.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_singleton_tip: Option<bool>,
+    /// Only set when listing the coins of a block.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relation: Option<BlockRelation>,
+}
+
+impl Coin {
+    pub fn new(
+        db: &Database,
+        coin_id: Bytes32,
+        mut row: CoinRow,
+        spent_height: Option<u32>,
+    ) -> Result<Self> {
+        row.spent_height = spent_height;
+
+        let created_timestamp = db.block_timestamp(row.created_height)?;
+        let spent_timestamp = match spent_height {
+            Some(spent_height) => db.block_timestamp(spent_height)?,
+            None => None,
+        };
+
+        let is_singleton_tip =
+            matches!(row.kind, CoinType::Singleton { .. }).then_some(spent_height.is_none());
+
+        let address = address_prefix()
+            .map(|prefix| puzzle_hash_address(&prefix, row.puzzle_hash))
+            .transpose()?;
+
+        let amount_xch = (amount_xch() && !matches!(row.kind, CoinType::Cat { .. }))
+            .then(|| mojos_to_xch(row.amount));
+
+        Ok(Self {
+            coin_id,
+            row,
+            address,
+            amount_xch,
+            created_timestamp,
+            spent_timestamp,
+            is_singleton_tip,
+            relation: None,
+        })
+    }
+
+    pub fn load(db: &Database, coin_id: Bytes32) -> Result<Option<Self>> {
+        let Some(row) = db.coin(coin_id)? else {
+            return Ok(None);
+        };
+        let spent_height = db.coin_spent_height(coin_id, &row)?;
+        Self::new(db, coin_id, row, spent_height).map(Some)
+    }
+
+    /// Loads each coin, skipping any that aren't in the database.
+    pub fn load_many(
+        db: &Database,
+        coin_ids: impl IntoIterator<Item = Bytes32>,
+    ) -> Result<Vec<Self>> {
+        let mut coins = Vec::new();
+
+        for coin_id in coin_ids {
+            if let Some(coin) = Self::load(db, coin_id)? {
+                coins.push(coin);
+            }
+        }
+
+        Ok(coins)
+    }
+
+    pub fn has_creator_type(&self, creator_type: Option<CreatorType>) -> bool {
+        creator_type.is_none() || self.row.creator_type == creator_type
+    }
+
+    /// Hinted coins are the ones in the hint index, which is every coin whose
+    /// row has a hint.
+    pub fn has_hint(&self, has_hint: Option<bool>) -> bool {
+        has_hint.is_none_or(|has_hint| self.row.hint.is_some() == has_hint)
+    }
+}
+
+/// None of the list indexes are keyed by amount or height, so sorting only
+/// reorders the coins within the page that was read. Cursors still follow the
+/// default order, which keeps pagination stable regardless of the sort.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoinSort {
+    Amount,
+    CreatedHeight,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// An `order` without a `sort` would otherwise be silently ignored.
+pub fn check_sort(sort: Option<CoinSort>, order: Option<SortOrder>) -> Result<(), String> {
+    if sort.is_none() && order.is_some() {
+        return Err("`order` can only be used together with `sort`".to_string());
+    }
+
+    Ok(())
+}
+
+pub fn sort_coins(coins: &mut [Coin], sort: Option<CoinSort>, order: Option<SortOrder>) {
+    let Some(sort) = sort else {
+        return;
+    };
+
+    match sort {
+        CoinSort::Amount => coins.sort_by_key(|coin| coin.row.amount),
+        CoinSort::CreatedHeight => coins.sort_by_key(|coin| coin.row.created_height),
+    }
+
+    if order.unwrap_or_default() == SortOrder::Desc {
+        coins.reverse();
+    }
+}
+
+/// Filtering by `creator_type` and `has_hint` happens after a page is read, so
+/// filtered pages can be shorter than the limit even when more coins follow.
+#[derive(Deserialize)]
+pub struct CoinsRequest {
+    #[serde(default)]
+    pub limit: Option<u32>,
+    #[serde(default)]
+    pub creator_type: Option<CreatorType>,
+    #[serde(default)]
+    pub has_hint: Option<bool>,
+    #[serde(default)]
+    pub sort: Option<CoinSort>,
+    #[serde(default)]
+    pub order: Option<SortOrder>,
+}
+
+#[derive(Serialize)]
+pub struct CoinsResponse {
+    pub coins: Vec<Coin>,
+}
+
+/// Which of a block's coins are listed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockCoinKind {
+    Created,
+    Spent,
+    #[default]
+    Both,
+}
+
+/// How a coin relates to the block it was listed for. A coin can be both
+/// created and spent in the same block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockRelation {
+    Created,
+    Spent,
+    CreatedAndSpent,
+}
+
+#[derive(Deserialize)]
+pub struct BlockCoinsRequest {
+    #[serde(default)]
+    pub limit: Option<u32>,
+    #[serde(default)]
+    pub cursor: Option<Bytes32>,
+    #[serde(default)]
+    pub creator_type: Option<CreatorType>,
+    #[serde(default)]
+    pub has_hint: Option<bool>,
+    #[serde(default)]
+    pub sort: Option<CoinSort>,
+    #[serde(default)]
+    pub order: Option<SortOrder>,
+    #[serde(default)]
+    pub kind: BlockCoinKind,
+}
+
+/// Coins created in the block followed by the rest of the coins spent in it,
+/// each in ascending coin id order.
+pub async fn coins_by_block(
+    State(app): State<App>,
+    uri: Uri,
+    Path(hash): Path<Bytes32>,
+    Query(query): Query<BlockCoinsRequest>,
+) -> Result<(LinkHeader, Json<CoinPageResponse>), AppError> {
+    check_sort(query.sort, query.order).map_err(AppError::InvalidParam)?;
+
+    app.read(move |app| {
+        let Some(height) = app.db.block_height(hash)? else {
+            return Err(AppError::NotFound(format!("Block {hash} not found")));
+        };
+
+        Ok(block_coins(app, height, &query)?.with_links(uri))
+    })
+    .await
+}
+
+/// The same as `coins_by_block`, without looking up the block by its hash.
+pub async fn coins_by_block_height(
+    State(app): State<App>,
+    uri: Uri,
+    Path(height): Path<u32>,
+    Query(query): Query<BlockCoinsRequest>,
+) -> Result<(LinkHeader, Json<CoinPageResponse>), AppError> {
+    check_sort(query.sort, query.order).map_err(AppError::InvalidParam)?;
+
+    app.read(move |app| {
+        if app.db.block(height)?.is_none() {
+            return Err(AppError::NotFound(format!("Block {height} not found")));
+        }
+
+        Ok(block_coins(app, height, &query)?.with_links(uri))
+    })
+    .await
+}
+
+/// The coins created in the block, paged through the created height index
+/// without reading the block's spent coins.
+pub async fn block_additions(
+    State(app): State<App>,
+    uri: Uri,
+    Path(height): Path<u32>,
+    Query(query): Query<CoinPageRequest>,
+) -> Result<(LinkHeader, Json<CoinPageResponse>), AppError> {
+    block_index_page(app, uri, height, query, HeightIndex::Created).await
+}
+
+/// The coins spent in the block, paged through the spent height index like
+/// `block_additions`.
+pub async fn block_removals(
+    State(app): State<App>,
+    uri: Uri,
+    Path(height): Path<u32>,
+    Query(query): Query<CoinPageRequest>,
+) -> Result<(LinkHeader, Json<CoinPageResponse>), AppError> {
+    block_index_page(app, uri, height, query, HeightIndex::Spent).await
+}
+
+/// The height index a block's page is read from.
+#[derive(Debug, Clone, Copy)]
+enum HeightIndex {
+    Created,
+    Spent,
+}
+
+async fn block_index_page(
+    app: App,
+    uri: Uri,
+    height: u32,
+    query: CoinPageRequest,
+    index: HeightIndex,
+) -> Result<(LinkHeader, Json<CoinPageResponse>), AppError> {
+    check_sort(query.sort, query.order).map_err(AppError::InvalidParam)?;
+
+    app.read(move |app| {
+        if app.db.block(height)?.is_none() {
+            return Err(AppError::NotFound(format!("Block {height} not found")));
+        }
+
+        let limit = app.config.coin_pagination.limit(query.limit) as usize;
+        let fetch_limit = CoinPageResponse::fetch_limit(limit);
+
+        let coin_ids = match index {
+            HeightIndex::Created => {
+                app.db
+                    .coins_by_created_height_page(height, query.cursor, fetch_limit)?
+            }
+            HeightIndex::Spent => {
+                app.db
+                    .coins_by_spent_height_page(height, query.cursor, fetch_limit)?
+            }
+        };
+
+        Ok(CoinPageResponse::new(&app.db, coin_ids, limit)?
+            .filter(query.creator_type, query.has_hint)
+            .sort(query.sort, query.order)
+            .with_links(uri))
+    })
+    .await
+}
+
+#[derive(Serialize)]
+pub struct LatestCoinsResponse {
+    pub height: u32,
+    pub header_hash: Bytes32,
+    pub coins: Vec<Coin>,
+    pub next_cursor: Option<Bytes32>,
+}
+
+/// The coins created and spent in the peak block. The peak can move between
+/// requests, so later pages are read from `/coins/block/height/{height}` with
+/// the returned height rather than from here.
+pub async fn latest_coins(
+    State(app): State<App>,
+    Query(query): Query<BlockCoinsRequest>,
+) -> Result<Json<LatestCoinsResponse>, AppError> {
+    check_sort(query.sort, query.order).map_err(AppError::InvalidParam)?;
+
+    if query.cursor.is_some() {
+        return Err(AppError::InvalidParam(
+            "`cursor` can't be used with the latest coins, page through \
+             `/coins/block/height/{height}` instead"
+                .to_string(),
+        ));
+    }
+
+    app.read(move |app| {
+        let block = match app.db.peak_height()? {
+            Some(height) => app.db.block(height)?.map(|block| (height, block)),
+            None => None,
+        };
+
+        let Some((height, block)) = block else {
+            return Err(AppError::NotFound(
+                "No blocks have been indexed".to_string(),
+            ));
+        };
+
+        let page = block_coins(app, height, &query)?;
+
+        Ok(Json(LatestCoinsResponse {
+            height,
+            header_hash: block.header_hash,
+            coins: page.coins,
+            next_cursor: page.next_cursor,
+        }))
+    })
+    .await
+}
+
+/// Unlike the other pages, `creator_type` and `has_hint` are applied while the
+/// page is read, so only the last page of a block is ever short. The cursor is
+/// the last coin id that was read, whether or not it matched.
+fn block_coins(app: &App, height: u32, query: &BlockCoinsRequest) -> Result<CoinPageResponse> {
+    let limit = app.config.coin_pagination.limit(query.limit) as usize;
+
+    let coin_ids = match query.kind {
+        BlockCoinKind::Created => app.db.coins_by_created_height(height)?,
+        BlockCoinKind::Spent => app.db.coins_by_spent_height(height)?,
+        BlockCoinKind::Both => [
+            app.db.coins_by_created_height(height)?,
+            app.db.coins_by_spent_height(height)?,
+        ]
+        .concat()
+        .into_iter()
+        .unique()
+        .collect_vec(),
+    };
+
+    let skip = match query.cursor {
+        Some(cursor) => coin_ids
+            .iter()
+            .position(|coin_id| *coin_id == cursor)
+            .map_or(coin_ids.len(), |index| index + 1),
+        None => 0,
+    };
+
+    if limit == 0 {
+        return Ok(CoinPageResponse::has_more(skip < coin_ids.len()));
+    }
+
+    let mut coins = Vec::new();
+    let mut next_cursor = None;
+
+    for (index, &coin_id) in coin_ids.iter().enumerate().skip(skip) {
+        if coins.len() >= limit {
+            next_cursor = coin_ids.get(index - 1).copied();
+            break;
+        }
+
+        let Some(mut coin) = Coin::load(&app.db, coin_id)? else {
+            continue;
+        };
+
+        coin.relation = Some(
+            match (
+                coin.row.created_height == height,
+                coin.row.spent_height == Some(height),
+            ) {
+                (true, true) => BlockRelation::CreatedAndSpent,
+                (true, false) => BlockRelation::Created,
+                (false, _) => BlockRelation::Spent,
+            },
+        );
+
+        if coin.has_creator_type(query.creator_type) && coin.has_hint(query.has_hint) {
+            coins.push(coin);
+        }
+    }
+
+    if truncate_to_budget(&mut coins)? {
+        next_cursor = coins.last().map(|coin| coin.coin_id);
+    }
+
+    sort_coins(&mut coins, query.sort, query.order);
+
+    Ok(CoinPageResponse {
+        coins,
+        next_cursor,
+        has_more: None,
+    })
+}
+
+#[derive(Deserialize)]
+pub struct ChildCoinsRequest {
+    /// Only children with this puzzle hash, such as the expected next coin of
+    /// a singleton.
+    #[serde(default)]
+    pub puzzle_hash: Option<Bytes32>,
+}
+
+/// The coin's children ordered by coin id. With `creator_type` or `has_hint`,
+/// children that don't match are skipped while the index is read, and at most
+/// `MAX_FILTER_SCAN` entries are read per request. A page that hits that limit
+/// can be short, and its cursor is the last coin id that was read.
+pub async fn coins_by_parent(
+    State(app): State<App>,
+    uri: Uri,
+    Path(CoinId(coin_id)): Path<CoinId>,
+    Query(query): Query<CoinPageRequest>,
+    Query(children_query): Query<ChildCoinsRequest>,
+) -> Result<(LinkHeader, Json<CoinPageResponse>), AppError> {
+    check_sort(query.sort, query.order).map_err(AppError::InvalidParam)?;
+
+    app.read(move |app| {
+        app.require_index(IndexKind::ParentCoinId)?;
+
+        let limit = app.config.coin_pagination.limit(query.limit) as usize;
+
+        let puzzle_hash = children_query.puzzle_hash;
+
+        let page = filtered_coin_page(&app.db, &query, limit, |cursor, limit| match puzzle_hash {
+            Some(puzzle_hash) => {
+                app.db
+                    .coins_by_parent_coin_id_and_puzzle_hash(coin_id, puzzle_hash, cursor, limit)
+            }
+            None => app.db.coins_by_parent_coin_id_page(coin_id, cursor, limit),
+        })?;
+
+        Ok(page.with_links(uri))
+    })
+    .await
+}
+
+#[derive(Serialize)]
+pub struct CountResponse {
+    pub count: usize,
+}
+
+pub async fn count_coins_by_parent(
+    State(app): State<App>,
+    Path(CoinId(coin_id)): Path<CoinId>,
+) -> Result<Json<CountResponse>, AppError> {
+    app.read(move |app| {
+        app.require_index(IndexKind::ParentCoinId)?;
+
+        Ok(Json(CountResponse {
+            count: app.db.count_coins_by_parent_coin_id(coin_id)?,
+        }))
+    })
+    .await
+}
+
+#[derive(Serialize)]
+pub struct PuzzleHashUsedResponse {
+    pub used: bool,
+}
+
+/// Whether the puzzle hash has ever received a coin, for warning about
+/// address reuse without fetching the coins themselves.
+pub async fn puzzle_hash_used(
+    State(app): State<App>,
+    Path(puzzle_hash): Path<Bytes32>,
+) -> Result<Json<PuzzleHashUsedResponse>, AppError> {
+    app.blocking(move |app| {
+        app.require_index(IndexKind::PuzzleHash)?;
+
+        Ok(Json(PuzzleHashUsedResponse {
+            used: app.db.puzzle_hash_used(puzzle_hash)?,
+        }))
+    })
+    .await
+}
+
+/// The earliest coin created with the puzzle hash. With the height ordered
+/// index that's the first of its coins, otherwise the index is ordered by coin
+/// id rather than height, so every coin with the puzzle hash has to be read.
+pub async fn first_coin_by_puzzle_hash(
+    State(app): State<App>,
+    Path(puzzle_hash): Path<Bytes32>,
+) -> Result<Json<CoinResponse>, AppError> {
+    app.read(move |app| {
+        let order = if app.db.is_index_built(IndexKind::PuzzleHashHeight) {
+            PuzzleHashOrder::CreatedHeight
+        } else {
+            app.require_index(IndexKind::PuzzleHash)?;
+            PuzzleHashOrder::CoinId
+        };
+
+        let coin_ids = app.db.coins_by_puzzle_hash(puzzle_hash, order)?;
+
+        let first = match order {
+            PuzzleHashOrder::CreatedHeight => {
+                let mut first = None;
+
+                for coin_id in coin_ids {
+                    if let Some(row) = app.db.coin(coin_id)? {
+                        first = Some((coin_id, row));
+                        break;
+                    }
+                }
+
+                first
+            }
+            PuzzleHashOrder::CoinId => {
+                let rows = app.db.coins(&coin_ids)?;
+
+                coin_ids
+                    .into_iter()
+                    .zip(rows)
+                    .filter_map(|(coin_id, row)| Some((coin_id, row?)))
+                    .min_by_key(|(coin_id, row)| (row.created_height, *coin_id))
+            }
+        };
+
+        let Some((coin_id, row)) = first else {
+            return Err(AppError::NotFound(format!(
+                "No coins found with puzzle hash {puzzle_hash}"
+            )));
+        };
+
+        let spend = app.db.coin_spend(coin_id)?;
+
+        Ok(Json(CoinResponse {
+            coin: Coin::new(
+                &app.db,
+                coin_id,
+                row,
+                spend.as_ref().map(|spend| spend.spent_height),
+            )?,
+            puzzle_reveal: None,
+            solution: None,
+            puzzle_reveal_size: spend.as_ref().map(|spend| spend.puzzle_reveal.len()),
+            solution_size: spend.as_ref().map(|spend| spend.solution.len()),
+            uncurried: None,
+            cost_attribution: None,
+        }))
+    })
+    .await
+}
+
+/// At most this many index entries are read for the latest coins, since with
+/// `unspent` most of them can be skipped.
+const MAX_LATEST_SCAN: usize = 10_000;
+
+#[derive(Deserialize)]
+pub struct LatestCoinsByPuzzleHashRequest {
+    #[serde(default)]
+    pub limit: Option<u32>,
+    #[serde(default)]
+    pub unspent: bool,
+}
+
+/// The puzzle hash's most recently created coins, newest first, read backwards
+/// through the height ordered puzzle hash index. There are no further pages,
+/// since this is only meant for the last few coins. With `unspent`, spent
+/// coins are skipped as the index is read, so fewer than `limit` coins can be
+/// returned once `MAX_LATEST_SCAN` entries have been read.
+pub async fn latest_coins_by_puzzle_hash(
+    State(app): State<App>,
+    Path(puzzle_hash): Path<Bytes32>,
+    Query(query): Query<LatestCoinsByPuzzleHashRequest>,
+) -> Result<Json<CoinsResponse>, AppError> {
+    app.read(move |app| {
+        app.require_index(IndexKind::PuzzleHashHeight)?;
+
+        let limit = app.config.coin_pagination.limit(query.limit) as usize;
+
+        let mut coins = Vec::new();
+        let mut cursor = None;
+        let mut scanned = 0;
+
+        while coins.len() < limit && scanned < MAX_LATEST_SCAN {
+            let page = app
+                .db
+                .newest_coins_by_puzzle_hash(puzzle_hash, cursor, limit)?;
+            let exhausted = page.len() < limit;
+
+            for (created_height, coin_id) in page {
+                cursor = Some((created_height, coin_id));
+                scanned += 1;
+
+                if let Some(coin) = Coin::load(&app.db, coin_id)? {
+                    if !query.unspent || coin.row.spent_height.is_none() {
+                        coins.push(coin);
+                    }
+                }
+
+                if coins.len() >= limit {
+                    break;
+                }
+            }
+
+            if exhausted {
+                break;
+            }
+        }
+
+        truncate_to_budget(&mut coins)?;
+
+        Ok(Json(CoinsResponse { coins }))
+    })
+    .await
+}
+
+/// How many puzzle hash index entries a coin history page reads at most while
+/// looking for unspent coins, since spent ones are passed over.
+const MAX_HISTORY_SCAN: usize = 10_000;
+
+#[derive(Deserialize)]
+pub struct CoinHistoryRequest {
+    #[serde(default)]
+    pub limit: Option<u32>,
+    #[serde(default)]
+    pub cursor: Option<Bytes>,
+    #[serde(default)]
+    pub include_unspent: Option<bool>,
+}
+
+/// Where a coin history page carries on from.
+#[derive(Debug, Clone, Copy)]
+enum HistoryCursor {
+    /// The last coin id read from the puzzle hash index for the unspent coins.
+    Unspent(Bytes32),
+    /// The spent height and coin id of the last spent coin listed.
+    Spent(u32, Bytes32),
+}
+
+impl HistoryCursor {
+    fn parse(cursor: &[u8]) -> Option<Self> {
+        match cursor.len() {
+            32 => Some(Self::Unspent(Bytes32::try_from(cursor).ok()?)),
+            36 => Some(Self::Spent(
+                u32::from_be_bytes(cursor[..4].try_into().ok()?),
+                Bytes32::try_from(&cursor[4..]).ok()?,
+            )),
+            _ => None,
+        }
+    }
+
+    fn to_bytes(self) -> Bytes {
+        match self {
+            Self::Unspent(coin_id) => Bytes::new(coin_id.to_vec()),
+            Self::Spent(spent_height, coin_id) => {
+                Bytes::new([&spent_height.to_be_bytes(), coin_id.as_ref()].concat())
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct CoinHistoryResponse {
+    pub coins: Vec<Coin>,
+    pub next_cursor: Option<Bytes>,
+    /// Only set for `limit=0`, which returns no coins.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_more: Option<bool>,
+}
+
+/// The puzzle hash's coins ordered by spent height, newest first, with the
+/// unspent coins on top (unless `include_unspent=false`) and ties broken by
+/// coin id.
+///
+/// Unspent coins are found by reading the puzzle hash index in coin id order,
+/// and at most `MAX_HISTORY_SCAN` entries are read per request, so a page can
+/// be short before the spent coins start. The cursor is then the hex coin id
+/// read last. Spent coins are read by seeking to each height the puzzle hash
+/// was active at, and their cursor is the hex `spent_height || coin_id` of the
+/// last one listed. Either way, no page reads what an earlier one already has.
+pub async fn coin_history(
+    State(app): State<App>,
+    uri: Uri,
+    Path(puzzle_hash): Path<Bytes32>,
+    Query(query): Query<CoinHistoryRequest>,
+) -> Result<(LinkHeader, Json<CoinHistoryResponse>), AppError> {
+    let include_unspent = query.include_unspent.unwrap_or(true);
+
+    let cursor = match &query.cursor {
+        Some(cursor) => match HistoryCursor::parse(cursor) {
+            Some(HistoryCursor::Unspent(_)) if !include_unspent => {
+                return Err(AppError::InvalidParam(
+                    "An unspent coin `cursor` can't be used with `include_unspent=false`"
+                        .to_string(),
+                ));
+            }
+            Some(cursor) => Some(cursor),
+            None => return Err(AppError::InvalidParam("Invalid `cursor`".to_string())),
+        },
+        None => None,
+    };
+
+    app.read(move |app| {
+        app.require_index(IndexKind::PuzzleHash)?;
+        app.require_index(IndexKind::PuzzleHashSummary)?;
+
+        let limit = app.config.coin_pagination.limit(query.limit) as usize;
+        let fetch_limit = CoinPageResponse::fetch_limit(limit);
+
+        let mut coin_ids = Vec::new();
+        let mut next_cursor = None;
+
+        let spent_cursor = match cursor {
+            Some(HistoryCursor::Spent(spent_height, coin_id)) => Some((spent_height, coin_id)),
+            _ if !include_unspent => None,
+            cursor => {
+                let mut cursor = match cursor {
+                    Some(HistoryCursor::Unspent(coin_id)) => Some(coin_id),
+                    _ => None,
+                };
+                let mut scanned = 0;
+
+                loop {
+                    let page =
+                        app.db
+                            .coins_by_puzzle_hash_page(puzzle_hash, cursor, fetch_limit)?;
+                    let exhausted = page.len() < fetch_limit;
+
+                    let coins = app.db.coins(&page)?;
+                    let spent_heights = app.db.coin_spent_heights(&page, &coins)?;
+
+                    for ((coin_id, coin), spent_height) in
+                        page.into_iter().zip(coins).zip(spent_heights)
+                    {
+                        cursor = Some(coin_id);
+                        scanned += 1;
+
+                        if coin.is_some() && spent_height.is_none() {
+                            coin_ids.push(coin_id);
+                        }
+
+                        if coin_ids.len() >= fetch_limit {
+                            break;
+                        }
+                    }
+
+                    if coin_ids.len() >= fetch_limit || scanned >= MAX_HISTORY_SCAN {
+                        next_cursor = cursor.map(HistoryCursor::Unspent);
+                        break;
+                    }
+
+                    if exhausted {
+                        break;
+                    }
+                }
+
+                None
+            }
+        };
+
+        // The spent coins only follow once every unspent one has been listed.
+        if next_cursor.is_none() && coin_ids.len() < fetch_limit {
+            let spent = app.db.spent_coins_by_puzzle_hash(
+                puzzle_hash,
+                spent_cursor,
+                fetch_limit - coin_ids.len(),
+            )?;
+
+            if coin_ids.len() + spent.len() >= fetch_limit {
+                next_cursor = spent
+                    .last()
+                    .map(|&(spent_height, coin_id)| HistoryCursor::Spent(spent_height, coin_id));
+            }
+
+            coin_ids.extend(spent.into_iter().map(|(_, coin_id)| coin_id));
+        }
+
+        if limit == 0 {
+            return Ok((
+                LinkHeader::new(uri),
+                Json(CoinHistoryResponse {
+                    coins: Vec::new(),
+                    next_cursor: None,
+                    has_more: Some(!coin_ids.is_empty() || next_cursor.is_some()),
+                }),
+            ));
+        }
+
+        let mut coins = Coin::load_many(&app.db, coin_ids)?;
+
+        if truncate_to_budget(&mut coins)? {
+            next_cursor = coins.last().map(|coin| match coin.row.spent_height {
+                Some(spent_height) => HistoryCursor::Spent(spent_height, coin.coin_id),
+                None => HistoryCursor::Unspent(coin.coin_id),
+            });
+        }
+
+        let next_cursor = next_cursor.map(HistoryCursor::to_bytes);
+
+        Ok((
+            LinkHeader::new(uri).next_cursor(next_cursor.as_ref()),
+            Json(CoinHistoryResponse {
+                coins,
+                next_cursor,
+                has_more: None,
+            }),
+        ))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+pub struct CoinIdsRequest {
+    #[serde(default)]
+    pub limit: Option<u32>,
+    #[serde(default)]
+    pub cursor: Option<Bytes32>,
+}
+
+/// Just the ids of the puzzle hash's coins, in ascending order. The next
+/// cursor is only in the `Link` header, since the body is a bare array.
+pub async fn coin_ids_by_puzzle_hash(
+    State(app): State<App>,
+    uri: Uri,
+    Path(puzzle_hash): Path<Bytes32>,
+    Query(query): Query<CoinIdsRequest>,
+) -> Result<(LinkHeader, Json<Vec<Bytes32>>), AppError> {
+    app.read(move |app| {
+        app.require_index(IndexKind::PuzzleHash)?;
+
+        let limit = app.config.coin_pagination.limit(query.limit) as usize;
+
+        let coin_ids = app
+            .db
+            .coins_by_puzzle_hash_page(puzzle_hash, query.cursor, limit)?;
+
+        let next_cursor = if coin_ids.len() >= limit {
+            coin_ids.last().copied()
+        } else {
+            None
+        };
+
+        Ok((
+            LinkHeader::new(uri).next_cursor(next_cursor),
+            Json(coin_ids),
+        ))
+    })
+    .await
+}
+
+impl From<&CoinRow> for ChiaCoin {
+    fn from(row: &CoinRow) -> Self {
+        Self {
+            parent_coin_info: row.parent_coin_id,
+            puzzle_hash: row.puzzle_hash,
+            amount: row.amount,
+        }
+    }
+}
+
+/// A spend in the shape of the full node RPC's `CoinSpend`.
+#[derive(Serialize)]
+pub struct ChiaCoinSpend {
+    pub coin: ChiaCoin,
+    pub puzzle_reveal: Bytes,
+    pub solution: Bytes,
+}
+
+#[derive(Serialize)]
+pub struct PuzzleHashSpendsResponse {
+    pub coin_spends: Vec<ChiaCoinSpend>,
+    pub next_cursor: Option<Bytes32>,
+}
+
+/// The spends of the puzzle hash's coins, for rebuilding a wallet's history.
+/// Pages are read from the puzzle hash index in coin id order and unspent
+/// coins are skipped, so a page can be short even when more spends follow.
+pub async fn spends_by_puzzle_hash(
+    State(app): State<App>,
+    uri: Uri,
+    Path(puzzle_hash): Path<Bytes32>,
+    Query(query): Query<CoinIdsRequest>,
+) -> Result<(LinkHeader, Json<PuzzleHashSpendsResponse>), AppError> {
+    app.read(move |app| {
+        app.require_index(IndexKind::PuzzleHash)?;
+
+        let limit = app.config.spend_pagination.limit(query.limit) as usize;
+
+        let coin_ids = app
+            .db
+            .coins_by_puzzle_hash_page(puzzle_hash, query.cursor, limit)?;
+
+        let mut next_cursor = if coin_ids.len() >= limit {
+            coin_ids.last().copied()
+        } else {
+            None
+        };
+
+        let rows = app.db.coins(&coin_ids)?;
+        let spends = app.db.coin_spends(&coin_ids)?;
+
+        let mut coin_spends = Vec::new();
+        let mut spend_ids = Vec::new();
+
+        for ((coin_id, row), spend) in coin_ids.into_iter().zip(rows).zip(spends) {
+            let (Some(row), Some(spend)) = (row, spend) else {
+                continue;
+            };
+
+            coin_spends.push(ChiaCoinSpend {
+                coin: ChiaCoin::from(&row),
+                puzzle_reveal: spend.puzzle_reveal,
+                solution: spend.solution,
+            });
+            spend_ids.push(coin_id);
+        }
+
+        // Puzzle reveals can be large enough that a full page doesn't fit.
+        if truncate_to_budget(&mut coin_spends)? {
+            next_cursor = spend_ids.get(coin_spends.len() - 1).copied();
+        }
+
+        Ok((
+            LinkHeader::new(uri).next_cursor(next_cursor),
+            Json(PuzzleHashSpendsResponse {
+                coin_spends,
+                next_cursor,
+            }),
+        ))
+    })
+    .await
+}
+
+/// The puzzle hash's coins created at the height, found by intersecting the
+/// two indexes rather than reading either one in full, and paged by coin id
+/// with the filters applied like `coins_by_parent`.
+pub async fn coins_by_puzzle_hash_created_at(
+    State(app): State<App>,
+    uri: Uri,
+    Path((puzzle_hash, height)): Path<(Bytes32, u32)>,
+    Query(query): Query<CoinPageRequest>,
+) -> Result<(LinkHeader, Json<CoinPageResponse>), AppError> {
+    check_sort(query.sort, query.order).map_err(AppError::InvalidParam)?;
+
+    app.read(move |app| {
+        app.require_index(IndexKind::PuzzleHash)?;
+
+        let limit = app.config.coin_pagination.limit(query.limit) as usize;
+
+        let page = filtered_coin_page(&app.db, &query, limit, |cursor, limit| {
+            app.db
+                .coins_by_puzzle_hash_and_created_height(puzzle_hash, height, cursor, limit)
+        })?;
+
+        Ok(page.with_links(uri))
+    })
+    .await
+}
+
+/// Index entries read by one request to `coins_at_height`, whether or not
+/// the coins turn out to be unspent at the height.
+const MAX_SNAPSHOT_SCAN: usize = 10_000;
+
+#[derive(Deserialize)]
+pub struct CoinsAtHeightRequest {
+    #[serde(default)]
+    pub limit: Option<u32>,
+    #[serde(default)]
+    pub cursor: Option<Bytes32>,
+}
+
+/// The puzzle hash's coins that were unspent as of the end of `height`, which
+/// is what its balance was made up of at that point. The spent status and
+/// timestamps in each coin are as they are now.
+///
+/// This reads the height ordered puzzle hash index from the puzzle hash's
+/// first coin up to `height` and checks each coin's spent height, so its cost
+/// grows with every coin the puzzle hash had by then, spent or not, rather
+/// than with the coins returned. At most `MAX_SNAPSHOT_SCAN` entries are read
+/// per request, so a page can hold fewer than `limit` coins (or none) and
+/// still have a `next_cursor` to carry on from.
+pub async fn coins_at_height(
+    State(app): State<App>,
+    uri: Uri,
+    Path((puzzle_hash, height)): Path<(Bytes32, u32)>,
+    Query(query): Query<CoinsAtHeightRequest>,
+) -> Result<(LinkHeader, Json<CoinPageResponse>), AppError> {
+    app.read(move |app| {
+        app.require_index(IndexKind::PuzzleHashHeight)?;
+
+        let limit = app.config.coin_pagination.limit(query.limit) as usize;
+
+        // The cursor is a coin id like every other page, and its created
+        // height is where the index picks up again.
+        let mut cursor = match query.cursor {
+            Some(coin_id) => {
+                let Some(coin) = app.db.coin(coin_id)? else {
+                    return Err(AppError::InvalidParam(format!(
+                        "Cursor coin {coin_id} not found"
+                    )));
+                };
+                Some((coin.created_height, coin_id))
+            }
+            None => None,
+        };
+
+        let mut coins = Vec::new();
+        let mut scanned = 0;
+        let mut more = true;
+
+        'scan: while more && coins.len() < limit && scanned < MAX_SNAPSHOT_SCAN {
+            let batch = limit.min(MAX_SNAPSHOT_SCAN - scanned);
+            let page =
+                app.db
+                    .coins_by_puzzle_hash_created_through(puzzle_hash, height, cursor, batch)?;
+            let len = page.len();
+            more = len >= batch;
+
+            for (index, (created_height, coin_id)) in page.into_iter().enumerate() {
+                cursor = Some((created_height, coin_id));
+                scanned += 1;
+
+                if let Some(coin) = Coin::load(&app.db, coin_id)? {
+                    if coin
+                        .row
+                        .spent_height
+                        .is_none_or(|spent_height| spent_height > height)
+                    {
+                        coins.push(coin);
+                    }
+                }
+
+                if coins.len() >= limit {
+                    more |= index + 1 < len;
+                    break 'scan;
+                }
+            }
+        }
+
+        let next_cursor = if truncate_to_budget(&mut coins)? {
+            coins.last().map(|coin| coin.coin_id)
+        } else if more && scanned > 0 {
+            cursor.map(|(_, coin_id)| coin_id)
+        } else {
+            None
+        };
+
+        let page = CoinPageResponse {
+            coins,
+            next_cursor,
+            has_more: None,
+        };
+
+        Ok(page.with_links(uri))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+pub struct CoinPageRequest {
+    #[serde(default)]
+    pub limit: Option<u32>,
+    #[serde(default)]
+    pub cursor: Option<Bytes32>,
+    /// Applied after the page is read, like `CoinsRequest::creator_type`.
+    #[serde(default)]
+    pub creator_type: Option<CreatorType>,
+    /// Applied after the page is read, like `CoinsRequest::has_hint`.
+    #[serde(default)]
+    pub has_hint: Option<bool>,
+    #[serde(default)]
+    pub sort: Option<CoinSort>,
+    #[serde(default)]
+    pub order: Option<SortOrder>,
+}
+
+/// With `limit=0`, a page has no coins and only says whether there would be
+/// any, when that can be told without scanning.
+#[derive(Serialize)]
+pub struct CoinPageResponse {
+    pub coins: Vec<Coin>,
+    pub next_cursor: Option<Bytes32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_more: Option<bool>,
+}
+
+impl CoinPageResponse {
+    /// How many coin ids to read for a page, which is one more than nothing
+    /// for `limit=0` so that `new` can tell whether the page would be empty.
+    pub fn fetch_limit(limit: usize) -> usize {
+        limit.max(1)
+    }
+
+    fn has_more(has_more: bool) -> Self {
+        Self {
+            coins: Vec::new(),
+            next_cursor: None,
+            has_more: Some(has_more),
+        }
+    }
+
+    pub fn new(db: &Database, coin_ids: Vec<Bytes32>, limit: usize) -> Result<Self> {
+        if limit == 0 {
+            return Ok(Self::has_more(!coin_ids.is_empty()));
+        }
+
+        let next_cursor = if coin_ids.len() >= limit {
+            coin_ids.last().copied()
+        } else {
+            None
+        };
+
+        let mut coins = Coin::load_many(db, coin_ids)?;
+
+        let next_cursor = if truncate_to_budget(&mut coins)? {
+            coins.last().map(|coin| coin.coin_id)
+        } else {
+            next_cursor
+        };
+
+        Ok(Self {
+            coins,
+            next_cursor,
+            has_more: None,
+        })
+    }
+
+    fn filter(mut self, creator_type: Option<CreatorType>, has_hint: Option<bool>) -> Self {
+        self.coins
+            .retain(|coin| coin.has_creator_type(creator_type) && coin.has_hint(has_hint));
+        self
+    }
+
+    fn sort(mut self, sort: Option<CoinSort>, order: Option<SortOrder>) -> Self {
+        sort_coins(&mut self.coins, sort, order);
+        self
+    }
+
+    fn with_links(self, uri: Uri) -> (LinkHeader, Json<Self>) {
+        (
+            LinkHeader::new(uri).next_cursor(self.next_cursor),
+            Json(self),
+        )
+    }
+}
+
+/// How many index entries a page filtered by `creator_type` or `has_hint`
+/// reads at most, since every entry's coin has to be loaded to check it.
+const MAX_FILTER_SCAN: usize = 10_000;
+
+/// Reads pages of coin ids from the cursor on until `limit` of their coins
+/// match the request's `creator_type` and `has_hint`, or `MAX_FILTER_SCAN`
+/// entries have been read. Telling whether any matching coins follow could
+/// mean scanning that many, so `limit=0` counts every coin.
+fn filtered_coin_page(
+    db: &Database,
+    query: &CoinPageRequest,
+    limit: usize,
+    mut coin_ids: impl FnMut(Option<Bytes32>, usize) -> Result<Vec<Bytes32>>,
+) -> Result<CoinPageResponse> {
+    if (query.creator_type.is_none() && query.has_hint.is_none()) || limit == 0 {
+        let coin_ids = coin_ids(query.cursor, CoinPageResponse::fetch_limit(limit))?;
+        return Ok(CoinPageResponse::new(db, coin_ids, limit)?.sort(query.sort, query.order));
+    }
+
+    let mut coins = Vec::new();
+    let mut cursor = query.cursor;
+    let mut scanned = 0;
+
+    let next_cursor = loop {
+        let page = coin_ids(cursor, limit)?;
+        let exhausted = page.len() < limit;
+
+        for coin_id in page {
+            cursor = Some(coin_id);
+            scanned += 1;
+
+            if let Some(coin) = Coin::load(db, coin_id)? {
+                if coin.has_creator_type(query.creator_type) && coin.has_hint(query.has_hint) {
+                    coins.push(coin);
+                }
+            }
+
+            if coins.len() >= limit {
+                break;
+            }
+        }
+
+        if coins.len() >= limit || scanned >= MAX_FILTER_SCAN {
+            break cursor;
+        }
+
+        if exhausted {
+            break None;
+        }
+    };
+
+    let next_cursor = if truncate_to_budget(&mut coins)? {
+        coins.last().map(|coin| coin.coin_id)
+    } else {
+        next_cursor
+    };
+
+    Ok(CoinPageResponse {
+        coins,
+        next_cursor,
+        has_more: None,
+    }
+    .sort(query.sort, query.order))
+}
+
+/// How many hint index entries a `since_height` page reads at most, since the
+/// coins created below that height are only listed if they were spent at or
+/// above it.
+const MAX_HINT_SCAN: usize = 10_000;
+
+#[derive(Deserialize)]
+pub struct HintCoinsRequest {
+    #[serde(default)]
+    pub limit: Option<u32>,
+    #[serde(default)]
+    pub cursor: Option<Bytes32>,
+    /// Newest coins first.
+    #[serde(default)]
+    pub reverse: bool,
+    /// Only coins created or spent at or above this height, for clients that
+    /// already synced the hint up to it.
+    #[serde(default)]
+    pub since_height: Option<u32>,
+    /// Applied after the page is read, like `CoinsRequest::creator_type`.
+    #[serde(default)]
+    pub creator_type: Option<CreatorType>,
+    #[serde(default)]
+    pub sort: Option<CoinSort>,
+    #[serde(default)]
+    pub order: Option<SortOrder>,
+}
+
+/// The hint's coins ordered by created height, oldest first unless `reverse`
+/// is set, with ties broken by coin id. The cursor is the last coin id of the
+/// previous page, and a cursor that's no longer indexed (after a reorg) ends
+/// the list. `sort` and `order` only reorder each page.
+///
+/// With `since_height`, at most `MAX_HINT_SCAN` entries are read per request,
+/// so a page can be short, and its cursor is the last coin id that was read.
+/// Telling whether any such coins follow could mean scanning that many, so
+/// `limit=0` ignores `since_height`.
+pub async fn coins_by_hint(
+    State(app): State<App>,
+    uri: Uri,
+    Path(hint): Path<Bytes32>,
+    Query(query): Query<HintCoinsRequest>,
+) -> Result<(LinkHeader, Json<CoinPageResponse>), AppError> {
+    check_sort(query.sort, query.order).map_err(AppError::InvalidParam)?;
+
+    app.read(move |app| {
+        app.require_index(IndexKind::Hint)?;
+
+        let limit = app.config.coin_pagination.limit(query.limit) as usize;
+
+        // The index is keyed by created height, which is read from the
+        // cursor's row to seek past it.
+        let mut cursor = match query.cursor {
+            Some(coin_id) => match app.db.coin(coin_id)? {
+                Some(coin) => Some((coin.created_height, coin_id)),
+                None => {
+                    return Ok(CoinPageResponse::new(&app.db, Vec::new(), limit)?.with_links(uri))
+                }
+            },
+            None => None,
+        };
+
+        let since_height = match query.since_height {
+            Some(since_height) if limit > 0 => since_height,
+            _ => {
+                let coin_ids = app
+                    .db
+                    .coins_by_hint(
+                        hint,
+                        cursor,
+                        query.reverse,
+                        CoinPageResponse::fetch_limit(limit),
+                    )?
+                    .into_iter()
+                    .map(|(_, coin_id)| coin_id)
+                    .collect_vec();
+
+                return Ok(CoinPageResponse::new(&app.db, coin_ids, limit)?
+                    .filter(query.creator_type, None)
+                    .sort(query.sort, query.order)
+                    .with_links(uri));
+            }
+        };
+
+        let mut coin_ids = Vec::new();
+        let mut scanned = 0;
+
+        let next_cursor = loop {
+            let page = app.db.coins_by_hint(hint, cursor, query.reverse, limit)?;
+            let exhausted = page.len() < limit;
+
+            for (created_height, coin_id) in page {
+                cursor = Some((created_height, coin_id));
+                scanned += 1;
+
+                let listed = created_height >= since_height
+                    || match app.db.coin(coin_id)? {
+                        Some(coin) => app
+                            .db
+                            .coin_spent_height(coin_id, &coin)?
+                            .is_some_and(|spent_height| spent_height >= since_height),
+                        None => false,
+                    };
+
+                if listed {
+                    coin_ids.push(coin_id);
+                }
+
+                if coin_ids.len() >= limit {
+                    break;
+                }
+            }
+
+            if coin_ids.len() >= limit || scanned >= MAX_HINT_SCAN {
+                break cursor.map(|(_, coin_id)| coin_id);
+            }
+
+            if exhausted {
+                break None;
+            }
+        };
+
+        let mut coins = Coin::load_many(&app.db, coin_ids)?;
+
+        let next_cursor = if truncate_to_budget(&mut coins)? {
+            coins.last().map(|coin| coin.coin_id)
+        } else {
+            next_cursor
+        };
+
+        Ok(CoinPageResponse {
+            coins,
+            next_cursor,
+            has_more: None,
+        }
+        .filter(query.creator_type, None)
+        .sort(query.sort, query.order)
+        .with_links(uri))
+    })
+    .await
+}
+
+const MAX_AMOUNT_SCAN: usize = 10_000;
+
+#[derive(Deserialize)]
+pub struct CoinsByAmountRequest {
+    #[serde(default)]
+    pub limit: Option<u32>,
+    #[serde(default)]
+    pub cursor: Option<Bytes32>,
+    #[serde(default)]
+    pub unspent: bool,
+}
+
+/// Coins of exactly `amount` mojos, ordered by coin id. With `unspent`, spent
+/// coins are skipped while the index is read, and at most `MAX_AMOUNT_SCAN`
+/// entries are read per request. A page that hits that limit can be short,
+/// and its cursor is the last coin id that was read.
+pub async fn coins_by_amount(
+    State(app): State<App>,
+    uri: Uri,
+    Path(amount): Path<u64>,
+    Query(query): Query<CoinsByAmountRequest>,
+) -> Result<(LinkHeader, Json<CoinPageResponse>), AppError> {
+    app.read(move |app| {
+        app.require_index(IndexKind::Amount)?;
+
+        let limit = app.config.coin_pagination.limit(query.limit) as usize;
+
+        // Telling whether any unspent coins follow could mean scanning up to
+        // `MAX_AMOUNT_SCAN` entries, so `limit=0` counts spent ones too.
+        if !query.unspent || limit == 0 {
+            let coin_ids = app.db.coins_by_amount(
+                amount,
+                query.cursor,
+                CoinPageResponse::fetch_limit(limit),
+            )?;
+            return Ok(CoinPageResponse::new(&app.db, coin_ids, limit)?.with_links(uri));
+        }
+
+        let mut coins = Vec::new();
+        let mut cursor = query.cursor;
+        let mut scanned = 0;
+
+        let next_cursor = loop {
+            let coin_ids = app.db.coins_by_amount(amount, cursor, limit)?;
+            let exhausted = coin_ids.len() < limit;
+
+            for coin_id in coin_ids {
+                cursor = Some(coin_id);
+                scanned += 1;
+
+                if let Some(coin) = Coin::load(&app.db, coin_id)? {
+                    if coin.row.spent_height.is_none() {
+                        coins.push(coin);
+                    }
+                }
+
+                if coins.len() >= limit {
+                    break;
+                }
+            }
+
+            if coins.len() >= limit || scanned >= MAX_AMOUNT_SCAN {
+                break cursor;
+            }
+
+            if exhausted {
+                break None;
+            }
+        };
+
+        let next_cursor = if truncate_to_budget(&mut coins)? {
+            coins.last().map(|coin| coin.coin_id)
+        } else {
+            next_cursor
+        };
+
+        Ok(CoinPageResponse {
+            coins,
+            next_cursor,
+            has_more: None,
+        }
+        .with_links(uri))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+pub struct AmountRangeRequest {
+    pub min: u64,
+    pub max: u64,
+    #[serde(default)]
+    pub limit: Option<u32>,
+    #[serde(default)]
+    pub cursor: Option<Bytes>,
+    #[serde(default)]
+    pub unspent: bool,
+}
+
+impl AmountRangeRequest {
+    /// A cursor from outside `min..=max` would otherwise return coins outside
+    /// the requested range.
+    fn check(&self) -> Result<(), AppError> {
+        if self.min > self.max {
+            return Err(AppError::InvalidParam(
+                "`min` must not be greater than `max`".to_string(),
+            ));
+        }
+
+        if let Some(cursor) = &self.cursor {
+            let Some(amount) = cursor
+                .get(..8)
+                .and_then(|amount| amount.try_into().ok())
+                .map(u64::from_be_bytes)
+            else {
+                return Err(AppError::InvalidParam("Invalid `cursor`".to_string()));
+            };
+
+            if !(self.min..=self.max).contains(&amount) {
+                return Err(AppError::InvalidParam(
+                    "`cursor` is outside of `min..=max`".to_string(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+pub struct AmountRangeResponse {
+    pub coins: Vec<Coin>,
+    pub next_cursor: Option<Bytes>,
+    /// Only set for `limit=0`, counting spent coins even with `unspent`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_more: Option<bool>,
+}
+
+pub fn amount_cursor(amount: u64, coin_id: Bytes32) -> Bytes {
+    Bytes::new([&amount.to_be_bytes(), coin_id.as_ref()].concat())
+}
+
+/// Coins with amounts in `min..=max` mojos, read in order from the amount
+/// index, so they're ordered by amount and then coin id. The cursor is the
+/// hex `amount || coin_id` key of the last coin read. With `unspent`, spent
+/// coins are skipped as the index is read, and like `coins_by_amount` at most
+/// `MAX_AMOUNT_SCAN` entries are read per request, so a page can be short
+/// even when more coins follow.
+pub async fn coins_by_amount_range(
+    State(app): State<App>,
+    uri: Uri,
+    Query(query): Query<AmountRangeRequest>,
+) -> Result<(LinkHeader, Json<AmountRangeResponse>), AppError> {
+    query.check()?;
+
+    app.read(move |app| {
+        app.require_index(IndexKind::Amount)?;
+
+        let limit = app.config.coin_pagination.limit(query.limit) as usize;
+
+        if limit == 0 {
+            let next = app.db.coins_by_amount_range(
+                query.min,
+                query.max,
+                query.cursor.as_ref().map(AsRef::as_ref),
+                1,
+            )?;
+
+            return Ok((
+                LinkHeader::new(uri),
+                Json(AmountRangeResponse {
+                    coins: Vec::new(),
+                    next_cursor: None,
+                    has_more: Some(!next.is_empty()),
+                }),
+            ));
+        }
+
+        let mut coins = Vec::new();
+        let mut cursor = query.cursor;
+        let mut scanned = 0;
+
+        let next_cursor = loop {
+            let page = app.db.coins_by_amount_range(
+                query.min,
+                query.max,
+                cursor.as_ref().map(AsRef::as_ref),
+                limit,
+            )?;
+            let exhausted = page.len() < limit;
+
+            for (amount, coin_id) in page {
+                cursor = Some(amount_cursor(amount, coin_id));
+                scanned += 1;
+
+                if let Some(coin) = Coin::load(&app.db, coin_id)? {
+                    if !query.unspent || coin.row.spent_height.is_none() {
+                        coins.push(coin);
+                    }
+                }
+
+                if coins.len() >= limit {
+                    break;
+                }
+            }
+
+            if coins.len() >= limit || scanned >= MAX_AMOUNT_SCAN {
+                break cursor;
+            }
+
+            if exhausted {
+                break None;
+            }
+        };
+
+        let next_cursor = if truncate_to_budget(&mut coins)? {
+            coins
+                .last()
+                .map(|coin| amount_cursor(coin.row.amount, coin.coin_id))
+        } else {
+            next_cursor
+        };
+
+        let links = LinkHeader::new(uri).next_cursor(next_cursor.as_ref());
+
+        Ok((
+            links,
+            Json(AmountRangeResponse {
+                coins,
+                next_cursor,
+                has_more: None,
+            }),
+        ))
+    })
+    .await
+}
+
+const MAX_PUZZLE_HASHES: usize = 100;
+
+#[derive(Deserialize)]
+pub struct CoinsByPuzzleHashesRequest {
+    pub puzzle_hashes: Vec<Bytes32>,
+    #[serde(default)]
+    pub limit: Option<u32>,
+    #[serde(default)]
+    pub cursor: Option<Bytes32>,
+    #[serde(default)]
+    pub creator_type: Option<CreatorType>,
+    #[serde(default)]
+    pub has_hint: Option<bool>,
+    #[serde(default)]
+    pub sort: Option<CoinSort>,
+    #[serde(default)]
+    pub order: Option<SortOrder>,
+}
+
+/// Each coin's `puzzle_hash` identifies which of the requested puzzle hashes
+/// it matched.
+pub async fn coins_by_puzzle_hashes(
+    State(app): State<App>,
+    Json(request): Json<CoinsByPuzzleHashesRequest>,
+) -> Result<Json<CoinPageResponse>, AppError> {
+    check_sort(request.sort, request.order).map_err(AppError::InvalidBody)?;
+
+    app.read(move |app| {
+        app.require_index(IndexKind::PuzzleHash)?;
+
+        if request.puzzle_hashes.len() > MAX_PUZZLE_HASHES {
+            return Err(AppError::InvalidBody(format!(
+                "At most {MAX_PUZZLE_HASHES} puzzle hashes can be requested at once"
+            )));
+        }
+
+        let limit = app.config.coin_pagination.limit(request.limit) as usize;
+
+        let coin_ids = app.db.coins_by_puzzle_hashes(
+            &request.puzzle_hashes,
+            request.cursor,
+            CoinPageResponse::fetch_limit(limit),
+        )?;
+
+        Ok(Json(
+            CoinPageResponse::new(&app.db, coin_ids, limit)?
+                .filter(request.creator_type, request.has_hint)
+                .sort(request.sort, request.order),
+        ))
+    })
+    .await
+}
+
+/// The coins paid out by an offer's settlement, found by the nonce of its
+/// notarized payments. Settlements of XCH, CATs and NFTs are recognized, as
+/// long as they use the settlement payments puzzle offers have used since
+/// Chia 1.6.
+pub async fn coins_by_offer_nonce(
+    State(app): State<App>,
+    uri: Uri,
+    Path(nonce): Path<Bytes32>,
+    Query(query): Query<CoinPageRequest>,
+) -> Result<(LinkHeader, Json<CoinPageResponse>), AppError> {
+    check_sort(query.sort, query.order).map_err(AppError::InvalidParam)?;
+
+    app.read(move |app| {
+        let limit = app.config.coin_pagination.limit(query.limit) as usize;
+
+        let coin_ids = app.db.coins_by_offer_nonce(
+            nonce,
+            query.cursor,
+            CoinPageResponse::fetch_limit(limit),
+        )?;
+
+        Ok(CoinPageResponse::new(&app.db, coin_ids, limit)?
+            .filter(query.creator_type, query.has_hint)
+            .sort(query.sort, query.order)
+            .with_links(uri))
+    })
+    .await
+}
+
+#[derive(Serialize)]
+pub struct CoinResponse {
+    pub coin: Coin,
+    pub puzzle_reveal: Option<Bytes>,
+    pub solution: Option<Bytes>,
+    pub puzzle_reveal_size: Option<usize>,
+    pub solution_size: Option<usize>,
+    /// Only set for spent coins, with `?uncurry=true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uncurried: Option<Uncurried>,
+    /// The parent spend's CLVM cost divided evenly between the coins it
+    /// created, with `?cost_attribution=true`. It's `None` when the parent
+    /// spend isn't indexed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_attribution: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct CoinRequest {
+    #[serde(default)]
+    pub omit_blobs: bool,
+    #[serde(default)]
+    pub include_spend: Option<bool>,
+    /// Parses the puzzle reveal, which is skipped by default for its cost.
+    #[serde(default)]
+    pub uncurry: bool,
+    /// Runs the parent spend to attribute part of its cost to this coin.
+    #[serde(default)]
+    pub cost_attribution: bool,
+}
+
+impl CoinRequest {
+    /// `omit_blobs` predates `include_spend`, and asking for both is ambiguous.
+    fn check(&self) -> Result<(), AppError> {
+        if self.omit_blobs && self.include_spend == Some(true) {
+            return Err(AppError::InvalidParam(
+                "`omit_blobs` can't be combined with `include_spend=true`".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+pub async fn coin_by_id(
+    State(app): State<App>,
+    prefer: Prefer,
+    Path(CoinId(coin_id)): Path<CoinId>,
+    Query(query): Query<CoinRequest>,
+) -> Result<(Prefer, Json<CoinResponse>), AppError> {
+    query.check()?;
+
+    let fetched = app
+        .flights
+        .coins
+        .run(coin_id, || {
+            app.blocking(move |app| {
+                let Some(coin) = app.db.coin(coin_id)? else {
+                    return Err(AppError::NotFound(format!("Coin {coin_id} not found")));
+                };
+
+                Ok((coin, app.db.coin_spend(coin_id)?))
+            })
+        })
+        .await?;
+
+    // Running the parent's spend is heavier than the lookups, so it holds a
+    // read permit like the range scans do.
+    let cost_attribution = if query.cost_attribution {
+        let parent_coin_id = fetched.0.parent_coin_id;
+
+        app.read(move |app| {
+            let Some(parent_spend) = app.db.coin_spend(parent_coin_id)? else {
+                return Ok(None);
+            };
+
+            let simulation = simulate(
+                &parent_spend.puzzle_reveal,
+                &parent_spend.solution,
+                MAX_REPLAY_COST,
+            )
+            .map_err(|error| AppError::replay(parent_coin_id, error))?;
+
+            Ok(simulation.cost_per_created_coin())
+        })
+        .await?
+    } else {
+        None
+    };
+
+    app.blocking(move |app| {
+        let (coin, spend) = (*fetched).clone();
+
+        let spent_height = spend.as_ref().map(|spend| spend.spent_height);
+        let puzzle_reveal_size = spend.as_ref().map(|spend| spend.puzzle_reveal.len());
+        let solution_size = spend.as_ref().map(|spend| spend.solution.len());
+
+        let include_spend = query
+            .include_spend
+            .unwrap_or(app.config.include_spend && !prefer.minimal)
+            && !query.omit_blobs;
+
+        let uncurried = match &spend {
+            Some(spend) if query.uncurry => Some(uncurry(&spend.puzzle_reveal)?),
+            _ => None,
+        };
+
+        let (puzzle_reveal, solution) = match spend {
+            Some(spend) if include_spend => (Some(spend.puzzle_reveal), Some(spend.solution)),
+            _ => (None, None),
+        };
+
+        Ok((
+            prefer,
+            Json(CoinResponse {
+                coin: Coin::new(&app.db, coin_id, coin, spent_height)?,
+                puzzle_reveal,
+                solution,
+                puzzle_reveal_size,
+                solution_size,
+                uncurried,
+                cost_attribution,
+            }),
+        ))
+    })
+    .await
+}
+
+#[derive(Serialize)]
+pub struct PuzzleHashResponse {
+    pub puzzle_hash: Bytes32,
+}
+
+pub async fn coin_puzzle_hash(
+    State(app): State<App>,
+    Path(CoinId(coin_id)): Path<CoinId>,
+) -> Result<Json<PuzzleHashResponse>, AppError> {
+    app.blocking(move |app| {
+        let Some(coin) = app.db.coin(coin_id)? else {
+            return Err(AppError::NotFound(format!("Coin {coin_id} not found")));
+        };
+
+        Ok(Json(PuzzleHashResponse {
+            puzzle_hash: coin.puzzle_hash,
+        }))
+    })
+    .await
+}
+
+fn default_min_confirmations() -> u32 {
+    1
+}
+
+#[derive(Deserialize)]
+pub struct SpendableRequest {
+    #[serde(default = "default_min_confirmations")]
+    pub min_confirmations: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpendableReason {
+    Ok,
+    AlreadySpent,
+    InsufficientConfirmations,
+}
+
+#[derive(Serialize)]
+pub struct SpendableResponse {
+    pub spendable: bool,
+    pub reason: SpendableReason,
+    /// The number of blocks from the one that created the coin up to the
+    /// peak, counting both.
+    pub confirmations: u32,
+}
+
+/// Whether the coin is unspent and buried under at least `min_confirmations`
+/// blocks, by the index's view of the chain.
+pub async fn coin_spendable(
+    State(app): State<App>,
+    Path(CoinId(coin_id)): Path<CoinId>,
+    Query(query): Query<SpendableRequest>,
+) -> Result<Json<SpendableResponse>, AppError> {
+    app.blocking(move |app| {
+        let Some(coin) = app.db.coin(coin_id)? else {
+            return Err(AppError::NotFound(format!("Coin {coin_id} not found")));
+        };
+
+        let peak_height = app.db.peak_height()?.unwrap_or(coin.created_height);
+        let confirmations = (peak_height + 1).saturating_sub(coin.created_height);
+
+        let reason = if app.db.coin_spend(coin_id)?.is_some() {
+            SpendableReason::AlreadySpent
+        } else if confirmations < query.min_confirmations {
+            SpendableReason::InsufficientConfirmations
+        } else {
+            SpendableReason::Ok
+        };
+
+        Ok(Json(SpendableResponse {
+            spendable: reason == SpendableReason::Ok,
+            reason,
+            confirmations,
+        }))
+    })
+    .await
+}
+
+#[derive(Serialize)]
+pub struct ConfirmationStatusResponse {
+    /// Whether the coin is in an indexed block. Coins that aren't, such as ones
+    /// still in the mempool, have every other field `false` or `0`.
+    pub confirmed: bool,
+    /// Whether `confirmations` is greater than `reorg_buffer`, which puts the
+    /// block that created the coin at least `reorg_buffer` blocks below the
+    /// peak, the same rule as `/blocks/latest?finalized=true`.
+    pub finalized: bool,
+    /// Counted like `SpendableResponse::confirmations`, so a coin created in
+    /// the peak block has one.
+    pub confirmations: u32,
+    pub spent: bool,
+}
+
+/// Whether the coin can be credited, by the index's view of the chain. A coin
+/// created at the peak is confirmed but not finalized, and a coin the index
+/// hasn't seen isn't an error since it may just not be confirmed yet.
+pub async fn coin_confirmation_status(
+    State(app): State<App>,
+    Path(CoinId(coin_id)): Path<CoinId>,
+) -> Result<Json<ConfirmationStatusResponse>, AppError> {
+    app.blocking(move |app| {
+        let Some(coin) = app.db.coin(coin_id)? else {
+            return Ok(Json(ConfirmationStatusResponse {
+                confirmed: false,
+                finalized: false,
+                confirmations: 0,
+                spent: false,
+            }));
+        };
+
+        let peak_height = app.db.peak_height()?.unwrap_or(coin.created_height);
+        let depth = peak_height.saturating_sub(coin.created_height);
+
+        Ok(Json(ConfirmationStatusResponse {
+            confirmed: true,
+            finalized: depth >= app.config.reorg_buffer,
+            confirmations: depth + 1,
+            spent: app.db.coin_spent_height(coin_id, &coin)?.is_some(),
+        }))
+    })
+    .await
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RawEncoding {
+    #[default]
+    Binary,
+    Hex,
+}
+
+#[derive(Deserialize)]
+pub struct RawCoinRequest {
+    #[serde(default)]
+    pub encoding: RawEncoding,
+}
+
+/// The coin in Chia's streamable serialization (parent coin info, puzzle hash
+/// and a big-endian `u64` amount), as raw bytes or as hex text.
+pub async fn coin_raw(
+    State(app): State<App>,
+    Path(CoinId(coin_id)): Path<CoinId>,
+    Query(query): Query<RawCoinRequest>,
+) -> Result<Response, AppError> {
+    app.blocking(move |app| {
+        let Some(coin) = app.db.coin(coin_id)? else {
+            return Err(AppError::NotFound(format!("Coin {coin_id} not found")));
+        };
+
+        let bytes = protocol::Coin::new(coin.parent_coin_id, coin.puzzle_hash, coin.amount)
+            .to_bytes()
+            .map_err(anyhow::Error::from)?;
+
+        Ok(match query.encoding {
+            RawEncoding::Binary => {
+                ([(CONTENT_TYPE, "application/octet-stream")], bytes).into_response()
+            }
+            RawEncoding::Hex => {
+                let prefix = if FORMAT.try_with(|format| format.hex_prefix).unwrap_or(true) {
+                    "0x"
+                } else {
+                    ""
+                };
+
+                (
+                    [(CONTENT_TYPE, "text/plain")],
+                    format!("{prefix}{}", hex::encode(bytes)),
+                )
+                    .into_response()
+            }
+        })
+    })
+    .await
+}
+
+/// A coin in the full node RPC's shape.
+#[derive(Serialize)]
+pub struct ChiaCoin {
+    pub parent_coin_info: Bytes32,
+    pub puzzle_hash: Bytes32,
+    pub amount: u64,
+}
+
+/// The shape of a `CoinRecord` in the Chia full node RPC, so that tools
+/// written against it can read coins from here as well.
+#[derive(Serialize)]
+pub struct CoinRecord {
+    pub coin: ChiaCoin,
+    /// The coin's `created_height`.
+    pub confirmed_block_index: u32,
+    /// The coin's `spent_height`, or `0` while it's unspent.
+    pub spent_block_index: u32,
+    pub spent: bool,
+    /// Whether the coin is a farmer or pool reward.
+    pub coinbase: bool,
+    /// The timestamp of the block the coin was created in.
+    pub timestamp: u64,
+}
+
+pub async fn coin_record(
+    State(app): State<App>,
+    Path(CoinId(coin_id)): Path<CoinId>,
+) -> Result<Json<CoinRecord>, AppError> {
+    app.blocking(move |app| {
+        let Some(coin) = Coin::load(&app.db, coin_id)? else {
+            return Err(AppError::NotFound(format!("Coin {coin_id} not found")));
+        };
+
+        Ok(Json(CoinRecord {
+            coin: ChiaCoin::from(&coin.row),
+            confirmed_block_index: coin.row.created_height,
+            spent_block_index: coin.row.spent_height.unwrap_or(0),
+            spent: coin.row.spent_height.is_some(),
+            coinbase: coin.row.kind == CoinType::Reward,
+            timestamp: coin.created_timestamp.unwrap_or(0),
+        }))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+pub struct ComputeCoinIdRequest {
+    pub parent_coin_info: Bytes32,
+    pub puzzle_hash: Bytes32,
+    pub amount: u64,
+}
+
+#[derive(Serialize)]
+pub struct ComputeCoinIdResponse {
+    pub coin_id: Bytes32,
+    pub exists: bool,
+}
+
+pub async fn compute_coin_id(
+    State(app): State<App>,
+    Json(request): Json<ComputeCoinIdRequest>,
+) -> Result<Json<ComputeCoinIdResponse>, AppError> {
+    app.blocking(move |app| {
+        let coin_id = protocol::Coin::new(
+            request.parent_coin_info,
+            request.puzzle_hash,
+            request.amount,
+        )
+        .coin_id();
+
+        Ok(Json(ComputeCoinIdResponse {
+            coin_id,
+            exists: app.db.coin(coin_id)?.is_some(),
+        }))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+pub struct RecentCoinsRequest {
+    #[serde(default)]
+    pub limit: Option<u32>,
+    #[serde(default)]
+    pub cursor: Option<Bytes>,
+    /// Applied after the page is read, like `CoinsRequest::creator_type`.
+    #[serde(default)]
+    pub creator_type: Option<CreatorType>,
+    /// Applied after the page is read, like `CoinsRequest::has_hint`.
+    #[serde(default)]
+    pub has_hint: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct RecentCoinsResponse {
+    pub coins: Vec<Coin>,
+    pub next_cursor: Option<Bytes>,
+}
+
+pub async fn recent_coins(
+    State(app): State<App>,
+    uri: Uri,
+    Query(query): Query<RecentCoinsRequest>,
+) -> Result<(LinkHeader, Json<RecentCoinsResponse>), AppError> {
+    app.read(move |app| {
+        let limit = app.config.coin_pagination.limit(query.limit) as usize;
+
+        let spent = app
+            .db
+            .recently_spent(query.cursor.as_ref().map(AsRef::as_ref), limit)?;
+
+        let next_cursor = if spent.len() >= limit {
+            spent.last().map(|(spent_height, coin_id)| {
+                Bytes::new([&spent_height.to_be_bytes(), coin_id.as_ref()].concat())
+            })
+        } else {
+            None
+        };
+
+        let mut coins = Coin::load_many(&app.db, spent.into_iter().map(|(_, coin_id)| coin_id))?;
+        coins.retain(|coin| {
+            coin.has_creator_type(query.creator_type) && coin.has_hint(query.has_hint)
+        });
+
+        Ok((
+            LinkHeader::new(uri).next_cursor(next_cursor.as_ref()),
+            Json(RecentCoinsResponse { coins, next_cursor }),
+        ))
+    })
+    .await
+}
+
+const MAX_DIFF_SPAN: u32 = 1000;
+
+#[derive(Deserialize)]
+pub struct CoinDiffRequest {
+    pub from: u32,
+    pub to: u32,
+}
+
+#[derive(Serialize)]
+pub struct CoinDiffResponse {
+    pub created: Vec<Bytes32>,
+    pub spent: Vec<Bytes32>,
+}
+
+/// Coins created and spent at heights `from..=to`.
+pub async fn coin_diff(
+    State(app): State<App>,
+    Query(query): Query<CoinDiffRequest>,
+) -> Result<Json<CoinDiffResponse>, AppError> {
+    app.read(move |app| {
+        if query.from > query.to {
+            return Err(AppError::InvalidParam(
+                "`from` must not be greater than `to`".to_string(),
+            ));
+        }
+
+        if query.to - query.from >= MAX_DIFF_SPAN {
+            return Err(AppError::InvalidParam(format!(
+                "At most {MAX_DIFF_SPAN} heights can be diffed at once"
+            )));
+        }
+
+        let created = app.db.coins_created_between(query.from, query.to)?;
+        let spent = app.db.coins_spent_between(query.from, query.to)?;
+
+        Ok(Json(CoinDiffResponse {
+            created: created
+                .into_iter()
+                .map(|(_, coin_id)| coin_id)
+                .collect_vec(),
+            spent: spent.into_iter().map(|(_, coin_id)| coin_id).collect_vec(),
+        }))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+pub struct CoinStatusesRequest {
+    pub coin_ids: Vec<Bytes32>,
+}
+
+#[derive(Serialize)]
+pub struct CoinStatus {
+    pub coin_id: Bytes32,
+    pub exists: bool,
+    pub spent: bool,
+    pub created_height: Option<u32>,
+    pub spent_height: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct CoinStatusesResponse {
+    pub coins: Vec<CoinStatus>,
+}
+
+pub async fn coin_statuses(
+    State(app): State<App>,
+    Json(request): Json<CoinStatusesRequest>,
+) -> Result<Json<CoinStatusesResponse>, AppError> {
+    app.read(move |app| {
+        let max = app.config.coin_pagination.max;
+
+        if request.coin_ids.len() > max as usize {
+            return Err(AppError::InvalidBody(format!(
+                "At most {max} coin ids can be requested at once"
+            )));
+        }
+
+        let coins = app.db.coins(&request.coin_ids)?;
+        let spent_heights = app.db.coin_spent_heights(&request.coin_ids, &coins)?;
+
+        Ok(Json(CoinStatusesResponse {
+            coins: request
+                .coin_ids
+                .into_iter()
+                .zip(coins.into_iter().zip(spent_heights))
+                .map(|(coin_id, (coin, spent_height))| CoinStatus {
+                    coin_id,
+                    exists: coin.is_some(),
+                    spent: spent_height.is_some(),
+                    created_height: coin.map(|coin| coin.created_height),
+                    spent_height,
+                })
+                .collect_vec(),
+        }))
+    })
+    .await
+}
+
+#[derive(Serialize)]
+pub struct CreatorSpendResponse {
+    pub parent_coin: Coin,
+    pub puzzle_reveal: Bytes,
+    pub solution: Bytes,
+}
+
+pub async fn creator_spend(
+    State(app): State<App>,
+    Path(CoinId(coin_id)): Path<CoinId>,
+) -> Result<Json<CreatorSpendResponse>, AppError> {
+    app.blocking(move |app| {
+        let Some(coin) = app.db.coin(coin_id)? else {
+            return Err(AppError::NotFound(format!("Coin {coin_id} not found")));
+        };
+
+        let parent_coin_id = coin.parent_coin_id;
+
+        let Some(parent) = app.db.coin(parent_coin_id)? else {
+            return Err(AppError::NotFound(format!(
+                "Parent coin {parent_coin_id} not found"
+            )));
+        };
+
+        let Some(spend) = app.db.coin_spend(parent_coin_id)? else {
+            return Err(AppError::NotFound(format!(
+                "Parent coin {parent_coin_id} has not been spent"
+            )));
+        };
+
+        Ok(Json(CreatorSpendResponse {
+            parent_coin: Coin::new(&app.db, parent_coin_id, parent, Some(spend.spent_height))?,
+            puzzle_reveal: spend.puzzle_reveal,
+            solution: spend.solution,
+        }))
+    })
+    .await
+}
+
+pub async fn coin_spent_in(
+    State(app): State<App>,
+    Path(CoinId(coin_id)): Path<CoinId>,
+) -> Result<Json<BlockResponse>, AppError> {
+    app.blocking(move |app| {
+        let Some(spend) = app.db.coin_spend(coin_id)? else {
+            return Err(AppError::NotFound(if app.db.coin(coin_id)?.is_some() {
+                format!("Coin {coin_id} has not been spent")
+            } else {
+                format!("Coin {coin_id} not found")
+            }));
+        };
+
+        let height = spend.spent_height;
+
+        let Some(block) = app.db.block(height)? else {
+            return Err(AppError::NotFound(format!("Block {height} not found")));
+        };
+
+        Ok(Json(BlockResponse {
+            block: Block { height, row: block },
+        }))
+    })
+    .await
+}
+
+#[derive(Serialize)]
+pub struct CreatedInResponse {
+    pub block: Block,
+    /// The coin's index among the coins created at the height, in the order
+    /// the block created them, or `None` for coins indexed before that was
+    /// recorded.
+    pub position: Option<u32>,
+    pub created_coins: usize,
+}
+
+pub async fn coin_created_in(
+    State(app): State<App>,
+    Path(CoinId(coin_id)): Path<CoinId>,
+) -> Result<Json<CreatedInResponse>, AppError> {
+    app.read(move |app| {
+        let Some(coin) = app.db.coin(coin_id)? else {
+            return Err(AppError::NotFound(format!("Coin {coin_id} not found")));
+        };
+
+        let height = coin.created_height;
+
+        let Some(block) = app.db.block(height)? else {
+            return Err(AppError::NotFound(format!("Block {height} not found")));
+        };
+
+        Ok(Json(CreatedInResponse {
+            block: Block { height, row: block },
+            position: coin.created_index,
+            created_coins: app.db.count_coins_by_created_height(height)?,
+        }))
+    })
+    .await
+}
+
+/// Each part of the timeline is included unless its flag is set to `false`,
+/// except that `Prefer: return=minimal` leaves out the children and spends
+/// unless they're asked for.
+#[derive(Deserialize)]
+pub struct TimelineRequest {
+    #[serde(default)]
+    pub parent: Option<bool>,
+    #[serde(default)]
+    pub creator_spend: Option<bool>,
+    #[serde(default)]
+    pub children: Option<bool>,
+    #[serde(default)]
+    pub spend: Option<bool>,
+    #[serde(default)]
+    pub max_nodes: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct TimelineResponse {
+    pub coin: Coin,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<Coin>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub creator_spend: Option<SpendRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub children: Option<Vec<Coin>>,
+    /// Whether children were left out to stay within the node budget.
+    pub truncated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spend: Option<SpendRecord>,
+}
+
+/// The coin along with its parent, the spend that created it, its children,
+/// and its own spend, for rendering a coin in a single request. Children are
+/// capped at the node budget.
+pub async fn coin_timeline(
+    State(app): State<App>,
+    prefer: Prefer,
+    Path(CoinId(coin_id)): Path<CoinId>,
+    Query(query): Query<TimelineRequest>,
+) -> Result<(Prefer, Json<TimelineResponse>), AppError> {
+    app.read(move |app| {
+        let full = !prefer.minimal;
+        let include_children = query.children.unwrap_or(full);
+
+        if include_children {
+            app.require_index(IndexKind::ParentCoinId)?;
+        }
+
+        let Some(coin) = Coin::load(&app.db, coin_id)? else {
+            return Err(AppError::NotFound(format!("Coin {coin_id} not found")));
+        };
+
+        let parent_coin_id = coin.row.parent_coin_id;
+
+        let parent = if query.parent.unwrap_or(true) {
+            Coin::load(&app.db, parent_coin_id)?
+        } else {
+            None
+        };
+
+        let creator_spend = if query.creator_spend.unwrap_or(full) {
+            app.db
+                .coin_spend(parent_coin_id)?
+                .map(|spend| SpendRecord::new(parent_coin_id, spend))
+        } else {
+            None
+        };
+
+        let mut truncated = false;
+
+        let children = if include_children {
+            let mut coin_ids = app.db.coins_by_parent_coin_id(coin_id)?;
+            let limit = app.node_budget(query.max_nodes) as usize;
+
+            if coin_ids.len() > limit {
+                coin_ids.truncate(limit);
+                truncated = true;
+            }
+
+            Some(Coin::load_many(&app.db, coin_ids)?)
+        } else {
+            None
+        };
+
+        let spend = if query.spend.unwrap_or(full) {
+            app.db
+                .coin_spend(coin_id)?
+                .map(|spend| SpendRecord::new(coin_id, spend))
+        } else {
+            None
+        };
+
+        Ok((
+            prefer,
+            Json(TimelineResponse {
+                coin,
+                parent,
+                creator_spend,
+                children,
+                truncated,
+                spend,
+            }),
+        ))
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use crate::db::{insert_test_chain, test_coin, test_hash, Database, LineageProof};
+    use crate::routes::{test_get, test_hex, test_router};
+
+    use super::*;
+
+    /// A CAT coin with the hint, as a wallet would receive one.
+    fn hinted_cat_coin(hint: Bytes32, index: u32, height: u32) -> (Bytes32, CoinRow) {
+        let (coin_id, mut row) =
+            test_coin(test_hash(100, index), test_hash(200, index), 10, height);
+
+        row.hint = Some(hint);
+        row.kind = CoinType::Cat {
+            asset_id: test_hash(300, 0),
+            inner_puzzle_hash: hint,
+            lineage_proof: LineageProof {
+                parent_parent_coin_id: test_hash(400, index),
+                parent_inner_puzzle_hash: Some(hint),
+                parent_amount: 10,
+            },
+        };
+
+        (coin_id, row)
+    }
+
+    #[tokio::test]
+    async fn hinted_cat_coins_are_paged_by_hint() {
+        let db = Database::open_temp().unwrap();
+        insert_test_chain(&db, 3, 1, Default::default()).unwrap();
+
+        let hint = test_hash(500, 0);
+        let first = hinted_cat_coin(hint, 1, 1);
+        let second = hinted_cat_coin(hint, 2, 2);
+        let other = hinted_cat_coin(test_hash(500, 1), 3, 1);
+
+        let mut tx = db.transaction();
+        for (coin_id, row) in [&second, &first, &other] {
+            tx.put_coin(*coin_id, row).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let router = test_router(&db);
+        let uri = format!("/coins/hint/{}?limit=1", hex::encode(hint));
+
+        let response = test_get(&router, &uri).await;
+        assert_eq!(response.status, StatusCode::OK);
+
+        let coins = response.body["coins"].as_array().unwrap();
+        assert_eq!(coins.len(), 1);
+        assert_eq!(test_hex(&coins[0]["coin_id"]), hex::encode(first.0));
+        assert_eq!(test_hex(&coins[0]["hint"]), hex::encode(hint));
+        assert_eq!(coins[0]["type"], "cat");
+
+        let cursor = test_hex(&response.body["next_cursor"]).to_string();
+        let response = test_get(&router, &format!("{uri}&cursor={cursor}")).await;
+
+        let coins = response.body["coins"].as_array().unwrap();
+        assert_eq!(coins.len(), 1);
+        assert_eq!(test_hex(&coins[0]["coin_id"]), hex::encode(second.0));
+
+        let cursor = test_hex(&response.body["next_cursor"]).to_string();
+        let response = test_get(&router, &format!("{uri}&cursor={cursor}")).await;
+
+        assert_eq!(response.body["coins"], serde_json::json!([]));
+        assert!(response.body["next_cursor"].is_null());
+    }
+
+    #[tokio::test]
+    async fn children_are_filtered_before_the_page_is_cut() {
+        let db = Database::open_temp().unwrap();
+        insert_test_chain(&db, 2, 0, Default::default()).unwrap();
+
+        let parent_coin_id = test_hash(700, 0);
+        let mut children = (0..5)
+            .map(|index| test_coin(parent_coin_id, test_hash(800, index), 10, 1))
+            .collect_vec();
+        children.sort_by_key(|(coin_id, _)| *coin_id);
+
+        for (_, row) in children.iter_mut().skip(1).step_by(2) {
+            row.hint = Some(test_hash(900, 0));
+        }
+
+        let mut tx = db.transaction();
+        for (coin_id, row) in &children {
+            tx.put_coin(*coin_id, row).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let router = test_router(&db);
+
+        for (query, expected) in [
+            ("", children.iter().collect_vec()),
+            (
+                "&has_hint=true",
+                children.iter().skip(1).step_by(2).collect_vec(),
+            ),
+        ] {
+            let uri = format!(
+                "/coins/children/{}?limit=1{query}",
+                hex::encode(parent_coin_id)
+            );
+
+            let mut coin_ids = Vec::new();
+            let mut response = test_get(&router, &uri).await;
+
+            loop {
+                assert_eq!(response.status, StatusCode::OK);
+
+                let coins = response.body["coins"].as_array().unwrap();
+                assert!(coins.len() <= 1);
+
+                for coin in coins {
+                    coin_ids.push(test_hex(&coin["coin_id"]).to_string());
+                }
+
+                if response.body["next_cursor"].is_null() {
+                    break;
+                }
+
+                let cursor = test_hex(&response.body["next_cursor"]).to_string();
+                response = test_get(&router, &format!("{uri}&cursor={cursor}")).await;
+            }
+
+            let expected = expected
+                .into_iter()
+                .map(|(coin_id, _)| hex::encode(coin_id))
+                .collect_vec();
+            assert_eq!(coin_ids, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn coins_created_at_a_height_are_paged() {
+        let db = Database::open_temp().unwrap();
+        insert_test_chain(&db, 3, 0, Default::default()).unwrap();
+
+        let puzzle_hash = test_hash(600, 1);
+        let mut coins = (0..3)
+            .map(|index| test_coin(test_hash(100, index), puzzle_hash, 10, 1))
+            .collect_vec();
+        coins.sort_by_key(|(coin_id, _)| *coin_id);
+        let later = test_coin(test_hash(100, 3), puzzle_hash, 10, 2);
+
+        let mut tx = db.transaction();
+        for (coin_id, row) in coins.iter().chain([&later]) {
+            tx.put_coin(*coin_id, row).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let router = test_router(&db);
+        let uri = format!(
+            "/coins/puzzle/{}/created/height/1?limit=2",
+            hex::encode(puzzle_hash)
+        );
+
+        let response = test_get(&router, &uri).await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body["coins"].as_array().unwrap().len(), 2);
+
+        let cursor = test_hex(&response.body["next_cursor"]).to_string();
+        assert_eq!(cursor, hex::encode(coins[1].0));
+
+        let response = test_get(&router, &format!("{uri}&cursor={cursor}")).await;
+
+        let page = response.body["coins"].as_array().unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(test_hex(&page[0]["coin_id"]), hex::encode(coins[2].0));
+        assert!(response.body["next_cursor"].is_null());
+    }
+
+    #[tokio::test]
+    async fn coin_history_pages_seek_from_the_cursor() {
+        let db = Database::open_temp().unwrap();
+        insert_test_chain(&db, 4, 0, Default::default()).unwrap();
+
+        let puzzle_hash = test_hash(600, 0);
+        let mut coins = (0..4)
+            .map(|index| test_coin(test_hash(100, index), puzzle_hash, 10, 1))
+            .collect_vec();
+        coins[1].1.spent_height = Some(2);
+        coins[2].1.spent_height = Some(3);
+        coins[3].1.spent_height = Some(3);
+
+        let mut tx = db.transaction();
+        for (coin_id, row) in &coins {
+            tx.put_coin(*coin_id, row).unwrap();
+
+            if let Some(spent_height) = row.spent_height {
+                tx.add_to_spent_height_index(spent_height, *coin_id)
+                    .unwrap();
+            }
+        }
+        tx.commit().unwrap();
+
+        let mut newest = [coins[2].0, coins[3].0];
+        newest.sort();
+        let expected = [coins[0].0, newest[0], newest[1], coins[1].0];
+
+        let router = test_router(&db);
+
+        for (query, expected) in [
+            ("", &expected[..]),
+            ("&include_unspent=false", &expected[1..]),
+        ] {
+            let uri = format!(
+                "/coins/puzzle/{}/history?limit=1{query}",
+                hex::encode(puzzle_hash)
+            );
+
+            let mut coin_ids = Vec::new();
+            let mut response = test_get(&router, &uri).await;
+
+            loop {
+                assert_eq!(response.status, StatusCode::OK);
+
+                for coin in response.body["coins"].as_array().unwrap() {
+                    coin_ids.push(test_hex(&coin["coin_id"]).to_string());
+                }
+
+                if response.body["next_cursor"].is_null() {
+                    break;
+                }
+
+                let cursor = test_hex(&response.body["next_cursor"]).to_string();
+                response = test_get(&router, &format!("{uri}&cursor={cursor}")).await;
+            }
+
+            assert_eq!(coin_ids, expected.iter().map(hex::encode).collect_vec());
+        }
+    }
+
+    #[tokio::test]
+    async fn created_in_reports_the_recorded_position() {
+        let db = Database::open_temp().unwrap();
+        insert_test_chain(&db, 2, 0, Default::default()).unwrap();
+
+        let (coin_id, mut row) = test_coin(test_hash(100, 0), test_hash(200, 0), 10, 1);
+        let (old_id, old) = test_coin(test_hash(100, 1), test_hash(200, 1), 10, 1);
+        row.created_index = Some(3);
+
+        let mut tx = db.transaction();
+        tx.put_coin(coin_id, &row).unwrap();
+        tx.put_coin(old_id, &old).unwrap();
+        tx.commit().unwrap();
+
+        let router = test_router(&db);
+
+        let response = test_get(
+            &router,
+            &format!("/coins/id/{}/created-in", hex::encode(coin_id)),
+        )
+        .await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body["position"], 3);
+        assert_eq!(response.body["created_coins"], 2);
+
+        let response = test_get(
+            &router,
+            &format!("/coins/id/{}/created-in", hex::encode(old_id)),
+        )
+        .await;
+        assert!(response.body["position"].is_null());
+    }
+
+    #[tokio::test]
+    async fn hint_coins_since_a_height_include_older_coins_spent_after_it() {
+        let db = Database::open_temp().unwrap();
+        insert_test_chain(&db, 3, 1, Default::default()).unwrap();
+
+        let hint = test_hash(500, 0);
+        let (spent_id, mut spent) = hinted_cat_coin(hint, 1, 1);
+        let (unspent_id, unspent) = hinted_cat_coin(hint, 2, 1);
+        let (newer_id, newer) = hinted_cat_coin(hint, 3, 2);
+        spent.spent_height = Some(2);
+
+        let mut tx = db.transaction();
+        tx.put_coin(spent_id, &spent).unwrap();
+        tx.put_coin(unspent_id, &unspent).unwrap();
+        tx.put_coin(newer_id, &newer).unwrap();
+        tx.commit().unwrap();
+
+        let router = test_router(&db);
+        let coin_ids = |body: &serde_json::Value| {
+            body["coins"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|coin| test_hex(&coin["coin_id"]).to_string())
+                .collect_vec()
+        };
+
+        let uri = format!("/coins/hint/{}?since_height=2", hex::encode(hint));
+        let response = test_get(&router, &uri).await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(
+            coin_ids(&response.body),
+            [hex::encode(spent_id), hex::encode(newer_id)]
+        );
+
+        let response = test_get(&router, &format!("{uri}&reverse=true")).await;
+        assert_eq!(
+            coin_ids(&response.body),
+            [hex::encode(newer_id), hex::encode(spent_id)]
+        );
+
+        let response = test_get(&router, &format!("{uri}&order=desc")).await;
+        assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn missing_timestamps_are_null_and_unasked_fields_are_left_out() {
+        let (coin_id, row) = test_coin(Bytes32::default(), Bytes32::default(), 1, 0);
+
+        let coin = Coin {
+            coin_id,
+            row,
+            address: None,
+            amount_xch: None,
+            created_timestamp: None,
+            spent_timestamp: None,
+            is_singleton_tip: None,
+            relation: None,
+        };
+
+        let value = serde_json::to_value(&coin).unwrap();
+        let object = value.as_object().unwrap();
+
+        assert_eq!(
+            object.get("created_timestamp"),
+            Some(&serde_json::Value::Null)
+        );
+        assert_eq!(
+            object.get("spent_timestamp"),
+            Some(&serde_json::Value::Null)
+        );
+        assert_eq!(object.get("spent_height"), Some(&serde_json::Value::Null));
+
+        for key in ["address", "amount_xch", "is_singleton_tip", "relation"] {
+            assert!(!object.contains_key(key), "`{key}` should be left out");
+        }
+    }
+}
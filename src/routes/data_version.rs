@@ -0,0 +1,22 @@
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::db::DATA_VERSION;
+
+const DATA_VERSION_HEADER: HeaderName = HeaderName::from_static("x-data-version");
+
+/// Adds the data version to every response, so clients can tell which optional
+/// fields and endpoints the server supports.
+pub async fn data_version_header(request: Request, next: Next) -> Response {
+    let mut response = next.run(request).await;
+
+    response
+        .headers_mut()
+        .insert(DATA_VERSION_HEADER, HeaderValue::from(DATA_VERSION));
+
+    response
+}
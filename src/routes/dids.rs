@@ -0,0 +1,63 @@
+use axum::extract::State;
+use chia::protocol::Bytes32;
+use serde::{Deserialize, Serialize};
+
+use crate::clvm::{did_recovery as parse_did_recovery, puzzle_hash, DidRecovery};
+
+use super::{singleton_tip, App, AppError, DidId, Json, Path, Query};
+
+#[derive(Deserialize)]
+pub struct DidRecoveryRequest {
+    #[serde(default)]
+    pub max_nodes: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct DidRecoveryResponse {
+    pub did_id: Bytes32,
+    /// The DID's current coin, which hasn't been spent.
+    pub coin_id: Bytes32,
+    /// The newest spend of the DID, whose puzzle reveal the recovery fields
+    /// are read from.
+    pub revealed_by: Bytes32,
+    pub revealed_height: u32,
+    /// Whether the current coin has the puzzle that spend revealed. A spend
+    /// that updates the DID creates the current coin with a new puzzle, which
+    /// isn't revealed until that coin is spent. The recovery fields are then
+    /// left out rather than showing the ones from before the update.
+    pub up_to_date: bool,
+    #[serde(flatten)]
+    pub recovery: Option<DidRecovery>,
+}
+
+/// The recovery list hash and required verifications of a DID, found by
+/// walking its singleton from the launcher to the current coin.
+pub async fn did_recovery(
+    State(app): State<App>,
+    Path(DidId(did_id)): Path<DidId>,
+    Query(query): Query<DidRecoveryRequest>,
+) -> Result<Json<DidRecoveryResponse>, AppError> {
+    app.read(move |app| {
+        let tip = singleton_tip(app, "DID", did_id, app.node_budget(query.max_nodes))?;
+
+        let puzzle_reveal = &tip.newest_spend.puzzle_reveal;
+
+        let Some(recovery) = parse_did_recovery(puzzle_reveal)? else {
+            return Err(AppError::NotFound(format!(
+                "Singleton {did_id} isn't a DID"
+            )));
+        };
+
+        let up_to_date = puzzle_hash(puzzle_reveal)? == tip.puzzle_hash;
+
+        Ok(Json(DidRecoveryResponse {
+            did_id,
+            coin_id: tip.coin_id,
+            revealed_by: tip.newest_spend_coin_id,
+            revealed_height: tip.newest_spend.spent_height,
+            up_to_date,
+            recovery: up_to_date.then_some(recovery),
+        }))
+    })
+    .await
+}
@@ -0,0 +1,402 @@
+use axum::extract::State;
+use serde::Serialize;
+
+use crate::db::{IndexKind, DATA_VERSION};
+
+use super::{App, Json};
+
+pub struct RouteInfo {
+    pub method: &'static str,
+    pub path: &'static str,
+    pub description: &'static str,
+    /// The optional index the route can't be served without.
+    pub index: Option<IndexKind>,
+}
+
+const fn route(method: &'static str, path: &'static str, description: &'static str) -> RouteInfo {
+    RouteInfo {
+        method,
+        path,
+        description,
+        index: None,
+    }
+}
+
+impl RouteInfo {
+    const fn needs(mut self, index: IndexKind) -> Self {
+        self.index = Some(index);
+        self
+    }
+}
+
+/// Every route the router can mount, written exactly as they are in `router`,
+/// which checks in debug builds that none are missing.
+pub const ROUTE_DIRECTORY: &[RouteInfo] = &[
+    route("GET", "/", "This list of routes."),
+    route("GET", "/health", "Whether the server is up."),
+    route("GET", "/metrics", "Metrics in the Prometheus text format."),
+    route(
+        "GET",
+        "/admin/errors",
+        "Recent errors that stopped ingestion.",
+    ),
+    route(
+        "POST",
+        "/admin/reindex/{height}",
+        "Re-indexes the block at a height.",
+    ),
+    route("GET", "/admin/storage", "Disk usage of each column family."),
+    route("GET, HEAD", "/state", "Sync progress and the indexed peak."),
+    route(
+        "GET",
+        "/ready",
+        "Whether the index has caught up with the node.",
+    ),
+    route("GET", "/blocks/latest", "The peak block."),
+    route("GET", "/blocks/height/{height}", "A block by height."),
+    route(
+        "GET",
+        "/blocks/height/{height}/spends",
+        "A block's coin spends.",
+    ),
+    route(
+        "GET",
+        "/blocks/height/{height}/additions",
+        "A page of the coins a block created.",
+    ),
+    route(
+        "GET",
+        "/blocks/height/{height}/removals",
+        "A page of the coins a block spent.",
+    ),
+    route(
+        "GET",
+        "/blocks/height/{height}/coins/count",
+        "How many coins a block created and spent.",
+    ),
+    route("GET", "/blocks/hash/{hash}", "A block by header hash."),
+    route("GET", "/blocks", "A page of blocks."),
+    route("GET", "/blocks/activity", "Per-block coin counts."),
+    route(
+        "GET",
+        "/blocks/range",
+        "The lowest and highest indexed block heights.",
+    ),
+    route(
+        "GET",
+        "/headers",
+        "A page of block headers, for header-only sync.",
+    ),
+    route("POST", "/blocks/heights", "Several blocks by height."),
+    route(
+        "GET",
+        "/blocks/prefix/{prefix}",
+        "Blocks whose header hash starts with a prefix.",
+    ),
+    route(
+        "GET",
+        "/blocks/export.ndjson",
+        "Blocks as newline delimited JSON.",
+    ),
+    route(
+        "GET",
+        "/coins/block/{hash}",
+        "The coins created and spent in a block, by hash.",
+    ),
+    route(
+        "GET",
+        "/coins/block/height/{height}",
+        "The coins created and spent in a block.",
+    ),
+    route(
+        "GET",
+        "/coins/children/{coin_id}",
+        "The coins a coin created.",
+    )
+    .needs(IndexKind::ParentCoinId),
+    route(
+        "GET",
+        "/coins/children/{coin_id}/count",
+        "How many coins a coin created.",
+    )
+    .needs(IndexKind::ParentCoinId),
+    route("GET", "/coins/hint/{hint}", "The coins with a hint.").needs(IndexKind::Hint),
+    route("GET", "/coins/amount/{amount}", "The coins with an amount.").needs(IndexKind::Amount),
+    route(
+        "GET",
+        "/coins/amount-range",
+        "The coins with amounts in a range.",
+    )
+    .needs(IndexKind::Amount),
+    route("GET", "/coins/id/{coin_id}", "A coin and its spend."),
+    route(
+        "GET",
+        "/coins/id/{coin_id}/proof",
+        "Merkle proofs of a coin's creation and spend.",
+    ),
+    route(
+        "GET",
+        "/coins/id/{coin_id}/spend-proof",
+        "A Merkle proof of a coin's spend against its block's removals root.",
+    ),
+    route(
+        "GET",
+        "/coins/id/{coin_id}/creator-spend",
+        "The spend that created a coin.",
+    ),
+    route(
+        "GET",
+        "/coins/id/{coin_id}/puzzle-hash",
+        "A coin's puzzle hash.",
+    ),
+    route(
+        "GET",
+        "/coins/id/{coin_id}/raw",
+        "A coin in its serialized form.",
+    ),
+    route(
+        "GET",
+        "/coins/id/{coin_id}/record",
+        "A coin in the shape of the full node's coin record.",
+    ),
+    route(
+        "GET",
+        "/coins/id/{coin_id}/spendable",
+        "Whether a coin is unspent and confirmed.",
+    ),
+    route(
+        "GET",
+        "/coins/id/{coin_id}/status",
+        "Whether a coin is confirmed and finalized.",
+    ),
+    route(
+        "GET",
+        "/coins/id/{coin_id}/created-in",
+        "The block that created a coin.",
+    ),
+    route(
+        "GET",
+        "/coins/id/{coin_id}/spent-in",
+        "The block that spent a coin.",
+    ),
+    route(
+        "GET",
+        "/coins/id/{coin_id}/timeline",
+        "A coin with its parent, children, and spends.",
+    ),
+    route(
+        "GET",
+        "/coins/id/{coin_id}/verify-lineage",
+        "Checks a coin's lineage up to an ancestor.",
+    ),
+    route(
+        "GET",
+        "/coins/id/{coin_id}/provenance",
+        "A singleton's lineage back to its launcher.",
+    ),
+    route(
+        "GET",
+        "/coins/id/{coin_id}/launcher",
+        "A singleton's launcher coin.",
+    ),
+    route("GET", "/coins/id/{coin_id}/tree", "A coin's descendants.")
+        .needs(IndexKind::ParentCoinId),
+    route(
+        "GET",
+        "/coins/id/{coin_id}/solution-tree",
+        "A coin's solution as CLVM text.",
+    ),
+    route(
+        "GET",
+        "/coins/id/{coin_id}/signatures",
+        "The AGG_SIG conditions of a coin's spend.",
+    ),
+    route(
+        "POST",
+        "/coins/puzzles",
+        "The coins of several puzzle hashes.",
+    )
+    .needs(IndexKind::PuzzleHash),
+    route(
+        "POST",
+        "/coins/select",
+        "Selects unspent coins for an amount.",
+    )
+    .needs(IndexKind::PuzzleHash),
+    route(
+        "GET",
+        "/coins/puzzle/{puzzle_hash}/exists",
+        "Whether a puzzle hash has ever received a coin.",
+    )
+    .needs(IndexKind::PuzzleHash),
+    route(
+        "GET",
+        "/coins/puzzle/{puzzle_hash}/first",
+        "The first coin with a puzzle hash.",
+    )
+    .needs(IndexKind::PuzzleHash),
+    route(
+        "GET",
+        "/coins/puzzle/{puzzle_hash}/latest",
+        "The newest coins with a puzzle hash.",
+    )
+    .needs(IndexKind::PuzzleHashHeight),
+    route(
+        "GET",
+        "/coins/puzzle/{puzzle_hash}/summary",
+        "A puzzle hash's balance, totals and activity heights.",
+    )
+    .needs(IndexKind::PuzzleHashSummary),
+    route(
+        "GET",
+        "/coins/puzzle/{puzzle_hash}/history",
+        "A puzzle hash's coins, most recently spent first.",
+    )
+    .needs(IndexKind::PuzzleHashSummary),
+    route(
+        "GET",
+        "/coins/puzzle/{puzzle_hash}/spends",
+        "The spends of a puzzle hash's coins.",
+    )
+    .needs(IndexKind::PuzzleHash),
+    route(
+        "GET",
+        "/coins/puzzle/{puzzle_hash}/ids",
+        "The ids of a puzzle hash's coins.",
+    )
+    .needs(IndexKind::PuzzleHash),
+    route(
+        "GET",
+        "/coins/puzzle/{puzzle_hash}/created/height/{height}",
+        "A puzzle hash's coins created at a height.",
+    )
+    .needs(IndexKind::PuzzleHash),
+    route(
+        "GET",
+        "/coins/puzzle/{puzzle_hash}/at/{height}",
+        "A puzzle hash's unspent coins as of a past height.",
+    )
+    .needs(IndexKind::PuzzleHashHeight),
+    route(
+        "GET",
+        "/coins/prefix/{prefix}",
+        "Coins whose id starts with a prefix.",
+    ),
+    route(
+        "GET",
+        "/coins/diff",
+        "The coins created and spent in a height range.",
+    ),
+    route("GET", "/coins/recent", "The most recently spent coins."),
+    route("GET", "/coins/spent", "The coins spent in a height range."),
+    route(
+        "GET",
+        "/coins/latest",
+        "The coins created and spent in the peak block.",
+    ),
+    route(
+        "POST",
+        "/coins/status",
+        "Whether each of several coins exists and is spent.",
+    ),
+    route("POST", "/coins/compute-id", "Computes a coin id."),
+    route("GET", "/spends/range", "The spends in a height range."),
+    route(
+        "POST",
+        "/balances",
+        "The balances of several puzzle hashes.",
+    )
+    .needs(IndexKind::PuzzleHash),
+    route("GET", "/cats", "Asset ids with their coin counts."),
+    route("GET", "/search/name/{query}", "Singletons by name."),
+    route(
+        "GET",
+        "/dids/{did_id}/recovery",
+        "A DID's recovery list hash and required verifications.",
+    )
+    .needs(IndexKind::ParentCoinId),
+    route(
+        "GET",
+        "/nfts/{launcher_id}/owner",
+        "The DID that owns an NFT.",
+    )
+    .needs(IndexKind::ParentCoinId),
+    route(
+        "GET",
+        "/offers/{nonce}/coins",
+        "The coins settled by an offer.",
+    ),
+    route("GET", "/stats/tx-series", "Transactions over time."),
+    route("GET", "/stats/reorgs", "Reorgs since startup."),
+    route("GET", "/stats/dust", "Unspent coins below a threshold.").needs(IndexKind::Amount),
+    route("GET", "/stats/supply", "The issued and burned supply."),
+    route(
+        "GET",
+        "/stats/difficulty",
+        "The difficulty of recent blocks.",
+    ),
+    route(
+        "GET",
+        "/stats/puzzle-usage",
+        "Spends in a height range by puzzle.",
+    ),
+    route("POST", "/simulate", "Runs a spend without submitting it."),
+    route("POST", "/puzzle/hash", "The tree hash of a puzzle reveal."),
+    route(
+        "GET",
+        "/encode/address/{puzzle_hash}",
+        "Encodes a puzzle hash as an address.",
+    ),
+    route(
+        "GET",
+        "/ws/events",
+        "Block and reorg events over a WebSocket.",
+    ),
+    route(
+        "GET",
+        "/ws/coins/sync",
+        "Coin diffs from a height, then live, over a WebSocket.",
+    ),
+];
+
+#[derive(Serialize)]
+pub struct RouteListing {
+    pub method: &'static str,
+    pub path: &'static str,
+    pub description: &'static str,
+    /// Whether the route is mounted and the index it needs is enabled.
+    pub enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<IndexKind>,
+}
+
+#[derive(Serialize)]
+pub struct RouteDirectoryResponse {
+    pub data_version: u32,
+    pub routes: Vec<RouteListing>,
+}
+
+/// The routes this server has, with whether each one can be used given the
+/// disabled routes and enabled indexes in the config.
+pub async fn route_directory(State(app): State<App>) -> Json<RouteDirectoryResponse> {
+    let routes = ROUTE_DIRECTORY
+        .iter()
+        .map(|route| RouteListing {
+            method: route.method,
+            path: route.path,
+            description: route.description,
+            enabled: !app
+                .config
+                .disabled_routes
+                .iter()
+                .any(|path| path == route.path)
+                && route.index.is_none_or(|index| app.db.is_index_built(index)),
+            index: route.index,
+        })
+        .collect();
+
+    Json(RouteDirectoryResponse {
+        data_version: DATA_VERSION,
+        routes,
+    })
+}
@@ -0,0 +1,169 @@
+use axum::{
+    extract::rejection::{JsonRejection, PathRejection, QueryRejection},
+    http::{StatusCode, Uri},
+    response::{IntoResponse, Response},
+};
+use chia::protocol::Bytes32;
+use serde::Serialize;
+use tracing::error;
+
+use crate::clvm::CostExceeded;
+
+use super::Json;
+
+/// The body of every error response, so clients can branch on `code` rather
+/// than on status codes or message text.
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorBody {
+    pub status: u16,
+    pub code: &'static str,
+    pub message: String,
+}
+
+/// The full internal error behind a 500 response, kept in the response's
+/// extensions so that error reporting sees what the body leaves out.
+#[derive(Debug, Clone)]
+pub struct InternalErrorReport(pub String);
+
+#[derive(Debug)]
+pub enum AppError {
+    NotFound(String),
+    Unauthorized(String),
+    InvalidParam(String),
+    InvalidBody(String),
+    NotImplemented(String),
+    ResponseTooLarge(String),
+    TooManyRequests(String),
+    Internal(anyhow::Error),
+}
+
+impl AppError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Self::InvalidParam(_) | Self::InvalidBody(_) => StatusCode::BAD_REQUEST,
+            Self::NotImplemented(_) => StatusCode::NOT_IMPLEMENTED,
+            Self::ResponseTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotFound(_) => "not_found",
+            Self::Unauthorized(_) => "unauthorized",
+            Self::InvalidParam(_) => "invalid_param",
+            Self::InvalidBody(_) => "invalid_body",
+            Self::NotImplemented(_) => "not_implemented",
+            Self::ResponseTooLarge(_) => "response_too_large",
+            Self::TooManyRequests(_) => "too_many_requests",
+            Self::Internal(_) => "internal",
+        }
+    }
+}
+
+impl AppError {
+    /// Running a recorded spend again failed. Going over the cost limit is a
+    /// budget like any other, but the spend was accepted on chain, so any
+    /// other failure is ours to report.
+    pub fn replay(coin_id: Bytes32, error: anyhow::Error) -> Self {
+        match error.downcast_ref::<CostExceeded>() {
+            Some(CostExceeded(max_cost)) => Self::ResponseTooLarge(format!(
+                "The spend of coin {coin_id} costs more than {max_cost} to run"
+            )),
+            None => {
+                Self::Internal(error.context(format!("Failed to run the spend of coin {coin_id}")))
+            }
+        }
+    }
+}
+
+impl From<anyhow::Error> for AppError {
+    fn from(error: anyhow::Error) -> Self {
+        Self::Internal(error)
+    }
+}
+
+impl From<PathRejection> for AppError {
+    fn from(rejection: PathRejection) -> Self {
+        Self::InvalidParam(rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for AppError {
+    fn from(rejection: QueryRejection) -> Self {
+        Self::InvalidParam(rejection.body_text())
+    }
+}
+
+impl From<JsonRejection> for AppError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::InvalidBody(rejection.body_text())
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let code = self.code();
+
+        let mut report = None;
+
+        let message = match self {
+            Self::NotFound(message)
+            | Self::Unauthorized(message)
+            | Self::InvalidParam(message)
+            | Self::InvalidBody(message)
+            | Self::NotImplemented(message)
+            | Self::ResponseTooLarge(message)
+            | Self::TooManyRequests(message) => message,
+            Self::Internal(error) => {
+                // Internal details stay in the logs rather than the response.
+                error!("Internal error: {error:?}");
+                report = Some(InternalErrorReport(format!("{error:?}")));
+                "Internal server error".to_string()
+            }
+        };
+
+        let mut response = (
+            status,
+            Json(ErrorBody {
+                status: status.as_u16(),
+                code,
+                message,
+            }),
+        )
+            .into_response();
+
+        if let Some(report) = report {
+            response.extensions_mut().insert(report);
+        }
+
+        response
+    }
+}
+
+pub async fn fallback(uri: Uri) -> AppError {
+    AppError::NotFound(format!("No route for {}", uri.path()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::Database;
+    use crate::routes::{test_get, test_router};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn unknown_paths_get_a_json_not_found() {
+        let db = Database::open_temp().unwrap();
+        let response = test_get(&test_router(&db), "/nonexistent").await;
+
+        assert_eq!(response.status, StatusCode::NOT_FOUND);
+        assert_eq!(response.body["status"], 404);
+        assert_eq!(response.body["code"], "not_found");
+        assert_eq!(response.body["message"], "No route for /nonexistent");
+    }
+}
@@ -0,0 +1,36 @@
+use axum::{extract::Request, http::HeaderName, middleware::Next, response::Response};
+
+use super::InternalErrorReport;
+
+const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Reports internal errors to Sentry, including panics caught by the panic
+/// layer, which is why this sits outside of it. Requests are tagged with the
+/// method, the path, and the `x-request-id` header if a proxy in front set one.
+pub async fn report_errors(request: Request, next: Next) -> Response {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+
+    let response = next.run(request).await;
+
+    if let Some(InternalErrorReport(message)) = response.extensions().get() {
+        sentry::with_scope(
+            |scope| {
+                scope.set_tag("method", &method);
+                scope.set_tag("route", &path);
+
+                if let Some(request_id) = &request_id {
+                    scope.set_tag("request_id", request_id);
+                }
+            },
+            || sentry::capture_message(message, sentry::Level::Error),
+        );
+    }
+
+    response
+}
@@ -0,0 +1,123 @@
+use axum::{
+    http::{
+        header::{ETAG, IF_NONE_MATCH},
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    response::{IntoResponse, Response},
+};
+use chia::protocol::Bytes32;
+
+use super::{App, AppError};
+
+/// A block's header hash identifies its contents, so it doubles as the ETag.
+/// Blocks within `reorg_buffer` of the peak can still be replaced by a reorg
+/// at the same height, so their tags are weak.
+pub fn block_etag(app: &App, height: u32, header_hash: Bytes32) -> Result<HeaderValue, AppError> {
+    let peak_height = app.db.peak_height()?.unwrap_or(height);
+    let weak = peak_height.saturating_sub(height) < app.config.reorg_buffer;
+
+    let etag = if weak {
+        format!("W/\"{header_hash}\"")
+    } else {
+        format!("\"{header_hash}\"")
+    };
+
+    Ok(HeaderValue::from_str(&etag).map_err(anyhow::Error::from)?)
+}
+
+/// Whether `If-None-Match` lists the tag, using the weak comparison that
+/// RFC 9110 requires for it.
+fn matches_if_none_match(headers: &HeaderMap, etag: &HeaderValue) -> bool {
+    let Some(if_none_match) = headers
+        .get(IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return false;
+    };
+
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let etag = etag.to_str().map(opaque).unwrap_or_default();
+
+    if_none_match
+        .split(',')
+        .any(|tag| tag.trim() == "*" || opaque(tag) == etag)
+}
+
+/// Answers with `304 Not Modified` when the client already has the tagged
+/// representation, and otherwise with the body and its ETag.
+pub fn conditional(headers: &HeaderMap, etag: HeaderValue, body: impl IntoResponse) -> Response {
+    if matches_if_none_match(headers, &etag) {
+        return (StatusCode::NOT_MODIFIED, [(ETAG, etag)]).into_response();
+    }
+
+    ([(ETAG, etag)], body).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STRONG: &str = "\"abc\"";
+    const WEAK: &str = "W/\"abc\"";
+
+    fn if_none_match(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(IF_NONE_MATCH, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn weak_and_strong_tags_match_each_other() {
+        for (header, etag) in [
+            (WEAK, STRONG),
+            (STRONG, WEAK),
+            (WEAK, WEAK),
+            (STRONG, STRONG),
+        ] {
+            assert!(matches_if_none_match(
+                &if_none_match(header),
+                &HeaderValue::from_static(etag)
+            ));
+        }
+
+        assert!(!matches_if_none_match(
+            &if_none_match("W/\"abd\""),
+            &HeaderValue::from_static(STRONG)
+        ));
+    }
+
+    #[test]
+    fn any_tag_in_a_list_matches() {
+        let headers = if_none_match("\"xyz\", W/\"abc\" ,\"def\"");
+
+        assert!(matches_if_none_match(
+            &headers,
+            &HeaderValue::from_static(STRONG)
+        ));
+        assert!(!matches_if_none_match(
+            &headers,
+            &HeaderValue::from_static("\"uvw\"")
+        ));
+    }
+
+    #[test]
+    fn wildcard_matches_anything() {
+        assert!(matches_if_none_match(
+            &if_none_match("*"),
+            &HeaderValue::from_static(STRONG)
+        ));
+    }
+
+    #[test]
+    fn conditional_answers_not_modified_only_on_a_match() {
+        let etag = HeaderValue::from_static(STRONG);
+
+        let response = conditional(&if_none_match(WEAK), etag.clone(), "body");
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers().get(ETAG), Some(&etag));
+
+        let response = conditional(&HeaderMap::new(), etag.clone(), "body");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get(ETAG), Some(&etag));
+    }
+}
@@ -0,0 +1,156 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    extract::{
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::Response,
+};
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    Notify,
+};
+
+use crate::{
+    config::{OverflowPolicy, WebSocketConfig},
+    events::Event,
+};
+
+use super::App;
+
+pub async fn events(State(app): State<App>, ws: WebSocketUpgrade) -> Response {
+    let receiver = app.events.subscribe();
+    let config = app.config.websocket;
+    ws.on_upgrade(move |socket| forward_events(socket, receiver, config))
+}
+
+/// The events waiting to be sent to one client.
+struct ClientQueue {
+    state: Mutex<QueueState>,
+    notify: Notify,
+}
+
+#[derive(Default)]
+struct QueueState {
+    events: VecDeque<Event>,
+    closed: bool,
+    overflowed: bool,
+}
+
+impl ClientQueue {
+    /// Queues an event, returning `false` once the queue has been closed.
+    fn push(&self, event: Event, config: WebSocketConfig) -> bool {
+        let mut state = self.state.lock().unwrap();
+
+        if state.closed {
+            return false;
+        }
+
+        if state.events.len() >= config.queue_capacity.max(1) {
+            match config.overflow {
+                OverflowPolicy::DropOldest => {
+                    state.events.pop_front();
+                }
+                OverflowPolicy::Close => {
+                    // The backlog is dropped, since the client is told to
+                    // reconnect rather than being sent the rest of it.
+                    state.events.clear();
+                    state.closed = true;
+                    state.overflowed = true;
+                    drop(state);
+                    self.notify.notify_one();
+                    return false;
+                }
+            }
+        }
+
+        state.events.push_back(event);
+        drop(state);
+        self.notify.notify_one();
+        true
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.notify.notify_one();
+    }
+
+    /// The next event to send, or `None` once the queue is closed and empty.
+    async fn pop(&self) -> Option<Event> {
+        loop {
+            {
+                let mut state = self.state.lock().unwrap();
+
+                if let Some(event) = state.events.pop_front() {
+                    return Some(event);
+                }
+
+                if state.closed {
+                    return None;
+                }
+            }
+
+            self.notify.notified().await;
+        }
+    }
+}
+
+/// Events are moved from the shared broadcast channel onto the client's own
+/// queue as soon as they arrive, so the socket can fall behind by at most the
+/// queue's capacity before the overflow policy kicks in.
+async fn forward_events(
+    mut socket: WebSocket,
+    mut receiver: broadcast::Receiver<Event>,
+    config: WebSocketConfig,
+) {
+    let queue = Arc::new(ClientQueue {
+        state: Mutex::new(QueueState::default()),
+        notify: Notify::new(),
+    });
+
+    let feeder = tokio::spawn({
+        let queue = queue.clone();
+
+        async move {
+            loop {
+                let event = match receiver.recv().await {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                };
+
+                if !queue.push(event, config) {
+                    break;
+                }
+            }
+
+            queue.close();
+        }
+    });
+
+    while let Some(event) = queue.pop().await {
+        let Ok(text) = serde_json::to_string(&event) else {
+            continue;
+        };
+
+        if socket.send(Message::Text(text.into())).await.is_err() {
+            break;
+        }
+    }
+
+    feeder.abort();
+
+    if queue.state.lock().unwrap().overflowed {
+        socket
+            .send(Message::Close(Some(CloseFrame {
+                code: close_code::POLICY,
+                reason: "Send queue is full".into(),
+            })))
+            .await
+            .ok();
+    }
+}
@@ -0,0 +1,153 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{FromRequest, FromRequestParts, Request},
+    http::{header::CONTENT_TYPE, HeaderValue},
+    response::{IntoResponse, Response},
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{strip_hex_prefixes, App, AppError, FORMAT};
+
+// These wrap axum's extractors so that malformed requests are rejected with
+// the same `ErrorBody` as every other failure.
+
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Path), rejection(AppError))]
+pub struct Path<T>(pub T);
+
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Query), rejection(AppError))]
+pub struct Query<T>(pub T);
+
+pub struct Json<T>(pub T);
+
+/// The nesting depth is checked before the body is parsed, so that a deeply
+/// nested body is rejected without recursing through it.
+impl<T> FromRequest<App> for Json<T>
+where
+    T: DeserializeOwned,
+{
+    type Rejection = AppError;
+
+    async fn from_request(request: Request, app: &App) -> Result<Self, Self::Rejection> {
+        let (parts, body) = request.into_parts();
+
+        let bytes = Bytes::from_request(Request::from_parts(parts.clone(), body), app)
+            .await
+            .map_err(|rejection| AppError::InvalidBody(rejection.body_text()))?;
+
+        let max_depth = app.config.max_json_depth;
+
+        if json_depth_exceeds(&bytes, max_depth) {
+            return Err(AppError::InvalidBody(format!(
+                "Request body is nested more than {max_depth} levels deep"
+            )));
+        }
+
+        let axum::Json(value) =
+            axum::Json::from_request(Request::from_parts(parts, Body::from(bytes)), app).await?;
+
+        Ok(Self(value))
+    }
+}
+
+/// Whether arrays and objects are nested more than `max_depth` levels deep,
+/// ignoring brackets inside strings. Malformed JSON is left to the parser.
+fn json_depth_exceeds(bytes: &[u8], max_depth: usize) -> bool {
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for &byte in bytes {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match byte {
+            b'"' => in_string = true,
+            b'[' | b'{' => {
+                depth += 1;
+
+                if depth > max_depth {
+                    return true;
+                }
+            }
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+
+    false
+}
+
+impl<T> IntoResponse for Json<T>
+where
+    T: Serialize,
+{
+    fn into_response(self) -> Response {
+        let format = FORMAT.try_with(|format| format.clone()).unwrap_or_default();
+
+        let body = if !format.pretty && format.hex_prefix {
+            serde_json::to_vec(&self.0)
+        } else {
+            serde_json::to_value(&self.0).and_then(|mut value| {
+                if !format.hex_prefix {
+                    strip_hex_prefixes(&mut value);
+                }
+
+                if format.pretty {
+                    serde_json::to_vec_pretty(&value)
+                } else {
+                    serde_json::to_vec(&value)
+                }
+            })
+        };
+
+        match body {
+            Ok(body) if body.len() > format.max_bytes => AppError::ResponseTooLarge(format!(
+                "Response of {} bytes exceeds the limit of {} bytes",
+                body.len(),
+                format.max_bytes
+            ))
+            .into_response(),
+            Ok(body) => (
+                [(CONTENT_TYPE, HeaderValue::from_static("application/json"))],
+                body,
+            )
+                .into_response(),
+            Err(error) => AppError::Internal(error.into()).into_response(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nested(depth: usize) -> String {
+        format!("{}{}", "[".repeat(depth), "]".repeat(depth))
+    }
+
+    #[test]
+    fn json_depth_at_the_limit_is_allowed() {
+        assert!(!json_depth_exceeds(nested(4).as_bytes(), 4));
+        assert!(!json_depth_exceeds(br#"{"a": [{"b": 1}], "c": {}}"#, 3));
+    }
+
+    #[test]
+    fn json_depth_past_the_limit_is_refused() {
+        assert!(json_depth_exceeds(nested(5).as_bytes(), 4));
+        assert!(json_depth_exceeds(br#"{"a": [{"b": []}]}"#, 3));
+    }
+
+    #[test]
+    fn json_depth_ignores_brackets_in_strings() {
+        assert!(!json_depth_exceeds(br#"["[[[[", "\"{{{{"]"#, 1));
+    }
+}
@@ -0,0 +1,307 @@
+use std::{io, sync::Arc};
+
+use anyhow::Result;
+use axum::{
+    extract::{Query, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::App;
+
+tokio::task_local! {
+    /// How `Json` responses for the current request are serialized.
+    pub static FORMAT: ResponseFormat;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseFormat {
+    pub pretty: bool,
+    pub hex_prefix: bool,
+    pub max_bytes: usize,
+    /// Prepended to links back to this server, without a trailing slash.
+    pub base_url: Option<Arc<str>>,
+    /// The bech32m prefix of the addresses added to coins, or `None` if they're
+    /// left out with `address=false`.
+    pub address_prefix: Option<Arc<str>>,
+    /// Whether coins include their amount in XCH, with `xch=true`.
+    pub amount_xch: bool,
+}
+
+impl Default for ResponseFormat {
+    fn default() -> Self {
+        Self {
+            pretty: false,
+            hex_prefix: true,
+            max_bytes: usize::MAX,
+            base_url: None,
+            address_prefix: None,
+            amount_xch: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum HexPrefix {
+    #[serde(rename = "0x")]
+    Prefixed,
+    None,
+}
+
+#[derive(Deserialize)]
+struct FormatRequest {
+    #[serde(default)]
+    pretty: Option<bool>,
+    #[serde(default)]
+    prefix: Option<HexPrefix>,
+    #[serde(default)]
+    address: Option<bool>,
+    #[serde(default)]
+    xch: Option<bool>,
+}
+
+pub async fn response_format(State(app): State<App>, request: Request, next: Next) -> Response {
+    let query = Query::<FormatRequest>::try_from_uri(request.uri()).ok();
+
+    let format = ResponseFormat {
+        pretty: query
+            .as_ref()
+            .and_then(|Query(query)| query.pretty)
+            .unwrap_or(app.config.pretty),
+        hex_prefix: match query.as_ref().and_then(|Query(query)| query.prefix) {
+            Some(prefix) => prefix == HexPrefix::Prefixed,
+            None => app.config.hex_prefix,
+        },
+        max_bytes: app.config.max_response_bytes,
+        base_url: app
+            .config
+            .public_base_url
+            .as_deref()
+            .map(|base_url| base_url.trim_end_matches('/').into()),
+        address_prefix: query
+            .as_ref()
+            .and_then(|Query(query)| query.address)
+            .unwrap_or(true)
+            .then(|| app.config.address_prefix.as_str().into()),
+        amount_xch: query.and_then(|Query(query)| query.xch).unwrap_or(false),
+    };
+
+    FORMAT.scope(format, next.run(request)).await
+}
+
+/// The response fields that hold hashes or other bytes, which serialize as
+/// `0x` prefixed hex. Other strings are left alone even if they look like
+/// hex, since they can be text the chain was given, such as singleton names.
+const HEX_FIELDS: &[&str] = &[
+    "additions_root",
+    "args",
+    "asset_id",
+    "broken_at",
+    "coin_id",
+    "coin_ids",
+    "coin_ids_proof",
+    "created",
+    "cursor",
+    "did_id",
+    "farmer_puzzle_hash",
+    "hash",
+    "header_hash",
+    "hint",
+    "indexed_peak",
+    "inner_puzzle_hash",
+    "launcher_id",
+    "memos",
+    "message",
+    "mod_hash",
+    "newest_spend_coin_id",
+    "next_cursor",
+    "nonce",
+    "offer_coins",
+    "owner_did",
+    "parent_coin_id",
+    "parent_coin_info",
+    "parent_inner_puzzle_hash",
+    "parent_parent_coin_id",
+    "peak_hash",
+    "pool_puzzle_hash",
+    "prev_block_hash",
+    "prev_hash",
+    "prev_transaction_block_hash",
+    "proof",
+    "public_key",
+    "puzzle_hash",
+    "puzzle_hash_proof",
+    "puzzle_hashes",
+    "puzzle_reveal",
+    "recovery_list_hash",
+    "removals_root",
+    "revealed_by",
+    "root",
+    "solution",
+    "spent",
+    "sub_slot_challenge",
+    "to",
+];
+
+/// Removes the `0x` prefix from the hex in `HEX_FIELDS`, and from a bare
+/// string or array of strings at the top level, which is how lists of ids are
+/// returned. Empty bytes keep their prefix so they aren't left as `""`.
+pub fn strip_hex_prefixes(value: &mut Value) {
+    strip_hex(value, true);
+}
+
+fn strip_hex(value: &mut Value, is_hex: bool) {
+    match value {
+        Value::String(string) if is_hex => {
+            if let Some(hex) = string.strip_prefix("0x") {
+                if !hex.is_empty() && hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+                    *string = hex.to_string();
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(|value| strip_hex(value, is_hex)),
+        Value::Object(map) => map
+            .iter_mut()
+            .for_each(|(key, value)| strip_hex(value, HEX_FIELDS.contains(&key.as_str()))),
+        Value::String(_) | Value::Null | Value::Bool(_) | Value::Number(_) => {}
+    }
+}
+
+/// The public base URL for links in the current response, or an empty string
+/// if links are relative to the request path.
+pub fn base_url() -> Arc<str> {
+    FORMAT
+        .try_with(|format| format.base_url.clone())
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| "".into())
+}
+
+/// The prefix to encode coin addresses with in the current response, or `None`
+/// if they're left out, which they always are outside of a request.
+pub fn address_prefix() -> Option<Arc<str>> {
+    FORMAT
+        .try_with(|format| format.address_prefix.clone())
+        .ok()
+        .flatten()
+}
+
+/// Whether coins in the current response include their amount in XCH.
+pub fn amount_xch() -> bool {
+    FORMAT.try_with(|format| format.amount_xch).unwrap_or(false)
+}
+
+/// Formats mojos as XCH with all 12 decimal places, such as `1.750000000000`.
+/// It's a string so that clients don't round it through a float.
+pub fn mojos_to_xch(mojos: u64) -> String {
+    const MOJOS_PER_XCH: u64 = 1_000_000_000_000;
+    format!("{}.{:012}", mojos / MOJOS_PER_XCH, mojos % MOJOS_PER_XCH)
+}
+
+/// The byte budget for the current response, or unlimited outside of a
+/// request.
+pub fn response_budget() -> usize {
+    FORMAT
+        .try_with(|format| format.max_bytes)
+        .unwrap_or(usize::MAX)
+}
+
+/// Room kept in the budget for what surrounds a page's items: the other fields
+/// of the response, such as `next_cursor`, and the list's own brackets.
+const PAGE_ENVELOPE_BYTES: usize = 256;
+
+/// Counts the bytes written to it, so items can be measured without being
+/// serialized into a buffer.
+#[derive(Default)]
+struct ByteCount(usize);
+
+impl io::Write for ByteCount {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0 += bytes.len();
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Drops items from the end of a page once the page would exceed the response
+/// budget, returning whether it was cut short. The first item is always kept
+/// so that the cursor can advance past it.
+///
+/// Only the lists of coins and spends use it, since puzzle reveals and memos
+/// make their items unbounded in size. Other lists rely on their `limit`, and
+/// any response still over the budget is refused with a 413 by `Json`.
+pub fn truncate_to_budget<T>(items: &mut Vec<T>) -> Result<bool>
+where
+    T: Serialize,
+{
+    let budget = response_budget().saturating_sub(PAGE_ENVELOPE_BYTES);
+    let mut total = 0;
+
+    for (index, item) in items.iter().enumerate() {
+        let mut count = ByteCount::default();
+        serde_json::to_writer(&mut count, item)?;
+
+        // Each item after the first is preceded by a comma.
+        total += count.0 + usize::from(index > 0);
+
+        if total > budget && index > 0 {
+            items.truncate(index);
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn only_hex_fields_lose_their_prefix() {
+        let mut value = json!({
+            "coin_id": "0xabcd",
+            "name": "0xabcd",
+            "coin": { "puzzle_hash": "0x0123", "memo": "0x0123" },
+            "offer_coins": [["0x01", "0x02"]],
+            "solution": "0x",
+        });
+
+        strip_hex_prefixes(&mut value);
+
+        assert_eq!(
+            value,
+            json!({
+                "coin_id": "abcd",
+                "name": "0xabcd",
+                "coin": { "puzzle_hash": "0123", "memo": "0x0123" },
+                "offer_coins": [["01", "02"]],
+                "solution": "0x",
+            })
+        );
+
+        let mut value = json!(["0xabcd", "0xef01"]);
+        strip_hex_prefixes(&mut value);
+        assert_eq!(value, json!(["abcd", "ef01"]));
+    }
+
+    #[test]
+    fn page_envelopes_fit_in_the_room_kept_for_them() {
+        let cursor = format!("0x{}", "ff".repeat(40));
+        let envelope = json!({
+            "coins": [],
+            "next_cursor": cursor,
+            "has_more": false,
+        });
+
+        assert!(serde_json::to_vec(&envelope).unwrap().len() <= PAGE_ENVELOPE_BYTES);
+    }
+}
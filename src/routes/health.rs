@@ -0,0 +1,18 @@
+use axum::extract::State;
+use serde::Serialize;
+
+use crate::db::IntegrityReport;
+
+use super::{App, Json};
+
+#[derive(Serialize)]
+pub struct HealthResponse {
+    /// The result of the integrity check run once at startup.
+    pub integrity: IntegrityReport,
+}
+
+pub async fn health(State(app): State<App>) -> Json<HealthResponse> {
+    Json(HealthResponse {
+        integrity: (*app.integrity).clone(),
+    })
+}
@@ -0,0 +1,130 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use super::{App, AppError};
+
+/// Rejections are only counted per IP for this many addresses, so that a
+/// flood of distinct addresses can't grow the counts without bound. The rest
+/// still count towards the total.
+const MAX_TRACKED_IPS: usize = 1024;
+
+/// Counts each client IP's requests in flight, so that a single client can't
+/// take up every read permit.
+#[derive(Debug, Default)]
+pub struct IpLimiter {
+    in_flight: Mutex<HashMap<IpAddr, usize>>,
+    rejections: Mutex<HashMap<IpAddr, u64>>,
+    rejections_total: AtomicU64,
+}
+
+/// One of an IP's in-flight slots, which is given back when it's dropped.
+pub struct IpPermit {
+    limiter: Arc<IpLimiter>,
+    ip: IpAddr,
+}
+
+impl Drop for IpPermit {
+    fn drop(&mut self) {
+        let mut in_flight = self.limiter.in_flight.lock().unwrap();
+
+        if let Some(count) = in_flight.get_mut(&self.ip) {
+            *count -= 1;
+
+            if *count == 0 {
+                in_flight.remove(&self.ip);
+            }
+        }
+    }
+}
+
+impl IpLimiter {
+    /// Takes one of the IP's `max` slots, or records a rejection if they're
+    /// all taken.
+    pub fn try_acquire(self: &Arc<Self>, ip: IpAddr, max: usize) -> Option<IpPermit> {
+        {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            let count = in_flight.entry(ip).or_default();
+
+            if *count < max {
+                *count += 1;
+
+                return Some(IpPermit {
+                    limiter: self.clone(),
+                    ip,
+                });
+            }
+        }
+
+        self.rejections_total.fetch_add(1, Ordering::Relaxed);
+
+        let mut rejections = self.rejections.lock().unwrap();
+
+        if rejections.len() < MAX_TRACKED_IPS || rejections.contains_key(&ip) {
+            *rejections.entry(ip).or_default() += 1;
+        }
+
+        None
+    }
+
+    /// The number of IPs with every slot taken.
+    pub fn saturated(&self, max: usize) -> usize {
+        self.in_flight
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|&&count| count >= max)
+            .count()
+    }
+
+    pub fn rejections_total(&self) -> u64 {
+        self.rejections_total.load(Ordering::Relaxed)
+    }
+
+    /// The IPs with the most rejections, most first.
+    pub fn top_rejections(&self, limit: usize) -> Vec<(IpAddr, u64)> {
+        let mut rejections = self
+            .rejections
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(&ip, &count)| (ip, count))
+            .collect::<Vec<_>>();
+
+        rejections.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        rejections.truncate(limit);
+        rejections
+    }
+}
+
+/// Refuses a request with a 429 while its IP already has
+/// `max_requests_per_ip` requests in flight. The slot is held until the
+/// handler returns, so a streamed body doesn't keep it.
+pub async fn limit_requests_per_ip(
+    State(app): State<App>,
+    ConnectInfo(address): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let max = app.config.max_requests_per_ip;
+
+    let Some(_permit) = app.ip_limiter.try_acquire(address.ip(), max) else {
+        return AppError::TooManyRequests(format!(
+            "At most {max} requests from one IP can be in flight at once"
+        ))
+        .into_response();
+    };
+
+    next.run(request).await
+}
@@ -0,0 +1,416 @@
+use std::collections::HashMap;
+
+use axum::extract::State;
+use chia::protocol::{self, Bytes, Bytes32};
+use chia_puzzles::SINGLETON_LAUNCHER_HASH;
+use serde::{Deserialize, Serialize};
+
+use crate::db::{CoinSpendRow, CoinType, IndexKind};
+
+use super::{App, AppError, Coin, CoinId, Json, Path, Query};
+
+#[derive(Deserialize)]
+pub struct VerifyLineageRequest {
+    pub to: Bytes32,
+    #[serde(default)]
+    pub max_nodes: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct VerifyLineageResponse {
+    pub verified: bool,
+    /// The first coin in the chain that's missing or whose id doesn't match
+    /// its parent, puzzle hash, and amount.
+    pub broken_at: Option<Bytes32>,
+    pub hops: u32,
+    /// Whether the walk ran out of its node budget without reaching `to`.
+    pub truncated: bool,
+}
+
+/// Walks from the coin up through its parents until reaching `to`, checking
+/// that each coin's id is recomputed from its own stored fields.
+pub async fn verify_lineage(
+    State(app): State<App>,
+    Path(CoinId(coin_id)): Path<CoinId>,
+    Query(query): Query<VerifyLineageRequest>,
+) -> Result<Json<VerifyLineageResponse>, AppError> {
+    app.read(move |app| {
+        let max_hops = app.node_budget(query.max_nodes);
+        let mut current = coin_id;
+        let mut hops = 0;
+
+        let broken_at = loop {
+            let Some(row) = app.db.coin(current)? else {
+                break Some(current);
+            };
+
+            if protocol::Coin::new(row.parent_coin_id, row.puzzle_hash, row.amount).coin_id()
+                != current
+            {
+                break Some(current);
+            }
+
+            if current == query.to {
+                break None;
+            }
+
+            if hops >= max_hops {
+                return Ok(Json(VerifyLineageResponse {
+                    verified: false,
+                    broken_at: None,
+                    hops,
+                    truncated: true,
+                }));
+            }
+
+            current = row.parent_coin_id;
+            hops += 1;
+        };
+
+        Ok(Json(VerifyLineageResponse {
+            verified: broken_at.is_none(),
+            broken_at,
+            hops,
+            truncated: false,
+        }))
+    })
+    .await
+}
+
+/// The launcher coin of a singleton, from the launcher id recorded when the
+/// singleton was indexed rather than by walking its lineage. Coins that aren't
+/// singletons have no launcher, so they're not found like a missing launcher.
+pub async fn coin_launcher(
+    State(app): State<App>,
+    Path(CoinId(coin_id)): Path<CoinId>,
+) -> Result<Json<Coin>, AppError> {
+    app.read(move |app| {
+        let Some(row) = app.db.coin(coin_id)? else {
+            return Err(AppError::NotFound(format!("Coin {coin_id} not found")));
+        };
+
+        let CoinType::Singleton { launcher_id, .. } = row.kind else {
+            return Err(AppError::NotFound(format!(
+                "Coin {coin_id} isn't a singleton"
+            )));
+        };
+
+        let Some(launcher) = Coin::load(&app.db, launcher_id)? else {
+            return Err(AppError::NotFound(format!(
+                "Launcher {launcher_id} not found"
+            )));
+        };
+
+        Ok(Json(launcher))
+    })
+    .await
+}
+
+/// A singleton's current coin, along with the newest spend of it, which is
+/// the newest puzzle reveal on chain.
+pub struct SingletonTip {
+    pub coin_id: Bytes32,
+    pub puzzle_hash: Bytes32,
+    pub newest_spend_coin_id: Bytes32,
+    pub newest_spend: CoinSpendRow,
+}
+
+/// Walks a singleton forward from its launcher, through the parent coin id
+/// index, to the coin that hasn't been spent yet. `name` is what the errors
+/// call the singleton, such as `DID`.
+pub fn singleton_tip(
+    app: &App,
+    name: &str,
+    launcher_id: Bytes32,
+    max_hops: u32,
+) -> Result<SingletonTip, AppError> {
+    app.require_index(IndexKind::ParentCoinId)?;
+
+    let Some(launcher) = app.db.coin(launcher_id)? else {
+        return Err(AppError::NotFound(format!(
+            "{name} {launcher_id} not found"
+        )));
+    };
+
+    if launcher.puzzle_hash.to_bytes() != SINGLETON_LAUNCHER_HASH {
+        return Err(AppError::NotFound(format!(
+            "Coin {launcher_id} isn't a singleton launcher"
+        )));
+    }
+
+    let mut current = launcher_id;
+    let mut puzzle_hash = launcher.puzzle_hash;
+    let mut newest_spend = None;
+    let mut hops = 0;
+
+    while let Some(spend) = app.db.coin_spend(current)? {
+        if hops >= max_hops {
+            return Err(AppError::ResponseTooLarge(format!(
+                "{name} {launcher_id} has been spent more than {max_hops} times"
+            )));
+        }
+
+        // The launcher's own spend doesn't reveal the singleton's puzzle.
+        if current != launcher_id {
+            newest_spend = Some((current, spend));
+        }
+
+        let mut next = None;
+
+        for child_id in app.db.coins_by_parent_coin_id(current)? {
+            let Some(child) = app.db.coin(child_id)? else {
+                continue;
+            };
+
+            if let CoinType::Singleton {
+                launcher_id: child_launcher_id,
+                ..
+            } = child.kind
+            {
+                if child_launcher_id == launcher_id {
+                    next = Some((child_id, child.puzzle_hash));
+                    break;
+                }
+            }
+        }
+
+        let Some(next) = next else {
+            return Err(AppError::NotFound(format!(
+                "{name} {launcher_id} has been melted"
+            )));
+        };
+
+        (current, puzzle_hash) = next;
+        hops += 1;
+    }
+
+    let Some((newest_spend_coin_id, newest_spend)) = newest_spend else {
+        return Err(AppError::NotFound(format!(
+            "{name} {launcher_id} hasn't been spent since it was launched, so its puzzle isn't known"
+        )));
+    };
+
+    Ok(SingletonTip {
+        coin_id: current,
+        puzzle_hash,
+        newest_spend_coin_id,
+        newest_spend,
+    })
+}
+
+#[derive(Deserialize)]
+pub struct ProvenanceRequest {
+    #[serde(default)]
+    pub max_nodes: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct ProvenanceLink {
+    pub coin: Coin,
+    /// The spend of this coin, which created the next link towards the
+    /// requested coin. It's `None` for an unspent tip.
+    pub puzzle_reveal: Option<Bytes>,
+    pub solution: Option<Bytes>,
+}
+
+#[derive(Serialize)]
+pub struct ProvenanceResponse {
+    pub launcher_id: Bytes32,
+    /// The requested coin first, followed by each parent in turn.
+    pub links: Vec<ProvenanceLink>,
+    /// Whether the launcher coin is the last link. When it isn't, the walk
+    /// ran out of its node budget or reached a parent that isn't indexed.
+    pub reached_launcher: bool,
+    pub truncated: bool,
+}
+
+/// Walks from a singleton coin up through its parents to its launcher,
+/// returning every coin along the way together with the spend that consumed
+/// it.
+pub async fn coin_provenance(
+    State(app): State<App>,
+    Path(CoinId(coin_id)): Path<CoinId>,
+    Query(query): Query<ProvenanceRequest>,
+) -> Result<Json<ProvenanceResponse>, AppError> {
+    app.read(move |app| {
+        let Some(row) = app.db.coin(coin_id)? else {
+            return Err(AppError::NotFound(format!("Coin {coin_id} not found")));
+        };
+
+        let CoinType::Singleton { launcher_id, .. } = row.kind else {
+            return Err(AppError::InvalidParam(format!(
+                "Coin {coin_id} isn't a singleton"
+            )));
+        };
+
+        let max_nodes = app.node_budget(query.max_nodes) as usize;
+
+        let mut links = Vec::new();
+        let mut current = Some((coin_id, row));
+        let mut reached_launcher = false;
+        let mut truncated = false;
+
+        while let Some((current_id, row)) = current.take() {
+            if links.len() >= max_nodes {
+                truncated = true;
+                break;
+            }
+
+            let parent_coin_id = row.parent_coin_id;
+            let spend = app.db.coin_spend(current_id)?;
+
+            links.push(ProvenanceLink {
+                coin: Coin::new(
+                    &app.db,
+                    current_id,
+                    row,
+                    spend.as_ref().map(|spend| spend.spent_height),
+                )?,
+                puzzle_reveal: spend.as_ref().map(|spend| spend.puzzle_reveal.clone()),
+                solution: spend.map(|spend| spend.solution),
+            });
+
+            if current_id == launcher_id {
+                reached_launcher = true;
+                break;
+            }
+
+            current = app
+                .db
+                .coin(parent_coin_id)?
+                .map(|parent| (parent_coin_id, parent));
+        }
+
+        Ok(Json(ProvenanceResponse {
+            launcher_id,
+            links,
+            reached_launcher,
+            truncated,
+        }))
+    })
+    .await
+}
+
+const MAX_TREE_DEPTH: u32 = 64;
+
+fn default_tree_depth() -> u32 {
+    8
+}
+
+#[derive(Deserialize)]
+pub struct CoinTreeRequest {
+    #[serde(default = "default_tree_depth")]
+    pub depth: u32,
+    #[serde(default)]
+    pub max_nodes: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct CoinTreeNode {
+    pub coin: Coin,
+    pub children: Vec<CoinTreeNode>,
+}
+
+#[derive(Serialize)]
+pub struct CoinTreeResponse {
+    pub tree: CoinTreeNode,
+    pub node_count: usize,
+    /// Whether some children were left out because of `depth` or the node
+    /// budget.
+    pub truncated: bool,
+}
+
+/// The coin and its descendants up to `depth` generations below it, read a
+/// generation at a time so that a node budget cuts off the deepest coins
+/// first. Coin ids can't repeat in a valid chain, but any that do are only
+/// included once.
+pub async fn coin_tree(
+    State(app): State<App>,
+    Path(CoinId(coin_id)): Path<CoinId>,
+    Query(query): Query<CoinTreeRequest>,
+) -> Result<Json<CoinTreeResponse>, AppError> {
+    if query.depth > MAX_TREE_DEPTH {
+        return Err(AppError::InvalidParam(format!(
+            "Depth can be at most {MAX_TREE_DEPTH}"
+        )));
+    }
+
+    app.read(move |app| {
+        app.require_index(IndexKind::ParentCoinId)?;
+
+        let Some(root) = Coin::load(&app.db, coin_id)? else {
+            return Err(AppError::NotFound(format!("Coin {coin_id} not found")));
+        };
+
+        let max_nodes = app.node_budget(query.max_nodes) as usize;
+
+        let mut coins = HashMap::from([(coin_id, root)]);
+        let mut children = HashMap::<Bytes32, Vec<Bytes32>>::new();
+        let mut generation = vec![coin_id];
+        let mut truncated = false;
+
+        'walk: for level in 0..=query.depth {
+            let mut next_generation = Vec::new();
+
+            for parent_coin_id in generation {
+                let child_ids = app.db.coins_by_parent_coin_id(parent_coin_id)?;
+
+                if child_ids.is_empty() {
+                    continue;
+                }
+
+                if level == query.depth {
+                    truncated = true;
+                    break 'walk;
+                }
+
+                for child_id in child_ids {
+                    if coins.contains_key(&child_id) {
+                        continue;
+                    }
+
+                    if coins.len() >= max_nodes {
+                        truncated = true;
+                        break 'walk;
+                    }
+
+                    let Some(coin) = Coin::load(&app.db, child_id)? else {
+                        continue;
+                    };
+
+                    coins.insert(child_id, coin);
+                    children.entry(parent_coin_id).or_default().push(child_id);
+                    next_generation.push(child_id);
+                }
+            }
+
+            generation = next_generation;
+        }
+
+        let node_count = coins.len();
+
+        Ok(Json(CoinTreeResponse {
+            tree: build_tree(coin_id, &mut coins, &children),
+            node_count,
+            truncated,
+        }))
+    })
+    .await
+}
+
+fn build_tree(
+    coin_id: Bytes32,
+    coins: &mut HashMap<Bytes32, Coin>,
+    children: &HashMap<Bytes32, Vec<Bytes32>>,
+) -> CoinTreeNode {
+    let coin = coins.remove(&coin_id).expect("every walked coin is loaded");
+
+    let children = children
+        .get(&coin_id)
+        .into_iter()
+        .flatten()
+        .map(|&child_id| build_tree(child_id, coins, children))
+        .collect();
+
+    CoinTreeNode { coin, children }
+}
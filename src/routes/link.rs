@@ -0,0 +1,133 @@
+use std::{convert::Infallible, fmt};
+
+use axum::{
+    http::{header::LINK, HeaderValue, Uri},
+    response::{IntoResponseParts, ResponseParts},
+};
+use itertools::Itertools;
+
+use super::base_url;
+
+/// A `Link` header with `next` and `prev` URLs built from the current request
+/// URI, replacing the query parameters that select the page. The URLs are
+/// relative unless `public_base_url` is configured.
+pub struct LinkHeader {
+    uri: Uri,
+    links: Vec<String>,
+}
+
+impl LinkHeader {
+    pub fn new(uri: Uri) -> Self {
+        Self {
+            uri,
+            links: Vec::new(),
+        }
+    }
+
+    pub fn next(self, params: &[(&str, String)]) -> Self {
+        self.link("next", params)
+    }
+
+    pub fn prev(self, params: &[(&str, String)]) -> Self {
+        self.link("prev", params)
+    }
+
+    /// Links to the next page of a cursor paginated list, if there is one.
+    pub fn next_cursor(self, cursor: Option<impl fmt::Display>) -> Self {
+        match cursor {
+            Some(cursor) => self.next(&[("cursor", cursor.to_string())]),
+            None => self,
+        }
+    }
+
+    fn link(mut self, rel: &str, params: &[(&str, String)]) -> Self {
+        let query = self
+            .uri
+            .query()
+            .unwrap_or_default()
+            .split('&')
+            .filter(|pair| {
+                let key = pair.split('=').next().unwrap_or_default();
+                !pair.is_empty() && params.iter().all(|(name, _)| *name != key)
+            })
+            .map(str::to_string)
+            .chain(params.iter().map(|(name, value)| format!("{name}={value}")))
+            .join("&");
+
+        self.links.push(format!(
+            "<{}{}?{}>; rel=\"{}\"",
+            base_url(),
+            self.uri.path(),
+            query,
+            rel
+        ));
+
+        self
+    }
+}
+
+impl IntoResponseParts for LinkHeader {
+    type Error = Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        if self.links.is_empty() {
+            return Ok(res);
+        }
+
+        if let Ok(value) = HeaderValue::from_str(&self.links.join(", ")) {
+            res.headers_mut().insert(LINK, value);
+        }
+
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::response::IntoResponse;
+
+    use super::*;
+
+    fn link(header: LinkHeader) -> Option<String> {
+        (header, ())
+            .into_response()
+            .headers()
+            .get(LINK)
+            .map(|value| value.to_str().unwrap().to_string())
+    }
+
+    #[test]
+    fn replaces_the_page_params_and_keeps_the_rest() {
+        let uri = Uri::from_static("/blocks?limit=10&start=5&reverse=true");
+        let header = LinkHeader::new(uri)
+            .next(&[("start", "15".to_string())])
+            .prev(&[("start", "0".to_string())]);
+
+        assert_eq!(
+            link(header).unwrap(),
+            "</blocks?limit=10&reverse=true&start=15>; rel=\"next\", \
+             </blocks?limit=10&reverse=true&start=0>; rel=\"prev\""
+        );
+    }
+
+    #[test]
+    fn links_the_next_cursor_only_when_there_is_one() {
+        let uri = Uri::from_static("/coins/hint/ab?cursor=cd");
+
+        assert_eq!(
+            link(LinkHeader::new(uri.clone()).next_cursor(Some("ef"))).unwrap(),
+            "</coins/hint/ab?cursor=ef>; rel=\"next\""
+        );
+        assert_eq!(link(LinkHeader::new(uri).next_cursor(None::<String>)), None);
+    }
+
+    #[test]
+    fn links_without_a_query_still_get_the_params() {
+        let header = LinkHeader::new(Uri::from_static("/coins/recent")).next_cursor(Some(5));
+
+        assert_eq!(
+            link(header).unwrap(),
+            "</coins/recent?cursor=5>; rel=\"next\""
+        );
+    }
+}
@@ -0,0 +1,162 @@
+use std::fmt::Write;
+
+use axum::{extract::State, http::header::CONTENT_TYPE, response::IntoResponse};
+
+use super::{App, AppError};
+
+/// How many of the most refused client IPs get their own series.
+const MAX_REJECTED_IPS: usize = 20;
+
+/// Metrics in the Prometheus text exposition format.
+pub async fn metrics(State(app): State<App>) -> Result<impl IntoResponse, AppError> {
+    let peak_height = app.db.peak_height()?;
+    let reorgs = app.reorgs.read().unwrap().clone();
+    let reads = app.db.read_stats();
+    let db_stats = app.db.stats()?;
+
+    let mut body = String::new();
+
+    let mut metric = |name: &str, kind: &str, help: &str, value: u64| {
+        writeln!(body, "# HELP {name} {help}").unwrap();
+        writeln!(body, "# TYPE {name} {kind}").unwrap();
+        writeln!(body, "{name} {value}").unwrap();
+    };
+
+    metric(
+        "postgresy_peak_height",
+        "gauge",
+        "The height of the highest indexed block.",
+        peak_height.map_or(0, u64::from),
+    );
+    metric(
+        "postgresy_reorgs_total",
+        "counter",
+        "Reorgs rolled back since startup.",
+        reorgs.total,
+    );
+    metric(
+        "postgresy_reorg_max_depth",
+        "gauge",
+        "The most blocks rolled back by a single reorg since startup.",
+        reorgs.max_depth.into(),
+    );
+    metric(
+        "postgresy_reorg_last_depth",
+        "gauge",
+        "The number of blocks rolled back by the most recent reorg.",
+        reorgs.recent.front().map_or(0, |reorg| reorg.depth.into()),
+    );
+
+    metric(
+        "postgresy_db_read_limit",
+        "gauge",
+        "The number of range scans that can read from the database at once.",
+        reads.limit as u64,
+    );
+    metric(
+        "postgresy_db_reads_in_flight",
+        "gauge",
+        "Range scans currently reading from the database.",
+        reads.in_flight as u64,
+    );
+    metric(
+        "postgresy_db_reads_waiting",
+        "gauge",
+        "Range scans waiting for a read permit.",
+        reads.waiting as u64,
+    );
+
+    metric(
+        "postgresy_rocksdb_estimated_keys",
+        "gauge",
+        "RocksDB's estimate of the number of keys across column families.",
+        db_stats.estimated_keys,
+    );
+    metric(
+        "postgresy_rocksdb_memtable_bytes",
+        "gauge",
+        "The size of the active and unflushed immutable memtables.",
+        db_stats.memtable_bytes,
+    );
+    metric(
+        "postgresy_rocksdb_live_sst_bytes",
+        "gauge",
+        "The size of the SST files in the current version of the database.",
+        db_stats.live_sst_bytes,
+    );
+    metric(
+        "postgresy_rocksdb_sst_files",
+        "gauge",
+        "The number of SST files across every level.",
+        db_stats.sst_files,
+    );
+    metric(
+        "postgresy_rocksdb_pending_compaction_bytes",
+        "gauge",
+        "RocksDB's estimate of the bytes compaction still has to rewrite.",
+        db_stats.pending_compaction_bytes,
+    );
+    metric(
+        "postgresy_rocksdb_running_compactions",
+        "gauge",
+        "Compactions currently running.",
+        db_stats.running_compactions,
+    );
+
+    metric(
+        "postgresy_single_flight_entries",
+        "gauge",
+        "Block and coin lookups currently tracked for sharing.",
+        app.flights.entries() as u64,
+    );
+    metric(
+        "postgresy_single_flight_fetches_total",
+        "counter",
+        "Block and coin lookups that read from the database.",
+        app.flights.fetches(),
+    );
+    metric(
+        "postgresy_single_flight_shared_total",
+        "counter",
+        "Block and coin lookups answered by a concurrent identical lookup.",
+        app.flights.shared(),
+    );
+
+    metric(
+        "postgresy_ws_subscribers",
+        "gauge",
+        "WebSocket clients currently subscribed to events.",
+        app.events.receiver_count() as u64,
+    );
+
+    let max_requests_per_ip = app.config.max_requests_per_ip;
+
+    if max_requests_per_ip > 0 {
+        metric(
+            "postgresy_ip_saturated",
+            "gauge",
+            "Client IPs currently at their limit of requests in flight.",
+            app.ip_limiter.saturated(max_requests_per_ip) as u64,
+        );
+        metric(
+            "postgresy_ip_rejections_total",
+            "counter",
+            "Requests refused because their IP had too many in flight.",
+            app.ip_limiter.rejections_total(),
+        );
+
+        let name = "postgresy_ip_rejections_by_ip_total";
+        writeln!(
+            body,
+            "# HELP {name} Requests refused per client IP, for the IPs refused most."
+        )
+        .unwrap();
+        writeln!(body, "# TYPE {name} counter").unwrap();
+
+        for (ip, count) in app.ip_limiter.top_rejections(MAX_REJECTED_IPS) {
+            writeln!(body, "{name}{{ip=\"{ip}\"}} {count}").unwrap();
+        }
+    }
+
+    Ok(([(CONTENT_TYPE, "text/plain; version=0.0.4")], body))
+}
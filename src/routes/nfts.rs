@@ -0,0 +1,56 @@
+use axum::extract::State;
+use chia::protocol::Bytes32;
+use serde::{Deserialize, Serialize};
+
+use crate::clvm::{nft_owner as parse_nft_owner, MAX_SIMULATION_COST};
+
+use super::{singleton_tip, App, AppError, Json, NftId, Path, Query};
+
+#[derive(Deserialize)]
+pub struct NftOwnerRequest {
+    #[serde(default)]
+    pub max_nodes: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct NftOwnerResponse {
+    pub launcher_id: Bytes32,
+    /// The NFT's current coin, which hasn't been spent.
+    pub coin_id: Bytes32,
+    /// The launcher id of the DID that owns the NFT, or `None` if it has no
+    /// owner or no ownership layer.
+    pub owner_did: Option<Bytes32>,
+}
+
+/// The DID that owns an NFT, as left by its newest spend. That spend's
+/// transfer, if it made one, decides the owner. Otherwise the owner is the one
+/// curried into the puzzle it revealed.
+pub async fn nft_owner(
+    State(app): State<App>,
+    Path(NftId(launcher_id)): Path<NftId>,
+    Query(query): Query<NftOwnerRequest>,
+) -> Result<Json<NftOwnerResponse>, AppError> {
+    app.read(move |app| {
+        let tip = singleton_tip(app, "NFT", launcher_id, app.node_budget(query.max_nodes))?;
+
+        // The spend was accepted on chain, so failing to run it again is ours
+        // to report rather than the client's.
+        let Some(owner) = parse_nft_owner(
+            &tip.newest_spend.puzzle_reveal,
+            &tip.newest_spend.solution,
+            MAX_SIMULATION_COST,
+        )?
+        else {
+            return Err(AppError::NotFound(format!(
+                "Singleton {launcher_id} isn't an NFT"
+            )));
+        };
+
+        Ok(Json(NftOwnerResponse {
+            launcher_id,
+            coin_id: tip.coin_id,
+            owner_did: owner.did_id,
+        }))
+    })
+    .await
+}
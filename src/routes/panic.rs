@@ -0,0 +1,19 @@
+use std::any::Any;
+
+use axum::response::{IntoResponse, Response};
+
+use super::AppError;
+
+/// Turns a panic in a handler into the same 500 response as any other
+/// internal error, rather than dropping the connection. Puzzle parsing runs
+/// on untrusted input, so a library bug there shouldn't take a worker down.
+/// The panic message is logged along with the other internal errors.
+pub fn handle_panic(payload: Box<dyn Any + Send + 'static>) -> Response {
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|message| (*message).to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string());
+
+    AppError::Internal(anyhow::anyhow!("Handler panicked: {message}")).into_response()
+}
@@ -0,0 +1,36 @@
+use axum::{
+    extract::{Request, State},
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+use super::{App, FORMAT};
+
+const PEAK_HEIGHT: HeaderName = HeaderName::from_static("x-peak-height");
+const PEAK_HASH: HeaderName = HeaderName::from_static("x-peak-hash");
+
+/// Adds the indexed peak to every response, so clients can track sync progress
+/// without polling `/state`.
+pub async fn peak_headers(State(app): State<App>, request: Request, next: Next) -> Response {
+    let mut response = next.run(request).await;
+
+    let Some((height, header_hash)) = app.sync_status.read().unwrap().indexed_peak else {
+        return response;
+    };
+
+    let prefix = if FORMAT.try_with(|format| format.hex_prefix).unwrap_or(true) {
+        "0x"
+    } else {
+        ""
+    };
+
+    let headers = response.headers_mut();
+    headers.insert(PEAK_HEIGHT, HeaderValue::from(height));
+
+    if let Ok(value) = HeaderValue::from_str(&format!("{prefix}{header_hash}")) {
+        headers.insert(PEAK_HASH, value);
+    }
+
+    response
+}
@@ -0,0 +1,102 @@
+use std::convert::Infallible;
+
+use axum::{
+    extract::FromRequestParts,
+    http::{header::VARY, request::Parts, HeaderName, HeaderValue},
+    response::{IntoResponseParts, ResponseParts},
+};
+
+const PREFER: HeaderName = HeaderName::from_static("prefer");
+const PREFERENCE_APPLIED: HeaderName = HeaderName::from_static("preference-applied");
+
+/// Whether the request sent `Prefer: return=minimal`, which turns the optional
+/// heavy fields off by default. Explicit query flags still take precedence.
+///
+/// As a response part, it echoes the preference in `Preference-Applied`. The
+/// response depends on the header either way, so caches are told with `Vary`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Prefer {
+    pub minimal: bool,
+}
+
+impl<S> FromRequestParts<S> for Prefer
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let minimal = parts
+            .headers
+            .get_all(PREFER)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|preference| preference.trim().eq_ignore_ascii_case("return=minimal"));
+
+        Ok(Self { minimal })
+    }
+}
+
+impl IntoResponseParts for Prefer {
+    type Error = Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        res.headers_mut()
+            .append(VARY, HeaderValue::from_static("prefer"));
+
+        if self.minimal {
+            res.headers_mut().insert(
+                PREFERENCE_APPLIED,
+                HeaderValue::from_static("return=minimal"),
+            );
+        }
+
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request};
+
+    use crate::db::{insert_test_chain, Database};
+    use crate::routes::{test_get, test_router, test_send};
+
+    use super::*;
+
+    /// Whether any `Vary` header lists `prefer`. The CORS layer adds its own.
+    fn varies_on_prefer(headers: &axum::http::HeaderMap) -> bool {
+        headers
+            .get_all(VARY)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|name| name.trim().eq_ignore_ascii_case("prefer"))
+    }
+
+    #[tokio::test]
+    async fn negotiated_responses_vary_on_prefer() {
+        let db = Database::open_temp().unwrap();
+        let coin_ids = insert_test_chain(&db, 1, 1, Default::default()).unwrap();
+
+        let router = test_router(&db);
+        let uri = format!("/coins/id/{}", hex::encode(coin_ids[0]));
+
+        let response = test_get(&router, &uri).await;
+        assert!(varies_on_prefer(&response.headers));
+        assert!(response.headers.get(PREFERENCE_APPLIED).is_none());
+
+        let request = Request::get(&uri)
+            .header(PREFER, "return=minimal")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = test_send(&router, request).await;
+        assert!(varies_on_prefer(&response.headers));
+        assert_eq!(
+            response.headers.get(PREFERENCE_APPLIED).unwrap(),
+            "return=minimal"
+        );
+    }
+}
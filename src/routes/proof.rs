@@ -0,0 +1,147 @@
+use anyhow::anyhow;
+use axum::extract::State;
+use chia::protocol::{self, Bytes32};
+use serde::Serialize;
+
+use crate::merkle::{additions_proof, removals_proof, AdditionsProof, RemovalsProof};
+
+use super::{App, AppError, CoinId, Json, Path};
+
+#[derive(Serialize)]
+pub struct CoinProofResponse {
+    pub created_height: u32,
+    pub additions: AdditionsProof,
+    pub spent_height: Option<u32>,
+    pub removals: Option<RemovalsProof>,
+}
+
+pub async fn coin_proof(
+    State(app): State<App>,
+    Path(CoinId(coin_id)): Path<CoinId>,
+) -> Result<Json<CoinProofResponse>, AppError> {
+    app.read(move |app| {
+        let Some(coin) = app.db.coin(coin_id)? else {
+            return Err(AppError::NotFound(format!("Coin {coin_id} not found")));
+        };
+
+        let additions_root = block_roots(&app, coin.created_height)?.0;
+
+        let mut additions = Vec::new();
+
+        for coin_id in app.db.coins_by_created_height(coin.created_height)? {
+            if let Some(row) = app.db.coin(coin_id)? {
+                additions.push(protocol::Coin::new(
+                    row.parent_coin_id,
+                    row.puzzle_hash,
+                    row.amount,
+                ));
+            }
+        }
+
+        let Some(additions) = additions_proof(&additions, coin.puzzle_hash) else {
+            return Err(AppError::Internal(anyhow!(
+                "Coin {coin_id} is missing from the additions at height {}",
+                coin.created_height
+            )));
+        };
+
+        if additions.root != additions_root {
+            return Err(AppError::Internal(anyhow!(
+                "Additions root mismatch at height {}",
+                coin.created_height
+            )));
+        }
+
+        let spent_height = app.db.coin_spend(coin_id)?.map(|spend| spend.spent_height);
+
+        let removals = spent_height
+            .map(|spent_height| spend_proof(app, coin_id, spent_height))
+            .transpose()?;
+
+        Ok(Json(CoinProofResponse {
+            created_height: coin.created_height,
+            additions,
+            spent_height,
+            removals,
+        }))
+    })
+    .await
+}
+
+#[derive(Serialize)]
+pub struct SpendProofResponse {
+    pub spent_height: u32,
+    pub removals: RemovalsProof,
+}
+
+/// Just the spend half of `coin_proof`: the coin's path in the removals Merkle
+/// set of the block it was spent in, checked against that block's removals
+/// root before it's returned. The removals set only commits to coin ids, so
+/// the spend's solution is tied to the block by its generator instead.
+pub async fn coin_spend_proof(
+    State(app): State<App>,
+    Path(CoinId(coin_id)): Path<CoinId>,
+) -> Result<Json<SpendProofResponse>, AppError> {
+    app.read(move |app| {
+        let Some(spend) = app.db.coin_spend(coin_id)? else {
+            return Err(AppError::NotFound(if app.db.coin(coin_id)?.is_some() {
+                format!("Coin {coin_id} has not been spent")
+            } else {
+                format!("Coin {coin_id} not found")
+            }));
+        };
+
+        Ok(Json(SpendProofResponse {
+            spent_height: spend.spent_height,
+            removals: spend_proof(app, coin_id, spend.spent_height)?,
+        }))
+    })
+    .await
+}
+
+/// The removals set is rebuilt from the spent height index rather than stored,
+/// so it's only returned if it hashes to the root recorded for the block.
+fn spend_proof(app: &App, coin_id: Bytes32, spent_height: u32) -> Result<RemovalsProof, AppError> {
+    let removals_root = block_roots(app, spent_height)?.1;
+
+    let removals = app.db.coins_by_spent_height(spent_height)?;
+
+    let Some(removals) = removals_proof(&removals, coin_id) else {
+        return Err(AppError::Internal(anyhow!(
+            "Coin {coin_id} is missing from the removals at height {spent_height}"
+        )));
+    };
+
+    if removals.root != removals_root {
+        return Err(AppError::Internal(anyhow!(
+            "Removals root mismatch at height {spent_height}"
+        )));
+    }
+
+    Ok(removals)
+}
+
+/// Blocks indexed before the Merkle roots were stored can't be proven against.
+fn block_roots(app: &App, height: u32) -> Result<(Bytes32, Bytes32), AppError> {
+    let Some(block) = app.db.block(height)? else {
+        return Err(AppError::NotFound(format!("Block {height} not found")));
+    };
+
+    let not_implemented = || {
+        AppError::NotImplemented(format!(
+            "Block {height} was indexed without its Merkle roots"
+        ))
+    };
+
+    let Some(transaction_info) = block.transaction_info else {
+        return Err(not_implemented());
+    };
+
+    match (
+        transaction_info.additions_root,
+        transaction_info.removals_root,
+    ) {
+        (Some(additions_root), Some(removals_root)) => Ok((additions_root, removals_root)),
+        _ => Err(not_implemented()),
+    }
+}
@@ -0,0 +1,103 @@
+use axum::extract::State;
+use chia::protocol::Bytes32;
+use serde::Serialize;
+
+use super::{App, AppError, Block, BlocksResponse, Coin, CoinsResponse, Json, Path};
+
+const MIN_PREFIX_LEN: usize = 6;
+const PREFIX_SEARCH_LIMIT: usize = 10;
+
+fn parse_hash_prefix(prefix: &str) -> Result<Vec<u8>, AppError> {
+    let prefix = hex::decode(prefix.strip_prefix("0x").unwrap_or(prefix))
+        .map_err(|_| AppError::InvalidParam("Prefix must be hex encoded".to_string()))?;
+
+    if !(MIN_PREFIX_LEN..=32).contains(&prefix.len()) {
+        return Err(AppError::InvalidParam(format!(
+            "Prefix must be between {MIN_PREFIX_LEN} and 32 bytes"
+        )));
+    }
+
+    Ok(prefix)
+}
+
+pub async fn blocks_by_prefix(
+    State(app): State<App>,
+    Path(prefix): Path<String>,
+) -> Result<Json<BlocksResponse>, AppError> {
+    app.read(move |app| {
+        let prefix = parse_hash_prefix(&prefix)?;
+
+        let hashes = app
+            .db
+            .block_hashes_by_prefix(&prefix, PREFIX_SEARCH_LIMIT)?;
+
+        let mut blocks = Vec::new();
+
+        for (_, height) in hashes {
+            if let Some(row) = app.db.block(height)? {
+                blocks.push(Block { height, row });
+            }
+        }
+
+        Ok(Json(BlocksResponse {
+            blocks,
+            has_more: None,
+        }))
+    })
+    .await
+}
+
+pub async fn coins_by_prefix(
+    State(app): State<App>,
+    Path(prefix): Path<String>,
+) -> Result<Json<CoinsResponse>, AppError> {
+    app.read(move |app| {
+        let prefix = parse_hash_prefix(&prefix)?;
+
+        let coin_ids = app.db.coin_ids_by_prefix(&prefix, PREFIX_SEARCH_LIMIT)?;
+
+        Ok(Json(CoinsResponse {
+            coins: Coin::load_many(&app.db, coin_ids)?,
+        }))
+    })
+    .await
+}
+
+const MIN_NAME_QUERY_LEN: usize = 3;
+
+#[derive(Serialize)]
+pub struct NameMatch {
+    pub launcher_id: Bytes32,
+    pub name: String,
+}
+
+#[derive(Serialize)]
+pub struct NameSearchResponse {
+    pub singletons: Vec<NameMatch>,
+}
+
+/// NFTs and DIDs whose on-chain metadata has a `name` starting with the query,
+/// ignoring case. Names that only appear in off-chain metadata aren't indexed,
+/// and NFT1 metadata has no `name` key, so matches are mostly DIDs.
+pub async fn singletons_by_name(
+    State(app): State<App>,
+    Path(query): Path<String>,
+) -> Result<Json<NameSearchResponse>, AppError> {
+    if query.trim().chars().count() < MIN_NAME_QUERY_LEN {
+        return Err(AppError::InvalidParam(format!(
+            "Query must be at least {MIN_NAME_QUERY_LEN} characters"
+        )));
+    }
+
+    app.read(move |app| {
+        let singletons = app
+            .db
+            .singletons_by_name(&query, PREFIX_SEARCH_LIMIT)?
+            .into_iter()
+            .map(|(launcher_id, name)| NameMatch { launcher_id, name })
+            .collect();
+
+        Ok(Json(NameSearchResponse { singletons }))
+    })
+    .await
+}
@@ -0,0 +1,101 @@
+use std::cmp::Reverse;
+
+use axum::extract::State;
+use chia::protocol::Bytes32;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::db::{IndexKind, PuzzleHashOrder};
+
+use super::{App, AppError, Coin, Json};
+
+const MAX_SELECTED_COINS: usize = 500;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionStrategy {
+    /// Fewest coins, which keeps the spend bundle small.
+    #[default]
+    LargestFirst,
+    /// Consolidates dust, at the cost of a larger spend bundle.
+    SmallestFirst,
+}
+
+#[derive(Deserialize)]
+pub struct CoinSelectionRequest {
+    pub puzzle_hash: Bytes32,
+    pub target_amount: u64,
+    #[serde(default)]
+    pub strategy: SelectionStrategy,
+}
+
+#[derive(Serialize)]
+pub struct CoinSelectionResponse {
+    pub coins: Vec<Coin>,
+    pub total: u128,
+    /// False when the unspent coins (or the first `MAX_SELECTED_COINS` of
+    /// them in strategy order) don't add up to the target, in which case
+    /// `coins` is everything that was selected anyway.
+    pub sufficient: bool,
+}
+
+/// Suggests unspent coins of the puzzle hash that add up to at least the
+/// target amount, taking them in strategy order with ties broken by coin id.
+///
+/// This is only advisory. Nothing is reserved, so the coins may be spent (or
+/// reorged out) by the time the client spends them.
+pub async fn select_coins(
+    State(app): State<App>,
+    Json(request): Json<CoinSelectionRequest>,
+) -> Result<Json<CoinSelectionResponse>, AppError> {
+    app.read(move |app| {
+        app.require_index(IndexKind::PuzzleHash)?;
+
+        let coin_ids = app
+            .db
+            .coins_by_puzzle_hash(request.puzzle_hash, PuzzleHashOrder::CoinId)?;
+
+        let mut unspent = Vec::new();
+
+        for (coin_id, row) in coin_ids.iter().copied().zip(app.db.coins(&coin_ids)?) {
+            let Some(row) = row else {
+                continue;
+            };
+
+            if app.db.coin_spent_height(coin_id, &row)?.is_none() {
+                unspent.push((coin_id, row));
+            }
+        }
+
+        let unspent = match request.strategy {
+            SelectionStrategy::LargestFirst => unspent
+                .into_iter()
+                .sorted_by_key(|(coin_id, row)| (Reverse(row.amount), *coin_id))
+                .collect_vec(),
+            SelectionStrategy::SmallestFirst => unspent
+                .into_iter()
+                .sorted_by_key(|(coin_id, row)| (row.amount, *coin_id))
+                .collect_vec(),
+        };
+
+        let target = u128::from(request.target_amount);
+        let mut total = 0;
+        let mut coins = Vec::new();
+
+        for (coin_id, row) in unspent {
+            if total >= target || coins.len() >= MAX_SELECTED_COINS {
+                break;
+            }
+
+            total += u128::from(row.amount);
+            coins.push(Coin::new(&app.db, coin_id, row, None)?);
+        }
+
+        Ok(Json(CoinSelectionResponse {
+            coins,
+            total,
+            sufficient: total >= target,
+        }))
+    })
+    .await
+}
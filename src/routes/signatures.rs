@@ -0,0 +1,34 @@
+use axum::extract::State;
+use serde::Serialize;
+
+use crate::clvm::{agg_sig_conditions, AggSigCondition, MAX_REPLAY_COST};
+
+use super::{App, AppError, CoinId, Json, Path};
+
+#[derive(Serialize)]
+pub struct SignaturesResponse {
+    pub signatures: Vec<AggSigCondition>,
+}
+
+/// The `AGG_SIG_*` conditions output by the coin's spend, which together make
+/// up what the spend bundle's aggregated signature had to cover.
+pub async fn coin_signatures(
+    State(app): State<App>,
+    Path(CoinId(coin_id)): Path<CoinId>,
+) -> Result<Json<SignaturesResponse>, AppError> {
+    app.read(move |app| {
+        let Some(spend) = app.db.coin_spend(coin_id)? else {
+            return Err(AppError::NotFound(if app.db.coin(coin_id)?.is_some() {
+                format!("Coin {coin_id} has not been spent")
+            } else {
+                format!("Coin {coin_id} not found")
+            }));
+        };
+
+        let signatures = agg_sig_conditions(&spend.puzzle_reveal, &spend.solution, MAX_REPLAY_COST)
+            .map_err(|error| AppError::replay(coin_id, error))?;
+
+        Ok(Json(SignaturesResponse { signatures }))
+    })
+    .await
+}
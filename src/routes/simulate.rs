@@ -0,0 +1,67 @@
+use axum::extract::State;
+use chia::protocol::{Bytes, Bytes32};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clvm::{puzzle_hash, simulate, Simulation, MAX_SIMULATION_COST},
+    db::IndexKind,
+};
+
+use super::{App, AppError, Json};
+
+#[derive(Deserialize)]
+pub struct SimulateRequest {
+    pub puzzle_reveal: Bytes,
+    pub solution: Bytes,
+}
+
+pub async fn simulate_spend(
+    Json(request): Json<SimulateRequest>,
+) -> Result<Json<Simulation>, AppError> {
+    simulate(
+        &request.puzzle_reveal,
+        &request.solution,
+        MAX_SIMULATION_COST,
+    )
+    .map(Json)
+    .map_err(|error| AppError::InvalidBody(error.to_string()))
+}
+
+#[derive(Deserialize)]
+pub struct PuzzleHashRequest {
+    pub puzzle_reveal: Bytes,
+}
+
+#[derive(Serialize)]
+pub struct PuzzleRevealHashResponse {
+    pub puzzle_hash: Bytes32,
+    /// Whether any indexed coin has the puzzle hash, left out when the puzzle
+    /// hash index is disabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub used: Option<bool>,
+}
+
+/// The tree hash of the puzzle reveal, for checking it against the puzzle hash
+/// of the coin it's meant to spend.
+pub async fn hash_puzzle(
+    State(app): State<App>,
+    Json(request): Json<PuzzleHashRequest>,
+) -> Result<Json<PuzzleRevealHashResponse>, AppError> {
+    let puzzle_hash = puzzle_hash(&request.puzzle_reveal)
+        .map_err(|error| AppError::InvalidBody(error.to_string()))?;
+
+    app.blocking(move |app| {
+        let used = if app.db.is_index_built(IndexKind::PuzzleHash) {
+            Some(
+                !app.db
+                    .coins_by_puzzle_hash_page(puzzle_hash, None, 1)?
+                    .is_empty(),
+            )
+        } else {
+            None
+        };
+
+        Ok(Json(PuzzleRevealHashResponse { puzzle_hash, used }))
+    })
+    .await
+}
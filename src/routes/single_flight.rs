@@ -0,0 +1,138 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    hash::Hash,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use chia::protocol::Bytes32;
+use tokio::sync::OnceCell;
+
+use crate::db::{BlockRow, CoinRow, CoinSpendRow};
+
+use super::AppError;
+
+/// Lets concurrent requests for the same key share a single database fetch.
+///
+/// Only successful fetches are shared. If the fetch fails (or its request is
+/// dropped), the next waiting request runs its own.
+pub struct SingleFlight<K, V> {
+    in_flight: Mutex<HashMap<K, Arc<OnceCell<Arc<V>>>>>,
+    fetches: AtomicU64,
+    shared: AtomicU64,
+}
+
+impl<K, V> Default for SingleFlight<K, V> {
+    fn default() -> Self {
+        Self {
+            in_flight: Mutex::new(HashMap::new()),
+            fetches: AtomicU64::new(0),
+            shared: AtomicU64::new(0),
+        }
+    }
+}
+
+impl<K, V> SingleFlight<K, V>
+where
+    K: Eq + Hash + Clone,
+{
+    pub async fn run<F, Fut>(&self, key: K, fetch: F) -> Result<Arc<V>, AppError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, AppError>>,
+    {
+        let cell = self
+            .in_flight
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_default()
+            .clone();
+
+        let mut fetched = false;
+
+        let result = cell
+            .get_or_try_init(|| {
+                fetched = true;
+                let future = fetch();
+                async move { future.await.map(Arc::new) }
+            })
+            .await
+            .cloned();
+
+        if fetched {
+            self.fetches.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.shared.fetch_add(1, Ordering::Relaxed);
+        }
+
+        // Later requests should see fresh data, so the entry only lives as
+        // long as its fetch. It may already have been replaced by a new one.
+        let mut in_flight = self.in_flight.lock().unwrap();
+
+        if in_flight
+            .get(&key)
+            .is_some_and(|current| Arc::ptr_eq(current, &cell))
+        {
+            in_flight.remove(&key);
+        }
+
+        result
+    }
+
+    /// Removes the entries that no request is waiting on, which are left behind
+    /// when every request for a key is dropped while its fetch is running.
+    pub fn prune(&self) -> usize {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        let len = in_flight.len();
+        in_flight.retain(|_, cell| Arc::strong_count(cell) > 1);
+        len - in_flight.len()
+    }
+
+    pub fn entries(&self) -> usize {
+        self.in_flight.lock().unwrap().len()
+    }
+
+    pub fn fetches(&self) -> u64 {
+        self.fetches.load(Ordering::Relaxed)
+    }
+
+    pub fn shared(&self) -> u64 {
+        self.shared.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlockKey {
+    Latest { finalized: bool },
+    Height(u32),
+    Hash(Bytes32),
+}
+
+/// The lookups that are hit hardest by identical concurrent requests.
+#[derive(Default)]
+pub struct Flights {
+    pub blocks: SingleFlight<BlockKey, (u32, BlockRow)>,
+    pub coins: SingleFlight<Bytes32, (CoinRow, Option<CoinSpendRow>)>,
+}
+
+impl Flights {
+    pub fn prune(&self) -> usize {
+        self.blocks.prune() + self.coins.prune()
+    }
+
+    pub fn entries(&self) -> usize {
+        self.blocks.entries() + self.coins.entries()
+    }
+
+    pub fn fetches(&self) -> u64 {
+        self.blocks.fetches() + self.coins.fetches()
+    }
+
+    pub fn shared(&self) -> u64 {
+        self.blocks.shared() + self.coins.shared()
+    }
+}
@@ -0,0 +1,31 @@
+use std::time::Instant;
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use tracing::warn;
+
+use super::App;
+
+pub async fn log_slow_requests(State(app): State<App>, request: Request, next: Next) -> Response {
+    let threshold = app.config.slow_request_threshold_ms;
+
+    if threshold == 0 {
+        return next.run(request).await;
+    }
+
+    let method = request.method().clone();
+    let uri = request.uri().clone();
+
+    let start = Instant::now();
+    let response = next.run(request).await;
+    let elapsed = start.elapsed();
+
+    if elapsed.as_millis() >= u128::from(threshold) {
+        warn!("Slow request {} {} took {:?}", method, uri, elapsed);
+    }
+
+    response
+}
@@ -0,0 +1,51 @@
+use axum::extract::State;
+use serde::{Deserialize, Serialize};
+
+use crate::clvm::{disassemble, Disassembly};
+
+use super::{App, AppError, CoinId, Json, Path, Query};
+
+const MAX_DISASSEMBLY_LEN: usize = 64 * 1024;
+
+#[derive(Deserialize)]
+pub struct SolutionTreeRequest {
+    #[serde(default)]
+    pub include_puzzle: bool,
+}
+
+#[derive(Serialize)]
+pub struct SolutionTreeResponse {
+    pub solution: Disassembly,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub puzzle: Option<Disassembly>,
+}
+
+/// The coin's solution (and optionally its puzzle reveal) as CLVM text, each
+/// cut off after `MAX_DISASSEMBLY_LEN` bytes.
+pub async fn solution_tree(
+    State(app): State<App>,
+    Path(CoinId(coin_id)): Path<CoinId>,
+    Query(query): Query<SolutionTreeRequest>,
+) -> Result<Json<SolutionTreeResponse>, AppError> {
+    app.blocking(move |app| {
+        let Some(spend) = app.db.coin_spend(coin_id)? else {
+            return Err(AppError::NotFound(if app.db.coin(coin_id)?.is_some() {
+                format!("Coin {coin_id} has not been spent")
+            } else {
+                format!("Coin {coin_id} not found")
+            }));
+        };
+
+        let puzzle = if query.include_puzzle {
+            Some(disassemble(&spend.puzzle_reveal, MAX_DISASSEMBLY_LEN)?)
+        } else {
+            None
+        };
+
+        Ok(Json(SolutionTreeResponse {
+            solution: disassemble(&spend.solution, MAX_DISASSEMBLY_LEN)?,
+            puzzle,
+        }))
+    })
+    .await
+}
@@ -0,0 +1,189 @@
+use axum::{extract::State, http::Uri};
+use chia::protocol::{Bytes, Bytes32};
+use serde::{Deserialize, Serialize};
+
+use crate::db::CoinSpendRow;
+
+use super::{truncate_to_budget, App, AppError, Coin, Json, LinkHeader, Query};
+
+#[derive(Deserialize)]
+pub struct SpendRangeRequest {
+    pub from: u32,
+    pub to: u32,
+    #[serde(default)]
+    pub limit: Option<u32>,
+    #[serde(default)]
+    pub cursor: Option<Bytes>,
+}
+
+impl SpendRangeRequest {
+    /// A cursor from outside `from..=to` would otherwise return spends outside
+    /// the requested range.
+    fn check(&self) -> Result<(), AppError> {
+        if self.from > self.to {
+            return Err(AppError::InvalidParam(
+                "`from` must not be greater than `to`".to_string(),
+            ));
+        }
+
+        if let Some(cursor) = &self.cursor {
+            let Some(height) = cursor
+                .get(..4)
+                .and_then(|height| height.try_into().ok())
+                .map(u32::from_be_bytes)
+            else {
+                return Err(AppError::InvalidParam("Invalid `cursor`".to_string()));
+            };
+
+            if !(self.from..=self.to).contains(&height) {
+                return Err(AppError::InvalidParam(
+                    "`cursor` is outside of `from..=to`".to_string(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+pub struct SpendRecord {
+    pub coin_id: Bytes32,
+    pub spent_height: u32,
+    pub puzzle_reveal: Bytes,
+    pub solution: Bytes,
+}
+
+impl SpendRecord {
+    pub fn new(coin_id: Bytes32, spend: CoinSpendRow) -> Self {
+        Self {
+            coin_id,
+            spent_height: spend.spent_height,
+            puzzle_reveal: spend.puzzle_reveal,
+            solution: spend.solution,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct SpendRangeResponse {
+    pub spends: Vec<SpendRecord>,
+    pub next_cursor: Option<Bytes>,
+}
+
+/// Spends at heights `from..=to`, ordered by height and then coin id.
+pub async fn spends_by_range(
+    State(app): State<App>,
+    uri: Uri,
+    Query(query): Query<SpendRangeRequest>,
+) -> Result<(LinkHeader, Json<SpendRangeResponse>), AppError> {
+    query.check()?;
+
+    app.read(move |app| {
+        let limit = app.config.spend_pagination.limit(query.limit) as usize;
+
+        let spent = app.db.coins_spent_between_page(
+            query.from,
+            query.to,
+            query.cursor.as_ref().map(AsRef::as_ref),
+            limit,
+        )?;
+
+        let mut next_cursor = if spent.len() >= limit {
+            spent.last().copied()
+        } else {
+            None
+        };
+
+        let coin_ids = spent
+            .iter()
+            .map(|(_, coin_id)| *coin_id)
+            .collect::<Vec<_>>();
+        let spends = app.db.coin_spends(&coin_ids)?;
+
+        let mut spends = coin_ids
+            .into_iter()
+            .zip(spends)
+            .filter_map(|(coin_id, spend)| Some(SpendRecord::new(coin_id, spend?)))
+            .collect::<Vec<_>>();
+
+        // Puzzle reveals can be large enough that a full page doesn't fit.
+        if truncate_to_budget(&mut spends)? {
+            next_cursor = spends
+                .last()
+                .map(|spend| (spend.spent_height, spend.coin_id));
+        }
+
+        let next_cursor = next_cursor.map(|(spent_height, coin_id)| {
+            Bytes::new([&spent_height.to_be_bytes(), coin_id.as_ref()].concat())
+        });
+
+        let links = LinkHeader::new(uri).next_cursor(next_cursor.as_ref());
+
+        Ok((
+            links,
+            Json(SpendRangeResponse {
+                spends,
+                next_cursor,
+            }),
+        ))
+    })
+    .await
+}
+
+#[derive(Serialize)]
+pub struct SpentCoinsResponse {
+    pub coins: Vec<Coin>,
+    pub next_cursor: Option<Bytes>,
+}
+
+/// Coins spent at heights `from..=to`, ordered by spent height and then coin
+/// id, without their puzzle reveals and solutions. Coins created in the range
+/// but still unspent aren't included. The cursor works like `spends_by_range`.
+pub async fn spent_coins_by_range(
+    State(app): State<App>,
+    uri: Uri,
+    Query(query): Query<SpendRangeRequest>,
+) -> Result<(LinkHeader, Json<SpentCoinsResponse>), AppError> {
+    query.check()?;
+
+    app.read(move |app| {
+        let limit = app.config.coin_pagination.limit(query.limit) as usize;
+
+        let spent = app.db.coins_spent_between_page(
+            query.from,
+            query.to,
+            query.cursor.as_ref().map(AsRef::as_ref),
+            limit,
+        )?;
+
+        let mut next_cursor = if spent.len() >= limit {
+            spent.last().copied()
+        } else {
+            None
+        };
+
+        let mut coins = Vec::new();
+
+        for (spent_height, coin_id) in spent {
+            if let Some(row) = app.db.coin(coin_id)? {
+                coins.push(Coin::new(&app.db, coin_id, row, Some(spent_height))?);
+            }
+        }
+
+        if truncate_to_budget(&mut coins)? {
+            next_cursor = coins
+                .last()
+                .and_then(|coin| Some((coin.row.spent_height?, coin.coin_id)));
+        }
+
+        let next_cursor = next_cursor.map(|(spent_height, coin_id)| {
+            Bytes::new([&spent_height.to_be_bytes(), coin_id.as_ref()].concat())
+        });
+
+        let links = LinkHeader::new(uri).next_cursor(next_cursor.as_ref());
+
+        Ok((links, Json(SpentCoinsResponse { coins, next_cursor })))
+    })
+    .await
+}
@@ -0,0 +1,239 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::{
+    extract::State,
+    http::{
+        header::{IF_MODIFIED_SINCE, LAST_MODIFIED},
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+
+use crate::{
+    db::DATA_VERSION,
+    sync::{lag_seconds, IngestionMode, SyncStatus},
+};
+
+use super::{App, AppError, Json};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotSyncedReason {
+    NoBlocks,
+    /// Blocks below the node's peak haven't been indexed yet.
+    CatchingUp,
+    /// The index is caught up, but the latest transaction block is older than
+    /// `max_peak_lag_seconds`, so the node itself is likely behind.
+    ChainStalled,
+}
+
+fn not_synced_reason(
+    app: &App,
+    status: &SyncStatus,
+    height: Option<u32>,
+    peak_timestamp: Option<u64>,
+) -> Option<NotSyncedReason> {
+    let max_lag = app.config.max_peak_lag_seconds;
+
+    if height.is_none() {
+        Some(NotSyncedReason::NoBlocks)
+    } else if !status.synced {
+        Some(NotSyncedReason::CatchingUp)
+    } else if max_lag > 0
+        && peak_timestamp.is_some_and(|timestamp| lag_seconds(timestamp) > max_lag)
+    {
+        Some(NotSyncedReason::ChainStalled)
+    } else {
+        None
+    }
+}
+
+#[derive(Serialize)]
+pub struct StateResponse {
+    pub peak_height: u32,
+    pub synced: bool,
+    /// Why `synced` is false.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<NotSyncedReason>,
+    /// The timestamp of the latest transaction block at or below the peak.
+    pub peak_timestamp: Option<u64>,
+    pub lag_seconds: Option<u64>,
+    pub ingestion_mode: Option<IngestionMode>,
+    /// The same as the `X-Data-Version` header.
+    pub data_version: u32,
+    /// The unix timestamp at which blocks were last committed.
+    pub last_block_ingested_at: Option<u64>,
+    pub node_connected: bool,
+    /// The smoothed ingestion rate over recent batches.
+    pub blocks_per_second: Option<f64>,
+    /// How long indexing the rest of the node's chain should take at that
+    /// rate, which is `0` once the peak is caught up.
+    pub eta_seconds: Option<u64>,
+}
+
+pub async fn state(State(app): State<App>, headers: HeaderMap) -> Result<Response, AppError> {
+    app.blocking(move |app| {
+        let status = *app.sync_status.read().unwrap();
+
+        // HTTP dates only have second precision, so round trip before comparing.
+        let last_modified = status
+            .peak_updated_at
+            .map(httpdate::fmt_http_date)
+            .map(|date| (httpdate::parse_http_date(&date).unwrap(), date));
+
+        if let Some((peak_updated_at, date)) = &last_modified {
+            let if_modified_since = headers
+                .get(IF_MODIFIED_SINCE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| httpdate::parse_http_date(value).ok());
+
+            if if_modified_since.is_some_and(|since: SystemTime| *peak_updated_at <= since) {
+                return Ok((
+                    StatusCode::NOT_MODIFIED,
+                    [(LAST_MODIFIED, HeaderValue::from_str(date).unwrap())],
+                )
+                    .into_response());
+            }
+        }
+
+        let height = app.db.peak_height()?;
+
+        let peak_timestamp = match height {
+            Some(height) => app.db.latest_timestamp(height)?,
+            None => None,
+        };
+
+        let reason = not_synced_reason(app, &status, height, peak_timestamp);
+
+        let mut response = Json(StateResponse {
+            peak_height: height.unwrap_or(0),
+            synced: reason.is_none(),
+            reason,
+            peak_timestamp,
+            lag_seconds: peak_timestamp.map(lag_seconds),
+            ingestion_mode: status.ingestion_mode,
+            data_version: DATA_VERSION,
+            last_block_ingested_at: status.last_block_ingested_at.and_then(|time| {
+                time.duration_since(UNIX_EPOCH)
+                    .ok()
+                    .map(|duration| duration.as_secs())
+            }),
+            node_connected: status.node_connected,
+            blocks_per_second: status.blocks_per_second,
+            eta_seconds: eta_seconds(&status, height),
+        })
+        .into_response();
+
+        if let Some((_, date)) = last_modified {
+            response
+                .headers_mut()
+                .insert(LAST_MODIFIED, HeaderValue::from_str(&date).unwrap());
+        }
+
+        Ok(response)
+    })
+    .await
+}
+
+fn eta_seconds(status: &SyncStatus, height: Option<u32>) -> Option<u64> {
+    let remaining = status.target_height?.saturating_sub(height.unwrap_or(0));
+
+    if remaining == 0 {
+        return Some(0);
+    }
+
+    let blocks_per_second = status.blocks_per_second.filter(|rate| *rate > 0.0)?;
+
+    Some((f64::from(remaining) / blocks_per_second).ceil() as u64)
+}
+
+/// The peak is only sent in the `X-Peak-Height` and `X-Peak-Hash` headers,
+/// so nothing is read from the database.
+pub async fn state_head() -> StatusCode {
+    StatusCode::OK
+}
+
+#[derive(Serialize)]
+pub struct ReadyResponse {
+    pub ready: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<NotSyncedReason>,
+}
+
+/// A readiness probe that fails with a 503 unless `/state` would report the
+/// index as synced.
+pub async fn ready(State(app): State<App>) -> Result<(StatusCode, Json<ReadyResponse>), AppError> {
+    app.blocking(move |app| {
+        let status = *app.sync_status.read().unwrap();
+        let height = app.db.peak_height()?;
+
+        let peak_timestamp = match height {
+            Some(height) => app.db.latest_timestamp(height)?,
+            None => None,
+        };
+
+        let reason = not_synced_reason(app, &status, height, peak_timestamp);
+
+        Ok((
+            if reason.is_none() {
+                StatusCode::OK
+            } else {
+                StatusCode::SERVICE_UNAVAILABLE
+            },
+            Json(ReadyResponse {
+                ready: reason.is_none(),
+                reason,
+            }),
+        ))
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::db::Database;
+    use crate::routes::{test_get, test_router};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn empty_database_has_a_consistent_contract() {
+        let db = Database::open_temp().unwrap();
+        let router = test_router(&db);
+
+        let response = test_get(&router, "/state").await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body["peak_height"], 0);
+        assert_eq!(response.body["synced"], false);
+
+        for uri in [
+            "/blocks",
+            "/blocks?reverse=true",
+            format!("/coins/hint/{}", hex::encode([1; 32])).as_str(),
+        ] {
+            let response = test_get(&router, uri).await;
+            assert_eq!(response.status, StatusCode::OK, "{uri}");
+
+            let list = response
+                .body
+                .get("blocks")
+                .or(response.body.get("coins"))
+                .unwrap();
+            assert_eq!(list, &json!([]), "{uri}");
+        }
+
+        for uri in [
+            "/blocks/latest",
+            "/blocks/height/0",
+            format!("/blocks/hash/{}", hex::encode([1; 32])).as_str(),
+            format!("/coins/id/{}", hex::encode([1; 32])).as_str(),
+        ] {
+            let response = test_get(&router, uri).await;
+            assert_eq!(response.status, StatusCode::NOT_FOUND, "{uri}");
+            assert_eq!(response.body["code"], "not_found", "{uri}");
+        }
+    }
+}
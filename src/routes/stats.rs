@@ -0,0 +1,428 @@
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use axum::{extract::State, http::Uri};
+use chia::protocol::{Bytes, Bytes32};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::{db::IndexKind, sync::ReorgRecord};
+
+use super::{amount_cursor, App, AppError, Json, LinkHeader, Query};
+
+const MAX_SERIES_BUCKETS: u32 = 1000;
+const MAX_DUST_THRESHOLD: u64 = 1_000_000_000;
+const DUST_BATCH: usize = 1000;
+const MAX_PUZZLE_USAGE_BLOCKS: u32 = 10_000;
+const MAX_DIFFICULTY_WINDOW: u32 = 10_000;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Bucket {
+    Minute,
+    #[default]
+    Hour,
+    Day,
+}
+
+impl Bucket {
+    fn seconds(self) -> u64 {
+        match self {
+            Self::Minute => 60,
+            Self::Hour => 60 * 60,
+            Self::Day => 24 * 60 * 60,
+        }
+    }
+}
+
+fn default_window() -> u32 {
+    24
+}
+
+fn default_difficulty_window() -> u32 {
+    100
+}
+
+#[derive(Deserialize)]
+pub struct TxSeriesRequest {
+    #[serde(default)]
+    pub bucket: Bucket,
+    #[serde(default = "default_window")]
+    pub window: u32,
+}
+
+#[derive(Serialize)]
+pub struct TxSeriesBucket {
+    pub bucket_start_ts: u64,
+    pub tx_count: u64,
+}
+
+#[derive(Serialize)]
+pub struct TxSeriesResponse {
+    pub series: Vec<TxSeriesBucket>,
+}
+
+/// Transactions per bucket over the last `window` buckets, oldest first, where
+/// each coin spend counts as a transaction. The newest bucket is the one
+/// containing the current time, so it's usually partial.
+pub async fn tx_series(
+    State(app): State<App>,
+    Query(query): Query<TxSeriesRequest>,
+) -> Result<Json<TxSeriesResponse>, AppError> {
+    app.read(move |app| {
+        if !(1..=MAX_SERIES_BUCKETS).contains(&query.window) {
+            return Err(AppError::InvalidParam(format!(
+                "Window must be between 1 and {MAX_SERIES_BUCKETS} buckets"
+            )));
+        }
+
+        let size = query.bucket.seconds();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(anyhow::Error::from)?
+            .as_secs();
+        let start = (now / size).saturating_sub(u64::from(query.window) - 1) * size;
+
+        let mut series = (0..u64::from(query.window))
+            .map(|index| TxSeriesBucket {
+                bucket_start_ts: start + index * size,
+                tx_count: 0,
+            })
+            .collect::<Vec<_>>();
+
+        for (_, transaction_info) in app.db.transaction_blocks_since(start)? {
+            let index = ((transaction_info.timestamp - start) / size) as usize;
+
+            // Block timestamps can be slightly ahead of the server clock.
+            if let Some(bucket) = series.get_mut(index) {
+                bucket.tx_count += u64::from(transaction_info.removals);
+            }
+        }
+
+        Ok(Json(TxSeriesResponse { series }))
+    })
+    .await
+}
+
+#[derive(Serialize)]
+pub struct ReorgStatsResponse {
+    pub total: u64,
+    pub max_depth: u32,
+    /// The most recent reorgs since startup, newest first.
+    pub reorgs: Vec<ReorgRecord>,
+}
+
+pub async fn reorg_stats(State(app): State<App>) -> Json<ReorgStatsResponse> {
+    let reorgs = app.reorgs.read().unwrap();
+
+    Json(ReorgStatsResponse {
+        total: reorgs.total,
+        max_depth: reorgs.max_depth,
+        reorgs: reorgs.recent.iter().copied().collect(),
+    })
+}
+
+/// Amount index entries read by one request to `dust_stats`.
+const MAX_DUST_SCAN: usize = 100_000;
+
+#[derive(Deserialize)]
+pub struct DustRequest {
+    pub threshold: u64,
+    #[serde(default)]
+    pub cursor: Option<Bytes>,
+}
+
+#[derive(Serialize)]
+pub struct DustResponse {
+    pub dust_count: usize,
+    pub dust_value: u128,
+    pub threshold: u64,
+    /// Where the next request carries on from, if the scan stopped early. The
+    /// counts only cover this request's part of the index.
+    pub next_cursor: Option<Bytes>,
+}
+
+/// The number and total value of unspent coins below `threshold` mojos. The
+/// amount index isn't split by asset, so CAT coins are counted alongside XCH.
+///
+/// Spent coins are still read from the index, so at most `MAX_DUST_SCAN`
+/// entries are read per request. The totals are then partial, and adding up
+/// the responses while following `next_cursor` (the hex `amount || coin_id`
+/// key of the last coin read) gives the full ones.
+pub async fn dust_stats(
+    State(app): State<App>,
+    uri: Uri,
+    Query(query): Query<DustRequest>,
+) -> Result<(LinkHeader, Json<DustResponse>), AppError> {
+    if query.threshold > MAX_DUST_THRESHOLD {
+        return Err(AppError::InvalidParam(format!(
+            "Threshold can be at most {MAX_DUST_THRESHOLD} mojos"
+        )));
+    }
+
+    if let Some(cursor) = &query.cursor {
+        let amount = cursor
+            .get(..8)
+            .and_then(|amount| amount.try_into().ok())
+            .map(u64::from_be_bytes);
+
+        if amount.is_none_or(|amount| amount >= query.threshold) {
+            return Err(AppError::InvalidParam("Invalid `cursor`".to_string()));
+        }
+    }
+
+    app.read(move |app| {
+        app.require_index(IndexKind::Amount)?;
+
+        let mut response = DustResponse {
+            dust_count: 0,
+            dust_value: 0,
+            threshold: query.threshold,
+            next_cursor: None,
+        };
+
+        let Some(max) = query.threshold.checked_sub(1) else {
+            return Ok((LinkHeader::new(uri), Json(response)));
+        };
+
+        let mut cursor = query.cursor;
+        let mut scanned = 0;
+
+        while scanned < MAX_DUST_SCAN {
+            let batch = DUST_BATCH.min(MAX_DUST_SCAN - scanned);
+            let page =
+                app.db
+                    .coins_by_amount_range(0, max, cursor.as_ref().map(AsRef::as_ref), batch)?;
+            let exhausted = page.len() < batch;
+
+            let coin_ids = page.iter().map(|&(_, coin_id)| coin_id).collect_vec();
+            let rows = app.db.coins(&coin_ids)?;
+            let spent_heights = app.db.coin_spent_heights(&coin_ids, &rows)?;
+
+            for ((&(amount, _), row), spent_height) in page.iter().zip(rows).zip(spent_heights) {
+                if row.is_some() && spent_height.is_none() {
+                    response.dust_count += 1;
+                    response.dust_value += u128::from(amount);
+                }
+            }
+
+            scanned += page.len();
+            cursor = page
+                .last()
+                .map(|&(amount, coin_id)| amount_cursor(amount, coin_id));
+
+            if exhausted {
+                cursor = None;
+                break;
+            }
+        }
+
+        response.next_cursor = cursor;
+
+        let links = LinkHeader::new(uri).next_cursor(response.next_cursor.as_ref());
+
+        Ok((links, Json(response)))
+    })
+    .await
+}
+
+#[derive(Serialize)]
+pub struct SupplyResponse {
+    pub issued: u128,
+    pub burned: u128,
+    pub net: i128,
+}
+
+/// Running totals kept in step with ingestion and rollbacks, so this doesn't
+/// scan anything. `issued` is the value of every reward coin, which includes
+/// the fees paid to farmers, and `burned` is the sum of all block fees.
+pub async fn supply_stats(State(app): State<App>) -> Result<Json<SupplyResponse>, AppError> {
+    app.blocking(move |app| {
+        let Some(supply) = app.db.supply()? else {
+            return Err(AppError::NotImplemented(
+                "Supply totals haven't been backfilled, run `backfill-supply` first".to_string(),
+            ));
+        };
+
+        Ok(Json(SupplyResponse {
+            issued: supply.issued,
+            burned: supply.burned,
+            net: supply.issued as i128 - supply.burned as i128,
+        }))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+pub struct PuzzleUsageRequest {
+    pub from: u32,
+    pub to: u32,
+}
+
+#[derive(Serialize)]
+pub struct PuzzleUsage {
+    pub mod_hash: Bytes32,
+    pub spend_count: usize,
+}
+
+#[derive(Serialize)]
+pub struct PuzzleUsageResponse {
+    pub puzzle_usage: Vec<PuzzleUsage>,
+    /// Spends in the range that were indexed before mod hashes were recorded.
+    pub unknown_count: usize,
+}
+
+/// Spends at heights `from..=to` grouped by the mod hash of their outermost
+/// puzzle layer, most spent first.
+pub async fn puzzle_usage(
+    State(app): State<App>,
+    Query(query): Query<PuzzleUsageRequest>,
+) -> Result<Json<PuzzleUsageResponse>, AppError> {
+    app.read(move |app| {
+        if query.from > query.to {
+            return Err(AppError::InvalidParam(
+                "`from` must not be greater than `to`".to_string(),
+            ));
+        }
+
+        if query.to - query.from >= MAX_PUZZLE_USAGE_BLOCKS {
+            return Err(AppError::InvalidParam(format!(
+                "At most {MAX_PUZZLE_USAGE_BLOCKS} blocks can be requested at once"
+            )));
+        }
+
+        let coin_ids = app
+            .db
+            .coins_spent_between(query.from, query.to)?
+            .into_iter()
+            .map(|(_, coin_id)| coin_id)
+            .collect::<Vec<_>>();
+
+        let mut counts = HashMap::new();
+        let mut unknown_count = 0;
+
+        for chunk in coin_ids.chunks(1000) {
+            for spend in app.db.coin_spends(chunk)?.into_iter().flatten() {
+                match spend.mod_hash {
+                    Some(mod_hash) => *counts.entry(mod_hash).or_insert(0) += 1,
+                    None => unknown_count += 1,
+                }
+            }
+        }
+
+        let puzzle_usage = counts
+            .into_iter()
+            .map(|(mod_hash, spend_count)| PuzzleUsage {
+                mod_hash,
+                spend_count,
+            })
+            .sorted_by_key(|usage| (Reverse(usage.spend_count), usage.mod_hash))
+            .collect();
+
+        Ok(Json(PuzzleUsageResponse {
+            puzzle_usage,
+            unknown_count,
+        }))
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+pub struct DifficultyRequest {
+    #[serde(default = "default_difficulty_window")]
+    pub window: u32,
+}
+
+#[derive(Serialize)]
+pub struct BlockDifficulty {
+    pub height: u32,
+    pub weight: u128,
+    /// The weight the block added over its parent.
+    pub difficulty: u128,
+}
+
+#[derive(Serialize)]
+pub struct DifficultyResponse {
+    pub blocks: Vec<BlockDifficulty>,
+}
+
+/// The difficulty of the last `window` blocks up to the peak, oldest first.
+/// Blocks only record their cumulative weight, so each difficulty is the
+/// difference from the block before it, and a block whose parent isn't
+/// indexed is left out.
+pub async fn difficulty_stats(
+    State(app): State<App>,
+    Query(query): Query<DifficultyRequest>,
+) -> Result<Json<DifficultyResponse>, AppError> {
+    app.read(move |app| {
+        if !(1..=MAX_DIFFICULTY_WINDOW).contains(&query.window) {
+            return Err(AppError::InvalidParam(format!(
+                "Window must be between 1 and {MAX_DIFFICULTY_WINDOW} blocks"
+            )));
+        }
+
+        let Some(peak_height) = app.db.peak_height()? else {
+            return Ok(Json(DifficultyResponse { blocks: Vec::new() }));
+        };
+
+        let heights = (peak_height.saturating_sub(query.window)..=peak_height).collect_vec();
+        let rows = app.db.blocks(&heights)?;
+
+        let blocks = heights
+            .into_iter()
+            .zip(rows)
+            .tuple_windows()
+            .filter_map(|((_, parent), (height, block))| {
+                let (parent, block) = (parent?, block?);
+
+                Some(BlockDifficulty {
+                    height,
+                    weight: block.weight,
+                    difficulty: block.weight.saturating_sub(parent.weight),
+                })
+            })
+            .collect();
+
+        Ok(Json(DifficultyResponse { blocks }))
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use serde_json::json;
+
+    use crate::db::{insert_test_chain, Database};
+    use crate::routes::{test_get, test_router};
+
+    #[tokio::test]
+    async fn dust_counts_unspent_coins_below_the_threshold() {
+        let db = Database::open_temp().unwrap();
+        insert_test_chain(&db, 2, 2, Default::default()).unwrap();
+        let router = test_router(&db);
+
+        let response = test_get(&router, "/stats/dust?threshold=1001").await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body["dust_count"], 4);
+        assert_eq!(response.body["dust_value"], 4000);
+        assert_eq!(response.body["next_cursor"], json!(null));
+
+        let response = test_get(&router, "/stats/dust?threshold=1000").await;
+        assert_eq!(response.body["dust_count"], 0);
+
+        let response = test_get(&router, "/stats/dust?threshold=0").await;
+        assert_eq!(response.body["dust_count"], 0);
+
+        let cursor = hex::encode(1000u64.to_be_bytes());
+        let response = test_get(
+            &router,
+            &format!("/stats/dust?threshold=1000&cursor={cursor}"),
+        )
+        .await;
+        assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    }
+}
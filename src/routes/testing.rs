@@ -0,0 +1,98 @@
+use std::sync::{Arc, RwLock};
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{HeaderMap, Request, StatusCode},
+    Router,
+};
+use serde_json::Value;
+use tokio::sync::broadcast;
+use tower::ServiceExt;
+
+use crate::{config::Config, db::Database};
+
+use super::{router, App, Flights, IpLimiter};
+
+impl App {
+    /// An app over the database that doesn't sync, for exercising handlers.
+    pub fn for_tests(db: Database, config: Config) -> Self {
+        let (events, _) = broadcast::channel(16);
+
+        Self {
+            db,
+            config: Arc::new(config),
+            sync_status: Arc::new(RwLock::new(Default::default())),
+            reorgs: Arc::new(RwLock::new(Default::default())),
+            ingestion_errors: Arc::new(RwLock::new(Default::default())),
+            events,
+            integrity: Arc::new(Default::default()),
+            flights: Arc::new(Flights::default()),
+            sync: None,
+            ip_limiter: Arc::new(IpLimiter::default()),
+        }
+    }
+}
+
+/// The router over the database with the default config.
+pub fn test_router(db: &Database) -> Router {
+    router(App::for_tests(db.clone(), Config::default()))
+}
+
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Value,
+}
+
+/// Sends the request through the router, reading the body as JSON, or as
+/// `null` if it's empty.
+pub async fn test_send(router: &Router, request: Request<Body>) -> TestResponse {
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let headers = response.headers().clone();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+    let body = if bytes.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(&bytes).unwrap()
+    };
+
+    TestResponse {
+        status,
+        headers,
+        body,
+    }
+}
+
+pub async fn test_get(router: &Router, uri: &str) -> TestResponse {
+    test_send(router, Request::get(uri).body(Body::empty()).unwrap()).await
+}
+
+/// A hex field of a response without the `0x` prefix, which depends on the
+/// format settings.
+pub fn test_hex(value: &Value) -> &str {
+    value.as_str().unwrap().trim_start_matches("0x")
+}
+
+mod tests {
+    use crate::db::{insert_test_chain, test_block, Database};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn serves_a_block_from_the_test_chain() {
+        let db = Database::open_temp().unwrap();
+        insert_test_chain(&db, 2, 1, Default::default()).unwrap();
+
+        let router = test_router(&db);
+        let response = test_get(&router, "/blocks/height/1").await;
+
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body["block"]["height"], 1);
+        assert_eq!(
+            test_hex(&response.body["block"]["header_hash"]),
+            hex::encode(test_block(1).header_hash)
+        );
+    }
+}
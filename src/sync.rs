@@ -1,61 +1,354 @@
-use std::collections::HashMap;
-use std::io::Cursor;
-use std::time::Instant;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use anyhow::Result;
-use chia::protocol::Bytes;
-use chia::{protocol::FullBlock, traits::Streamable};
+use anyhow::{bail, Context, Result};
+use chia::protocol::FullBlock;
+use chia::protocol::{Bytes, Bytes32};
 use chia_wallet_sdk::coinset::{ChiaRpcClient, FullNodeClient};
+use rand::Rng;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::Serialize;
 use sqlx::{Row, SqlitePool};
-use tracing::debug;
-use zstd::decode_all;
+use tokio::sync::{mpsc, Mutex};
+use tracing::{debug, error, info};
 
 use crate::config::Config;
-use crate::db::{CoinSpendRow, Database};
-use crate::parse_blocks;
+use crate::db::{CoinSpendRow, Database, Transaction};
+use crate::events::Event;
 use crate::process::{process_blocks, Insertion};
+use crate::{parse_block, parse_blocks};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const RECENT_REORGS: usize = 100;
+const RECENT_INGESTION_ERRORS: usize = 100;
+
+/// How much each batch moves the smoothed ingestion rate, so a single slow or
+/// fast batch doesn't swing the sync ETA.
+const RATE_SMOOTHING: f64 = 0.2;
+
+/// How many seconds the server clock is ahead of a block timestamp.
+pub fn lag_seconds(timestamp: u64) -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs().saturating_sub(timestamp))
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SyncStatus {
+    pub target_height: Option<u32>,
+    pub synced: bool,
+    pub peak_updated_at: Option<SystemTime>,
+    pub ingestion_mode: Option<IngestionMode>,
+    /// When a batch of blocks was last committed, unlike `peak_updated_at`
+    /// which is also set at startup.
+    pub last_block_ingested_at: Option<SystemTime>,
+    /// The height and header hash of the highest indexed block.
+    pub indexed_peak: Option<(u32, Bytes32)>,
+    /// Whether the node's RPC answered the last time its peak was polled.
+    pub node_connected: bool,
+    /// An exponential moving average of the blocks indexed per second over
+    /// recent batches.
+    pub blocks_per_second: Option<f64>,
+}
+
+/// Blocks are always read from the node's database, so there's no push mode.
+/// The node's RPC is only polled for its peak every `POLL_INTERVAL`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IngestionMode {
+    /// More than a batch behind the node's peak, reading blocks in bulk.
+    CatchingUp,
+    /// Following the node's peak as it's polled.
+    Polling,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ReorgRecord {
+    pub fork_height: u32,
+    /// How many indexed blocks were rolled back.
+    pub depth: u32,
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ReorgLog {
+    /// The most recent reorgs, newest first.
+    pub recent: VecDeque<ReorgRecord>,
+    pub total: u64,
+    pub max_depth: u32,
+}
+
+impl ReorgLog {
+    fn record(&mut self, reorg: ReorgRecord) {
+        self.recent.push_front(reorg);
+        self.recent.truncate(RECENT_REORGS);
+        self.total += 1;
+        self.max_depth = self.max_depth.max(reorg.depth);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IngestionError {
+    /// The height that was next to be indexed when the error happened.
+    pub height: u32,
+    pub error: String,
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct IngestionErrorLog {
+    /// The most recent errors, newest first.
+    pub recent: VecDeque<IngestionError>,
+}
+
+impl IngestionErrorLog {
+    fn record(&mut self, error: IngestionError) {
+        self.recent.push_front(error);
+        self.recent.truncate(RECENT_INGESTION_ERRORS);
+    }
+}
+
+#[derive(Debug, Default)]
+struct Written {
+    blocks: usize,
+    coins: usize,
+    cat_tails: usize,
+    coin_spends: usize,
+    offer_coins: usize,
+    /// The height, header hash and timestamp of each block.
+    new_blocks: Vec<(u32, Bytes32, Option<u64>)>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Reindex {
+    pub height: u32,
+    pub coins_before: usize,
+    pub coins_after: usize,
+}
 
 pub struct Sync {
     db: Database,
     config: Config,
     sqlite: SqlitePool,
     rpc: FullNodeClient,
+    status: Arc<RwLock<SyncStatus>>,
+    reorgs: Arc<RwLock<ReorgLog>>,
+    errors: Arc<RwLock<IngestionErrorLog>>,
+    events: mpsc::UnboundedSender<Event>,
+    /// Serializes committing batches with reindexing.
+    ingestion: Mutex<()>,
 }
 
 impl Sync {
-    pub fn new(db: Database, config: Config, sqlite: SqlitePool, rpc: FullNodeClient) -> Self {
+    pub fn new(
+        db: Database,
+        config: Config,
+        sqlite: SqlitePool,
+        rpc: FullNodeClient,
+        status: Arc<RwLock<SyncStatus>>,
+        reorgs: Arc<RwLock<ReorgLog>>,
+        errors: Arc<RwLock<IngestionErrorLog>>,
+        events: mpsc::UnboundedSender<Event>,
+    ) -> Self {
         Self {
             db,
             config,
             sqlite,
             rpc,
+            status,
+            reorgs,
+            errors,
+            events,
+            ingestion: Mutex::new(()),
+        }
+    }
+
+    pub async fn start(self: Arc<Self>) -> Result<()> {
+        if let Some(peak_height) = self.db.peak_height()? {
+            let block = self.db.block(peak_height)?;
+
+            let mut status = self.status.write().unwrap();
+            status.peak_updated_at = Some(SystemTime::now());
+            status.indexed_peak = block.map(|block| (peak_height, block.header_hash));
+        }
+
+        let mut failures = 0;
+
+        loop {
+            // A failed batch is retried on the next poll rather than stopping
+            // the sync, so that a transient node or disk error doesn't leave
+            // the index behind until a restart.
+            // Each poll runs as its own task, so that a panic while indexing
+            // is logged and retried like any other failure instead of ending
+            // the sync.
+            let poll = tokio::spawn({
+                let sync = self.clone();
+                async move { sync.poll().await }
+            });
+
+            let result = match poll.await {
+                Ok(result) => result,
+                Err(error) => Err(anyhow::Error::from(error).context("Indexing panicked")),
+            };
+
+            if let Err(error) = result {
+                failures += 1;
+
+                let height = self
+                    .db
+                    .peak_height()
+                    .ok()
+                    .flatten()
+                    .map_or(0, |height| height + 1);
+
+                error!("Failed to index from height {}: {:?}", height, error);
+
+                self.errors.write().unwrap().record(IngestionError {
+                    height,
+                    error: format!("{error:#}"),
+                    timestamp: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map_or(0, |now| now.as_secs()),
+                });
+
+                tokio::time::sleep(self.retry_delay(failures)).await;
+                continue;
+            }
+
+            failures = 0;
+
+            tokio::time::sleep(POLL_INTERVAL).await;
         }
     }
 
-    pub async fn start(self) -> Result<()> {
-        let peak_height = self
-            .rpc
-            .get_blockchain_state()
-            .await?
-            .blockchain_state
-            .unwrap()
-            .peak
-            .height;
+    /// Doubles the poll interval for each consecutive failure up to
+    /// `node_retry_max_seconds`, then waits a random part of that, so a
+    /// restarting node isn't polled in lockstep.
+    fn retry_delay(&self, failures: u32) -> Duration {
+        let max = Duration::from_secs(self.config.node_retry_max_seconds).max(POLL_INTERVAL);
+        let backoff = POLL_INTERVAL
+            .saturating_mul(1 << failures.saturating_sub(1).min(16))
+            .min(max);
+
+        backoff.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+    }
+
+    /// Follows the node to its current peak, rolling back first if the
+    /// indexed chain was reorged out.
+    async fn poll(&self) -> Result<()> {
+        let blockchain_state = self.rpc.get_blockchain_state().await;
+
+        self.status.write().unwrap().node_connected = blockchain_state.is_ok();
+
+        // A node that's still starting up answers without its state, which is
+        // retried like any other failed poll.
+        let Some(blockchain_state) = blockchain_state?.blockchain_state else {
+            bail!("The node didn't return its blockchain state");
+        };
+
+        let peak_height = blockchain_state.peak.height;
+
+        let behind = peak_height
+            .saturating_add(1)
+            .saturating_sub(self.db.peak_height()?.map_or(0, |height| height + 1));
+
+        {
+            let mut status = self.status.write().unwrap();
+            status.target_height = Some(peak_height);
+            status.ingestion_mode = Some(if behind > self.config.batch_size {
+                IngestionMode::CatchingUp
+            } else {
+                IngestionMode::Polling
+            });
+        }
+
+        if let Some(fork_height) = self.find_fork().await? {
+            let _ingestion = self.ingestion.lock().await;
+
+            let depth = self.db.peak_height()?.unwrap_or(fork_height) - fork_height;
+
+            self.db.rollback_to(fork_height)?;
 
-        let mut sync_height = self.db.peak_height()?.unwrap_or(0);
+            self.reorgs.write().unwrap().record(ReorgRecord {
+                fork_height,
+                depth,
+                timestamp: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |now| now.as_secs()),
+            });
+
+            if let Some(block) = self.db.block(fork_height)? {
+                self.status.write().unwrap().indexed_peak = Some((fork_height, block.header_hash));
+
+                self.events
+                    .send(Event::Reorg {
+                        fork_height,
+                        depth,
+                        peak_hash: block.header_hash,
+                    })
+                    .ok();
+            }
+        }
+
+        self.sync_to(peak_height).await?;
+
+        self.status.write().unwrap().synced = true;
+
+        Ok(())
+    }
+
+    /// Finds the highest indexed block that is still in the node's main chain,
+    /// if any indexed blocks above it have been reorged out.
+    async fn find_fork(&self) -> Result<Option<u32>> {
+        let Some(peak_height) = self.db.peak_height()? else {
+            return Ok(None);
+        };
+
+        let mut height = peak_height;
+
+        loop {
+            let Some(block) = self.db.block(height)? else {
+                return Ok(None);
+            };
+
+            let row = sqlx::query(
+                "SELECT header_hash FROM full_blocks WHERE in_main_chain = 1 AND height = ?",
+            )
+            .bind(i64::from(height))
+            .fetch_optional(&self.sqlite)
+            .await?;
+
+            let main_chain_hash = row.map(|row| row.get::<Vec<u8>, _>("header_hash"));
+
+            if main_chain_hash.as_deref() == Some(block.header_hash.as_ref()) {
+                return Ok((height < peak_height).then_some(height));
+            }
+
+            if height == 0 {
+                return Ok(None);
+            }
+
+            info!("Block at height {} is no longer in the main chain", height);
+
+            height -= 1;
+        }
+    }
+
+    async fn sync_to(&self, peak_height: u32) -> Result<()> {
+        let mut sync_height = self.db.peak_height()?.map_or(0, |height| height + 1);
 
         let mut instant = Instant::now();
         let mut blocks_processed = 0;
 
-        while sync_height < peak_height {
+        while sync_height <= peak_height {
             if instant.elapsed().as_secs() > 60 {
                 debug!("Resetting start time");
                 instant = Instant::now();
                 blocks_processed = 0;
             }
 
-            let blocks_remaining = peak_height - sync_height;
+            let blocks_remaining = peak_height + 1 - sync_height;
             let blocks_per_second = blocks_processed as f32 / instant.elapsed().as_secs_f32();
 
             if blocks_per_second > 0.0 {
@@ -74,49 +367,16 @@ impl Sync {
                 );
             }
 
-            let response = sqlx::query(&format!(
-                "SELECT block FROM full_blocks WHERE in_main_chain = 1 AND height IN ({})",
-                (sync_height..sync_height + self.config.batch_size)
-                    .map(|h| h.to_string())
-                    .collect::<Vec<String>>()
-                    .join(",")
-            ))
-            .fetch_all(&self.sqlite)
-            .await?;
-
-            let blobs = response
-                .into_par_iter()
-                .map(|row| row.get::<Vec<u8>, _>("block"))
-                .collect::<Vec<_>>();
-
-            let blocks = parse_blocks(blobs);
+            let batch_start = Instant::now();
 
-            let mut refs = HashMap::new();
+            let (blocks, refs, batch_end) = self.fetch_commit(sync_height, peak_height).await?;
 
-            for block in &blocks {
-                for &ref_block in &block.transactions_generator_ref_list {
-                    if refs.contains_key(&ref_block) {
-                        continue;
-                    }
-
-                    let response = sqlx::query(&format!(
-                        "SELECT block FROM full_blocks WHERE in_main_chain = 1 AND height = {}",
-                        ref_block
-                    ))
-                    .fetch_one(&self.sqlite)
-                    .await?;
-
-                    let blob = response.get::<Vec<u8>, _>("block");
-                    let block =
-                        FullBlock::from_bytes(&decode_all(Cursor::new(blob)).unwrap()).unwrap();
-
-                    refs.insert(ref_block, block);
-                }
-            }
+            // Held until the batch is committed, so a reindex can't interleave.
+            let _ingestion = self.ingestion.lock().await;
 
             let process_start = Instant::now();
 
-            let mut insertions = process_blocks(blocks, refs);
+            let insertions = process_blocks(blocks, refs);
 
             let process_duration = process_start.elapsed();
 
@@ -124,60 +384,48 @@ impl Sync {
 
             let insert_start = Instant::now();
 
-            let mut block_inserts = 0;
-            let mut coin_inserts = 0;
-            let mut cat_tail_inserts = 0;
-            let mut coin_spend_inserts = 0;
+            let written = self.write_insertions(&mut tx, insertions)?;
 
-            insertions.sort();
+            tx.set_peak_height(batch_end)?;
 
-            for insertion in insertions {
-                match insertion {
-                    Insertion::Block { block, height } => {
-                        tx.put_block(height, &block)?;
+            tx.commit()?;
 
-                        block_inserts += 1;
-                    }
-                    Insertion::Coin { coin, coin_id } => {
-                        tx.put_coin(coin_id, &coin)?;
+            {
+                let now = SystemTime::now();
+                let rate = f64::from(batch_end + 1 - sync_height)
+                    / batch_start.elapsed().as_secs_f64().max(f64::EPSILON);
+
+                let mut status = self.status.write().unwrap();
+                status.peak_updated_at = Some(now);
+                status.last_block_ingested_at = Some(now);
+                status.blocks_per_second = Some(match status.blocks_per_second {
+                    Some(smoothed) => smoothed + RATE_SMOOTHING * (rate - smoothed),
+                    None => rate,
+                });
+            }
 
-                        coin_inserts += 1;
-                    }
-                    Insertion::CatTail { asset_id, tail } => {
-                        tx.put_tail(asset_id, &Bytes::new(tail))?;
+            let mut new_blocks = written.new_blocks;
+            new_blocks.sort_unstable();
 
-                        cat_tail_inserts += 1;
-                    }
-                    Insertion::CoinSpend {
-                        coin_id,
-                        puzzle_reveal,
-                        solution,
-                        spent_height,
-                    } => {
-                        tx.put_coin_spend(
-                            coin_id,
-                            &CoinSpendRow {
-                                spent_height,
-                                puzzle_reveal: Bytes::new(puzzle_reveal),
-                                solution: Bytes::new(solution),
-                            },
-                        )?;
-
-                        tx.add_to_spent_height_index(spent_height, coin_id)?;
-
-                        coin_spend_inserts += 1;
-                    }
-                }
+            if let Some(&(height, header_hash, _)) = new_blocks.last() {
+                self.status.write().unwrap().indexed_peak = Some((height, header_hash));
             }
 
-            tx.set_peak_height(sync_height)?;
-
-            tx.commit()?;
+            for (height, header_hash, timestamp) in new_blocks {
+                self.events
+                    .send(Event::Block {
+                        height,
+                        header_hash,
+                        timestamp,
+                        lag_seconds: timestamp.map(lag_seconds),
+                    })
+                    .ok();
+            }
 
             let insert_duration = insert_start.elapsed();
 
-            sync_height += self.config.batch_size;
-            blocks_processed += self.config.batch_size;
+            blocks_processed += batch_end + 1 - sync_height;
+            sync_height = batch_end + 1;
 
             debug!(
                 "{} blocks processed in {:?}, with an average of {} per batch",
@@ -193,13 +441,276 @@ impl Sync {
             );
 
             debug!(
-                "{} blocks, {} coins, {} tails, {} spends",
-                block_inserts, coin_inserts, cat_tail_inserts, coin_spend_inserts
+                "{} blocks, {} coins, {} tails, {} spends, {} offer coins",
+                written.blocks,
+                written.coins,
+                written.cat_tails,
+                written.coin_spends,
+                written.offer_coins
             );
 
-            debug!("Synced to height {}\n", sync_height);
+            debug!("Synced to height {}\n", batch_end);
         }
 
         Ok(())
     }
+
+    /// Fetches `batch_size` blocks at a time from `start` until there are
+    /// `commit_blocks` of them or `commit_interval_ms` has passed, returning
+    /// them with the height of the last one. They're all processed and written
+    /// together, so spends of coins created earlier in the commit are matched
+    /// up in memory. Nothing is written if the process stops before then, and
+    /// syncing picks up after the last committed peak.
+    async fn fetch_commit(
+        &self,
+        start: u32,
+        peak_height: u32,
+    ) -> Result<(Vec<FullBlock>, HashMap<u32, FullBlock>, u32)> {
+        let started = Instant::now();
+        let interval = Duration::from_millis(self.config.commit_interval_ms);
+        let commit_blocks = self.config.commit_blocks.max(self.config.batch_size);
+
+        let mut blocks = Vec::new();
+        let mut refs = HashMap::new();
+        let mut height = start;
+
+        loop {
+            let end = (height + self.config.batch_size - 1).min(peak_height);
+
+            let (fetched, fetched_refs) = self.fetch_blocks(height, end).await?;
+            blocks.extend(fetched);
+            refs.extend(fetched_refs);
+
+            height = end + 1;
+
+            if height > peak_height
+                || height - start >= commit_blocks
+                || (!interval.is_zero() && started.elapsed() >= interval)
+            {
+                return Ok((blocks, refs, end));
+            }
+        }
+    }
+
+    /// Reads the main chain blocks at heights `start..=end` from the node's
+    /// database, along with the blocks their generators refer to.
+    async fn fetch_blocks(
+        &self,
+        start: u32,
+        end: u32,
+    ) -> Result<(Vec<FullBlock>, HashMap<u32, FullBlock>)> {
+        let response = sqlx::query(&format!(
+            "SELECT block FROM full_blocks WHERE in_main_chain = 1 AND height IN ({})",
+            (start..=end)
+                .map(|h| h.to_string())
+                .collect::<Vec<String>>()
+                .join(",")
+        ))
+        .fetch_all(&self.sqlite)
+        .await?;
+
+        let blobs = response
+            .into_par_iter()
+            .map(|row| row.get::<Vec<u8>, _>("block"))
+            .collect::<Vec<_>>();
+
+        let blocks = parse_blocks(blobs)?;
+
+        let mut refs = HashMap::new();
+
+        for block in &blocks {
+            for &ref_block in &block.transactions_generator_ref_list {
+                if refs.contains_key(&ref_block) {
+                    continue;
+                }
+
+                let response = sqlx::query(&format!(
+                    "SELECT block FROM full_blocks WHERE in_main_chain = 1 AND height = {}",
+                    ref_block
+                ))
+                .fetch_one(&self.sqlite)
+                .await?;
+
+                let blob = response.get::<Vec<u8>, _>("block");
+                let block = parse_block(blob)
+                    .with_context(|| format!("Block {ref_block} couldn't be decoded"))?;
+
+                refs.insert(ref_block, block);
+            }
+        }
+
+        Ok((blocks, refs))
+    }
+
+    /// Adds the processed blocks to the transaction, without moving the peak.
+    fn write_insertions(
+        &self,
+        tx: &mut Transaction<'_>,
+        mut insertions: Vec<Insertion>,
+    ) -> Result<Written> {
+        let mut written = Written::default();
+
+        insertions.sort();
+
+        // Spent heights are denormalized onto coin rows. Coins created in
+        // this batch get theirs as they're written, and the rest are
+        // updated after the loop.
+        let mut spent_heights: HashMap<Bytes32, u32> = insertions
+            .iter()
+            .filter_map(|insertion| match insertion {
+                Insertion::CoinSpend {
+                    coin_id,
+                    spent_height,
+                    ..
+                } => Some((*coin_id, *spent_height)),
+                _ => None,
+            })
+            .collect();
+
+        for insertion in insertions {
+            match insertion {
+                Insertion::Block { block, height } => {
+                    tx.put_block(height, &block)?;
+
+                    written.new_blocks.push((
+                        height,
+                        block.header_hash,
+                        block
+                            .transaction_info
+                            .map(|transaction_info| transaction_info.timestamp),
+                    ));
+
+                    written.blocks += 1;
+                }
+                Insertion::Coin { mut coin, coin_id } => {
+                    coin.spent_height = spent_heights.remove(&coin_id).or(coin.spent_height);
+                    tx.put_coin(coin_id, &coin)?;
+
+                    written.coins += 1;
+                }
+                Insertion::CatTail { asset_id, tail } => {
+                    tx.put_tail(asset_id, &Bytes::new(tail))?;
+
+                    written.cat_tails += 1;
+                }
+                Insertion::CoinSpend {
+                    coin_id,
+                    puzzle_reveal,
+                    solution,
+                    spent_height,
+                    mod_hash,
+                    offer_coins,
+                    singleton_name,
+                } => {
+                    written.offer_coins += offer_coins.len();
+
+                    tx.put_coin_spend(
+                        coin_id,
+                        &CoinSpendRow {
+                            spent_height,
+                            puzzle_reveal: Bytes::new(puzzle_reveal),
+                            solution: Bytes::new(solution),
+                            mod_hash: Some(mod_hash),
+                            offer_coins,
+                            singleton_name,
+                        },
+                    )?;
+
+                    tx.add_to_spent_height_index(spent_height, coin_id)?;
+
+                    written.coin_spends += 1;
+                }
+            }
+        }
+
+        for (coin_id, spent_height) in spent_heights {
+            if let Some(coin) = self.db.coin(coin_id)? {
+                tx.set_spent_height(coin_id, &coin, Some(spent_height))?;
+            }
+        }
+
+        Ok(written)
+    }
+
+    /// Re-reads an indexed block from the node and rewrites its block row,
+    /// the coins created and spends made in it, and their index entries, for
+    /// heights that were indexed incorrectly. The block must still be the one
+    /// in the node's main chain, since a replaced block is a reorg instead.
+    ///
+    /// CAT tail entries are only added, like they are during a rollback, so
+    /// stale ones are left in place.
+    pub async fn reindex(&self, height: u32) -> Result<Reindex> {
+        let (blocks, refs) = self.fetch_blocks(height, height).await?;
+
+        let _ingestion = self.ingestion.lock().await;
+
+        let Some(old_block) = self.db.block(height)? else {
+            bail!("Block {height} hasn't been indexed");
+        };
+
+        let Some(block) = blocks.first() else {
+            bail!("Block {height} isn't in the node's main chain");
+        };
+
+        if block.header_hash() != old_block.header_hash {
+            bail!("Block {height} was replaced in the node's main chain, so it'll be rolled back");
+        }
+
+        let created = self.db.coins_by_created_height(height)?;
+        let coins_before = created.len();
+
+        let mut tx = self.db.transaction();
+
+        // The stale entries are deleted first, since the last write to a key
+        // in a batch wins.
+        for coin_id in self.db.coins_by_spent_height(height)? {
+            if let Some(spend) = self.db.coin_spend(coin_id)? {
+                tx.delete_coin_spend(coin_id, &spend)?;
+            }
+
+            if let Some(coin) = self
+                .db
+                .coin(coin_id)?
+                .filter(|coin| coin.created_height < height)
+            {
+                tx.set_spent_height(coin_id, &coin, None)?;
+            }
+        }
+
+        for coin_id in created {
+            if let Some(coin) = self.db.coin(coin_id)? {
+                tx.delete_coin(coin_id, &coin)?;
+            }
+        }
+
+        tx.delete_block(height, &old_block)?;
+
+        let mut insertions = process_blocks(blocks, refs);
+
+        // Coins spent above this height keep those spends.
+        for insertion in &mut insertions {
+            if let Insertion::Coin { coin, coin_id } = insertion {
+                coin.spent_height = self
+                    .db
+                    .coin_spend(*coin_id)?
+                    .map(|spend| spend.spent_height)
+                    .filter(|&spent_height| spent_height != height);
+            }
+        }
+
+        let written = self.write_insertions(&mut tx, insertions)?;
+
+        tx.commit()?;
+
+        info!(
+            "Reindexed block {} with {} coins, which had {} before",
+            height, written.coins, coins_before
+        );
+
+        Ok(Reindex {
+            height,
+            coins_before,
+            coins_after: written.coins,
+        })
+    }
 }